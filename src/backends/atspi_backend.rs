@@ -1,15 +1,19 @@
 #![cfg(feature = "atspi-backend")]
 use crate::backends::{Backend, BackendResult};
-use crate::config::{Config, OverlayTarget};
-use crate::hints::Child;
+use crate::config::{CaretPlacement, Config, OverlayTarget};
+use crate::coords::intersect;
+use crate::hints::{Child, normalize_extents};
 use crate::window_system::WindowSystem;
 use anyhow::{Result, anyhow};
 
 use atspi::connection::AccessibilityConnection;
 use atspi::proxy::accessible::AccessibleProxy;
 use atspi::proxy::component::ComponentProxy;
-use atspi::{CoordType, Role, State};
+use atspi::proxy::text::TextProxy;
+use atspi::{CoordType, Role, ScrollType, State, StateSet};
 use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use std::time::Instant;
 use tokio::runtime::Runtime;
 use zbus::zvariant::OwnedObjectPath;
 
@@ -28,8 +32,13 @@ impl AtspiBackend {
         }
     }
 
-    async fn collect_children(&self) -> Result<(Vec<Child>, Option<(i32, i32, i32, i32)>)> {
-        let conn = AccessibilityConnection::new().await?;
+    async fn collect_children(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<(Vec<Child>, Option<(i32, i32, i32, i32)>, bool)> {
+        let conn = AccessibilityConnection::new()
+            .await
+            .map_err(a11y_bus_unavailable)?;
 
         let bus = conn.connection();
 
@@ -40,12 +49,45 @@ impl AtspiBackend {
             .build()
             .await?;
 
+        if self.cfg.hints.focused_only {
+            return self.collect_focused(&root, bus).await;
+        }
+
         let mut out = Vec::new();
         let mut focus_extents: Option<(i32, i32, i32, i32)> = None;
+        let mut partial;
+
+        // An open menu/dropdown is usually its own top-level window that
+        // find_focused_window skips, so hint it alone when present.
+        if self.cfg.overlay_target == OverlayTarget::Window
+            && let Some(popup_path) = self.find_open_popup(&root, bus).await
+        {
+            log::info!("atspi backend: hinting open popup {}", popup_path.as_str());
+            partial = self
+                .walk_iterative(popup_path, &mut out, bus, None, deadline)
+                .await?;
+            if !out.is_empty() {
+                // Cover the popup items plus the window they belong to
+                let window = self
+                    .find_focused_window(&root, bus)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|(_, extents)| extents);
+                let extents = out
+                    .iter()
+                    .map(|c| (c.absolute_x, c.absolute_y, c.width, c.height))
+                    .chain(window)
+                    .reduce(union_extents);
+                return Ok((out, extents, partial));
+            }
+        }
+
         if self.cfg.overlay_target == OverlayTarget::Window {
             if let Some((focused_path, extents)) = self.find_focused_window(&root, bus).await? {
                 focus_extents = Some(extents);
-                self.walk_iterative(focused_path, &mut out, bus, focus_extents)
+                partial = self
+                    .walk_iterative(focused_path, &mut out, bus, focus_extents, deadline)
                     .await?;
             } else {
                 log::warn!(
@@ -64,52 +106,119 @@ impl AtspiBackend {
 
                 if let Some(extents) = fallback_extents {
                     focus_extents = Some(extents);
-                    self.walk_iterative(
-                        root.inner().path().to_owned().into(),
-                        &mut out,
-                        bus,
-                        focus_extents,
-                    )
-                    .await?;
+                    partial = self
+                        .walk_iterative(
+                            root.inner().path().to_owned().into(),
+                            &mut out,
+                            bus,
+                            focus_extents,
+                            deadline,
+                        )
+                        .await?;
                 } else {
                     log::warn!("atspi backend: xdotool fallback failed; falling back to full tree");
-                    self.walk_iterative(
-                        root.inner().path().to_owned().into(),
-                        &mut out,
-                        bus,
-                        focus_extents,
-                    )
-                    .await?;
+                    partial = self
+                        .walk_iterative(
+                            root.inner().path().to_owned().into(),
+                            &mut out,
+                            bus,
+                            focus_extents,
+                            deadline,
+                        )
+                        .await?;
                 }
             }
         } else {
-            self.walk_iterative(
-                root.inner().path().to_owned().into(),
-                &mut out,
-                bus,
-                focus_extents,
-            )
-            .await?;
+            partial = self
+                .walk_iterative(
+                    root.inner().path().to_owned().into(),
+                    &mut out,
+                    bus,
+                    focus_extents,
+                    deadline,
+                )
+                .await?;
         }
 
-        if out.is_empty() {
+        if out.is_empty() && !partial {
             // If no children found with focus filtering, try without filtering
-            self.walk_iterative(
-                root.inner().path().to_owned().into(),
-                &mut out,
-                bus,
-                None, // No focus filtering
-            )
-            .await?;
+            partial = self
+                .walk_iterative(
+                    root.inner().path().to_owned().into(),
+                    &mut out,
+                    bus,
+                    None, // No focus filtering
+                    deadline,
+                )
+                .await?;
         }
 
         if out.is_empty() {
             Err(anyhow!("atspi backend found zero children"))
         } else {
-            Ok((out, focus_extents))
+            Ok((out, focus_extents, partial))
         }
     }
 
+    /// Find a showing popup menu or expanded combo box anywhere in the
+    /// accessible tree. Hidden subtrees are pruned to keep this cheap.
+    async fn find_open_popup(
+        &self,
+        root: &AccessibleProxy<'_>,
+        bus: &zbus::Connection,
+    ) -> Option<OwnedObjectPath> {
+        const MAX_POPUP_DEPTH: usize = 8;
+
+        let mut level: Vec<OwnedObjectPath> = root
+            .get_children()
+            .await
+            .ok()?
+            .into_iter()
+            .map(|c| c.path)
+            .collect();
+        let mut depth = 0;
+
+        while !level.is_empty() && depth < MAX_POPUP_DEPTH {
+            let probes = level.iter().map(|path| async move {
+                let accessible = AccessibleProxy::builder(bus)
+                    .path(path.clone())
+                    .ok()?
+                    .build()
+                    .await
+                    .ok()?;
+                let state = accessible.get_state().await.unwrap_or_default();
+                // Applications aren't Showing themselves; below them prune hidden nodes
+                if depth > 0 && !state.contains(State::Showing) {
+                    return None;
+                }
+                let role = accessible.get_role().await.unwrap_or(Role::Invalid);
+                let children: Vec<OwnedObjectPath> = accessible
+                    .get_children()
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|c| c.path)
+                    .collect();
+                let open = match popup_open(role, state) {
+                    Some(open) => open,
+                    None => any_showing(bus, &children).await,
+                };
+                Some((path.clone(), open, children))
+            });
+
+            let mut next = Vec::new();
+            for (path, open, children) in self.join_limited(probes).await.into_iter().flatten() {
+                if open {
+                    return Some(path);
+                }
+                next.extend(children);
+            }
+            level = next;
+            depth += 1;
+        }
+        None
+    }
+
     async fn find_focused_window(
         &self,
         root: &AccessibleProxy<'_>,
@@ -122,23 +231,39 @@ impl AtspiBackend {
 
         // Optimization: Check all apps in parallel to find the active one
         let state_futures = children_refs.iter().map(|child_ref| async move {
-            if let Ok(proxy) = AccessibleProxy::builder(bus).path(child_ref.path.clone()) {
-                if let Ok(proxy) = proxy.build().await {
-                    if let Ok(state) = proxy.get_state().await {
-                        return Some((child_ref.path.clone(), state));
-                    }
-                }
+            if let Ok(proxy) = AccessibleProxy::builder(bus).path(child_ref.path.clone())
+                && let Ok(proxy) = proxy.build().await
+                && let Ok(state) = proxy.get_state().await
+            {
+                return Some((child_ref.path.clone(), state));
             }
             None
         });
 
-        let states = join_all(state_futures).await;
-        let active_app = states
+        let states = self.join_limited(state_futures).await;
+        let active_apps: Vec<OwnedObjectPath> = states
             .into_iter()
             .flatten()
-            .find(|(_, s)| s.contains(State::Active));
+            .filter(|(_, s)| s.contains(State::Active))
+            .map(|(path, _)| path)
+            .collect();
 
-        let mut stack: Vec<OwnedObjectPath> = if let Some((path, _)) = active_app {
+        // Some compositors mark several apps Active, others none; let the
+        // compositor's idea of the focused window decide between candidates.
+        let candidates: Vec<OwnedObjectPath> = if active_apps.is_empty() {
+            children_refs.iter().map(|c| c.path.clone()).collect()
+        } else {
+            active_apps.clone()
+        };
+        let active_app = if candidates.len() > 1 {
+            self.match_compositor_window(bus, &candidates)
+                .await
+                .or_else(|| active_apps.first().cloned())
+        } else {
+            active_apps.first().cloned()
+        };
+
+        let mut stack: Vec<OwnedObjectPath> = if let Some(path) = active_app {
             vec![path]
         } else {
             children_refs.into_iter().map(|c| c.path).collect()
@@ -173,10 +298,9 @@ impl AtspiBackend {
                     .path(path.clone())?
                     .build()
                     .await
+                    && let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await
                 {
-                    if let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await {
-                        return Ok(Some((path, (x, y, w, h))));
-                    }
+                    return Ok(Some((path, (x, y, w, h))));
                 }
                 return Ok(Some((path, (0, 0, 0, 0))));
             }
@@ -185,16 +309,15 @@ impl AtspiBackend {
                 focused_node = Some(path.clone());
             }
 
-            if windowish && first_window.is_none() {
-                if let Ok(component) = ComponentProxy::builder(bus)
+            if windowish
+                && first_window.is_none()
+                && let Ok(component) = ComponentProxy::builder(bus)
                     .path(path.clone())?
                     .build()
                     .await
-                {
-                    if let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await {
-                        first_window = Some((path.clone(), (x, y, w, h)));
-                    }
-                }
+                && let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await
+            {
+                first_window = Some((path.clone(), (x, y, w, h)));
             }
 
             if let Ok(children) = accessible.get_children().await {
@@ -226,10 +349,9 @@ impl AtspiBackend {
                         .path(current_path.clone())?
                         .build()
                         .await
+                        && let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await
                     {
-                        if let Ok((x, y, w, h)) = component.get_extents(CoordType::Screen).await {
-                            return Ok(Some((current_path, (x, y, w, h))));
-                        }
+                        return Ok(Some((current_path, (x, y, w, h))));
                     }
                     return Ok(Some((current_path, (0, 0, 0, 0))));
                 }
@@ -246,14 +368,214 @@ impl AtspiBackend {
         Ok(first_window)
     }
 
+    /// The app among `apps` owning the top-level window closest to the
+    /// compositor's focused-window geometry, if the compositor reports one.
+    async fn match_compositor_window(
+        &self,
+        bus: &zbus::Connection,
+        apps: &[OwnedObjectPath],
+    ) -> Option<OwnedObjectPath> {
+        let target = if self.window_system.window_system_type
+            == crate::window_system::WindowSystemType::Wayland
+        {
+            self.window_system
+                .get_active_window_geometry_wayland()
+                .or_else(|| self.window_system.get_active_window_geometry_x11())
+        } else {
+            self.window_system.get_active_window_geometry_x11()
+        }?;
+
+        let windows = self
+            .join_limited(apps.iter().map(|app| async move {
+                let accessible = AccessibleProxy::builder(bus)
+                    .path(app.clone())
+                    .ok()?
+                    .build()
+                    .await
+                    .ok()?;
+                let mut extents = Vec::new();
+                for window in accessible.get_children().await.ok()? {
+                    let Ok(builder) = ComponentProxy::builder(bus).path(window.path) else {
+                        continue;
+                    };
+                    let Ok(component) = builder.build().await else {
+                        continue;
+                    };
+                    if let Ok(window) = component.get_extents(CoordType::Screen).await {
+                        extents.push(window);
+                    }
+                }
+                Some((app.clone(), extents))
+            }))
+            .await;
+
+        let (app, distance) = closest_app(target, windows.into_iter().flatten())?;
+        log::info!(
+            "atspi backend: {} candidate apps; picked {} (geometry off by {}px)",
+            apps.len(),
+            app.as_str(),
+            distance
+        );
+        Some(app)
+    }
+
+    /// The focused element of the focused window and its siblings, focused one
+    /// first, for `hints.focused_only`.
+    async fn collect_focused(
+        &self,
+        root: &AccessibleProxy<'_>,
+        bus: &zbus::Connection,
+    ) -> Result<(Vec<Child>, Option<(i32, i32, i32, i32)>, bool)> {
+        let (window, extents) = self
+            .find_focused_window(root, bus)
+            .await?
+            .ok_or_else(|| anyhow!("atspi backend: no focused window"))?;
+        let focused = self
+            .find_focused_element(window, bus)
+            .await
+            .ok_or_else(|| anyhow!("atspi backend: nothing has focus in the focused window"))?;
+
+        let parent = AccessibleProxy::builder(bus)
+            .path(focused.clone())?
+            .build()
+            .await?
+            .parent()
+            .await?;
+        let mut elements = match AccessibleProxy::builder(bus)
+            .path(parent.path)?
+            .build()
+            .await
+        {
+            Ok(parent) => parent
+                .get_children()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| c.path)
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if !elements.contains(&focused) {
+            elements.push(focused.clone());
+        }
+
+        let probes = elements
+            .into_iter()
+            .map(|path| async move { self.element_child(bus, path).await });
+        let out: Vec<Child> = self
+            .join_limited(probes)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+        Ok((focused_first(out, focused.as_str())?, Some(extents), false))
+    }
+
+    /// First Showing element below `window` with the Focused state, level by level.
+    async fn find_focused_element(
+        &self,
+        window: OwnedObjectPath,
+        bus: &zbus::Connection,
+    ) -> Option<OwnedObjectPath> {
+        const MAX_FOCUS_DEPTH: usize = 50;
+
+        let mut level = vec![window];
+        let mut depth = 0;
+        while !level.is_empty() && depth < MAX_FOCUS_DEPTH {
+            let probes = level.iter().map(|path| async move {
+                let accessible = AccessibleProxy::builder(bus)
+                    .path(path.clone())
+                    .ok()?
+                    .build()
+                    .await
+                    .ok()?;
+                let state = accessible.get_state().await.unwrap_or_default();
+                if !state.contains(State::Showing) {
+                    return None;
+                }
+                let children: Vec<OwnedObjectPath> = accessible
+                    .get_children()
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|c| c.path)
+                    .collect();
+                Some((path.clone(), state.contains(State::Focused), children))
+            });
+
+            let mut next = Vec::new();
+            for (path, focused, children) in self.join_limited(probes).await.into_iter().flatten() {
+                // The window itself counts as focused on some toolkits
+                if focused && depth > 0 {
+                    return Some(path);
+                }
+                next.extend(children);
+            }
+            level = next;
+            depth += 1;
+        }
+        None
+    }
+
+    /// The element at `path` as a hint target, if it is showing and has extents.
+    async fn element_child(&self, bus: &zbus::Connection, path: OwnedObjectPath) -> Option<Child> {
+        let accessible = AccessibleProxy::builder(bus)
+            .path(path.clone())
+            .ok()?
+            .build()
+            .await
+            .ok()?;
+        let state = accessible.get_state().await.unwrap_or_default();
+        if !state.contains(State::Showing) {
+            return None;
+        }
+        let component = ComponentProxy::builder(bus)
+            .path(path.clone())
+            .ok()?
+            .build()
+            .await
+            .ok()?;
+        let (x, y, w, h) = normalize_extents(component.get_extents(CoordType::Screen).await.ok()?)?;
+        let role = accessible.get_role().await.ok().map(|r| format!("{r:?}"));
+        let name = if self.cfg.hints.show_names {
+            accessible.name().await.ok().filter(|n| !n.is_empty())
+        } else {
+            None
+        };
+        Some(Child {
+            absolute_x: x,
+            absolute_y: y,
+            width: w,
+            height: h,
+            role,
+            accessible_path: Some(path.to_string()),
+            name,
+            source: Some("atspi".into()),
+        })
+    }
+
+    /// Await `futures` with at most `atspi.max_concurrent` in flight, keeping
+    /// their order so labels stay stable between runs.
+    async fn join_limited<F: std::future::Future>(
+        &self,
+        futures: impl IntoIterator<Item = F>,
+    ) -> Vec<F::Output> {
+        let limit = self.cfg.backends.atspi.max_concurrent.max(1);
+        stream::iter(futures).buffered(limit).collect().await
+    }
+
     async fn walk_iterative(
         &self,
         start_path: OwnedObjectPath,
         out: &mut Vec<Child>,
         bus: &zbus::Connection,
         focus_extents: Option<(i32, i32, i32, i32)>,
-    ) -> Result<()> {
-        let mut current_level = vec![start_path];
+        deadline: Option<Instant>,
+    ) -> Result<bool> {
+        let visible_only = self.cfg.backends.atspi.visible_only;
+        let show_names = self.cfg.hints.show_names;
+        // Each path comes with the visible bounds of its nearest scroll container
+        let mut current_level: Vec<(OwnedObjectPath, Option<Rect>)> = vec![(start_path, None)];
         let mut visited = std::collections::HashSet::new(); // Restore cycle detection
 
         // Limit depth to avoid infinite loops or too deep traversal
@@ -264,54 +586,87 @@ impl AtspiBackend {
             depth += 1;
 
             // Filter out visited paths to prevent cycles
-            current_level.retain(|p| visited.insert(p.clone()));
+            current_level.retain(|(p, _)| visited.insert(p.clone()));
             if current_level.is_empty() {
                 break;
             }
 
             // Process current level in parallel
-            let futures = current_level.iter().map(|path| async move {
+            let futures = current_level.iter().map(|(path, clip)| async move {
                 let mut result_children = Vec::new();
                 let mut result_child = None;
+                let mut child_clip = *clip;
 
                 // Skip null path explicitly
                 if path.as_str() == "/org/a11y/atspi/null" {
-                    return (result_child, result_children);
+                    return (result_child, result_children, child_clip);
                 }
 
                 // Try to build accessible proxy
-                if let Ok(proxy) = AccessibleProxy::builder(bus).path(path.clone()) {
-                    if let Ok(proxy) = proxy.build().await {
-                        // Get children
-                        if let Ok(children) = proxy.get_children().await {
-                            result_children = children.into_iter().map(|c| c.path).collect();
-                        }
+                if let Ok(proxy) = AccessibleProxy::builder(bus).path(path.clone())
+                    && let Ok(proxy) = proxy.build().await
+                {
+                    // Get children
+                    if let Ok(children) = proxy.get_children().await {
+                        result_children = children.into_iter().map(|c| c.path).collect();
+                    }
 
-                        // Get extents (via Component interface)
-                        // Not all accessibles implement Component, so this might fail/return error, which is fine
-                        if let Ok(component) = ComponentProxy::builder(bus).path(path.clone()) {
-                            if let Ok(component) = component.build().await {
-                                if let Ok((x, y, w, h)) =
-                                    component.get_extents(CoordType::Screen).await
-                                {
-                                    if w > 0 && h > 0 {
-                                        result_child = Some((x, y, w, h));
-                                    }
+                    // Get extents (via Component interface)
+                    // Not all accessibles implement Component, so this might fail/return error, which is fine
+                    if let Ok(component) = ComponentProxy::builder(bus).path(path.clone())
+                        && let Ok(component) = component.build().await
+                    {
+                        // Broken apps report negative sizes; flip or skip them
+                        if let Ok(extents) = component.get_extents(CoordType::Screen).await
+                            && let Some((x, y, w, h)) = normalize_extents(extents)
+                        {
+                            // Hidden (e.g. a collapsed page) or scrolled out of
+                            // view: neither it nor anything below gets a hint
+                            if visible_only {
+                                let state = proxy.get_state().await.unwrap_or_default();
+                                if is_hidden(state, (x, y, w, h), *clip) {
+                                    return (None, Vec::new(), child_clip);
                                 }
                             }
+                            let role = proxy.get_role().await.ok();
+                            child_clip = clip_below(role, (x, y, w, h), *clip);
+                            let role = role.map(|r| format!("{r:?}"));
+                            let name = if show_names {
+                                proxy.name().await.ok().filter(|n| !n.is_empty())
+                            } else {
+                                None
+                            };
+                            result_child = Some((x, y, w, h, role, name, path.to_string()));
                         }
                     }
                 }
-                (result_child, result_children)
+                (result_child, result_children, child_clip)
             });
 
-            let results = join_all(futures).await;
+            // Stop at the deadline and keep whatever earlier levels produced.
+            let results = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline.into(), self.join_limited(futures)).await
+                    {
+                        Ok(results) => results,
+                        Err(_) => {
+                            log::warn!(
+                                "atspi backend: collection deadline hit at depth {}; returning {} children",
+                                depth,
+                                out.len()
+                            );
+                            return Ok(true);
+                        }
+                    }
+                }
+                None => self.join_limited(futures).await,
+            };
 
             current_level = Vec::new();
 
-            for (child_opt, children_paths) in results {
-                if let Some((x, y, w, h)) = child_opt {
-                    let inside_focus = focus_extents.map_or(true, |(fx, fy, fw, fh)| {
+            for (child_opt, children_paths, clip) in results {
+                if let Some((x, y, w, h, role, name, path)) = child_opt {
+                    let inside_focus = focus_extents.is_none_or(|(fx, fy, fw, fh)| {
                         x >= fx && y >= fy && (x + w) <= (fx + fw) && (y + h) <= (fy + fh)
                     });
                     if inside_focus {
@@ -320,13 +675,149 @@ impl AtspiBackend {
                             absolute_y: y,
                             width: w,
                             height: h,
+                            role,
+                            accessible_path: Some(path),
+                            name,
+                            source: Some("atspi".into()),
                         });
                     }
                 }
-                current_level.extend(children_paths);
+                current_level.extend(children_paths.into_iter().map(|path| (path, clip)));
             }
         }
-        Ok(())
+        Ok(false)
+    }
+}
+
+/// Screen rectangle as (x, y, width, height).
+type Rect = (i32, i32, i32, i32);
+
+/// Whether an element at `extents` shows at least partly inside the visible
+/// bounds `clip` of its scroll container (always, outside any container).
+fn is_visible_in(extents: Rect, clip: Option<Rect>) -> bool {
+    clip.is_none_or(|clip| intersect(extents, clip).is_some())
+}
+
+/// `hints.focused_only` hints: the element at `focused` first, then its
+/// siblings in tree order.
+fn focused_first(mut elements: Vec<Child>, focused: &str) -> Result<Vec<Child>> {
+    // Stable, so the siblings keep their order after the focused element
+    elements.sort_by_key(|c| c.accessible_path.as_deref() != Some(focused));
+    if elements.is_empty() {
+        return Err(anyhow!("atspi backend: the focused element has no extents"));
+    }
+    Ok(elements)
+}
+
+/// Whether an element at `extents` with `state` is skipped under
+/// `backends.atspi.visible_only`: not Showing (e.g. on a collapsed page), or
+/// scrolled out of its container's visible bounds `clip`.
+fn is_hidden(state: StateSet, extents: Rect, clip: Option<Rect>) -> bool {
+    !state.contains(State::Showing) || !is_visible_in(extents, clip)
+}
+
+/// The visible bounds for the children of an element with `role` at `extents`,
+/// inside its own container's `clip`: scroll containers narrow them to
+/// themselves.
+fn clip_below(role: Option<Role>, extents: Rect, clip: Option<Rect>) -> Option<Rect> {
+    match role {
+        Some(Role::ScrollPane | Role::Viewport) => Some(match clip {
+            Some(outer) => intersect(extents, outer).unwrap_or((extents.0, extents.1, 0, 0)),
+            None => extents,
+        }),
+        _ => clip,
+    }
+}
+
+/// The app whose top-level window extents come closest to `target`, summing
+/// the differences in position and size, with that distance. Earlier apps win
+/// ties; apps without windows are never picked.
+fn closest_app<T>(
+    target: Rect,
+    apps: impl IntoIterator<Item = (T, Vec<Rect>)>,
+) -> Option<(T, i32)> {
+    let (tx, ty, tw, th) = target;
+    apps.into_iter()
+        .filter_map(|(app, windows)| {
+            windows
+                .into_iter()
+                .map(|(x, y, w, h)| {
+                    (x - tx).abs() + (y - ty).abs() + (w - tw).abs() + (h - th).abs()
+                })
+                .min()
+                .map(|distance| (app, distance))
+        })
+        .min_by_key(|(_, distance)| *distance)
+}
+
+/// Whether a node with `role` and `state` is an open popup menu or dropdown;
+/// `None` for a collapsed menu, which is open only if one of its items is
+/// Showing.
+fn popup_open(role: Role, state: StateSet) -> Option<bool> {
+    match role {
+        Role::Menu | Role::PopupMenu if state.contains(State::Expanded) => Some(true),
+        // Closed menus keep their items around but not Showing
+        Role::Menu | Role::PopupMenu => None,
+        Role::ComboBox => Some(state.contains(State::Expanded)),
+        _ => Some(false),
+    }
+}
+
+/// Whether any of `paths` currently has the Showing state.
+async fn any_showing(bus: &zbus::Connection, paths: &[OwnedObjectPath]) -> bool {
+    let states = join_all(paths.iter().map(|path| async move {
+        AccessibleProxy::builder(bus)
+            .path(path.clone())
+            .ok()?
+            .build()
+            .await
+            .ok()?
+            .get_state()
+            .await
+            .ok()
+    }))
+    .await;
+    states
+        .into_iter()
+        .flatten()
+        .any(|s| s.contains(State::Showing))
+}
+
+/// Smallest rectangle containing both `a` and `b` (x, y, width, height).
+fn union_extents(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+    let x = a.0.min(b.0);
+    let y = a.1.min(b.1);
+    let right = (a.0 + a.2).max(b.0 + b.2);
+    let bottom = (a.1 + a.3).max(b.1 + b.3);
+    (x, y, right - x, bottom - y)
+}
+
+impl AtspiBackend {
+    /// Elements below the one at `path` (not that element itself), for the
+    /// second stage of `hints.scoped` hinting.
+    pub fn get_subtree(&mut self, path: &str, deadline: Option<Instant>) -> Result<BackendResult> {
+        let (mut children, partial) = self.rt.block_on(async {
+            let conn = AccessibilityConnection::new()
+                .await
+                .map_err(a11y_bus_unavailable)?;
+            let mut out = Vec::new();
+            let partial = self
+                .walk_iterative(
+                    OwnedObjectPath::try_from(path)?,
+                    &mut out,
+                    conn.connection(),
+                    None,
+                    deadline,
+                )
+                .await?;
+            Ok::<_, anyhow::Error>((out, partial))
+        })?;
+        children.retain(|c| c.accessible_path.as_deref() != Some(path));
+        Ok(BackendResult {
+            children,
+            focus_extents: None,
+            partial,
+        })
     }
 }
 
@@ -335,11 +826,222 @@ impl Backend for AtspiBackend {
         "atspi"
     }
 
-    fn get_children(&mut self) -> Result<BackendResult> {
-        let (children, focus_extents) = self.rt.block_on(self.collect_children())?;
+    fn get_children(&mut self, deadline: Option<Instant>) -> Result<BackendResult> {
+        let (children, focus_extents, partial) =
+            self.rt.block_on(self.collect_children(deadline))?;
         Ok(BackendResult {
             children,
             focus_extents,
+            partial,
         })
     }
 }
+
+/// Turn a failure to reach the accessibility bus into an actionable message. This
+/// is the usual state on minimal setups where nothing has enabled a11y yet.
+fn a11y_bus_unavailable(err: atspi::AtspiError) -> anyhow::Error {
+    anyhow!(
+        "accessibility bus unavailable ({err}); enable it with \
+         `gsettings set org.gnome.desktop.interface toolkit-accessibility true` \
+         and restart your apps"
+    )
+}
+
+/// Check that the accessibility bus answers, for `hintsx --doctor`.
+pub fn probe_bus(timeout: std::time::Duration) -> Result<()> {
+    let rt = Runtime::new()?;
+    rt.block_on(async {
+        tokio::time::timeout(timeout, AccessibilityConnection::new())
+            .await
+            .map_err(|_| anyhow!("accessibility bus did not answer within {timeout:?}"))?
+            .map_err(a11y_bus_unavailable)?;
+        Ok(())
+    })
+}
+
+/// Ask the application to scroll the element at `path` into view, wherever it
+/// sees fit within its viewport.
+pub fn scroll_into_view(path: &str) -> Result<()> {
+    let rt = Runtime::new()?;
+    rt.block_on(async {
+        let conn = AccessibilityConnection::new()
+            .await
+            .map_err(a11y_bus_unavailable)?;
+        let component = ComponentProxy::builder(conn.connection())
+            .path(OwnedObjectPath::try_from(path)?)?
+            .build()
+            .await?;
+        if !component.scroll_to(ScrollType::Anywhere).await? {
+            return Err(anyhow!("element refused to scroll into view"));
+        }
+        Ok(())
+    })
+}
+
+/// Roles whose elements take a text caret.
+pub const TEXT_ROLES: &[&str] = &["Entry", "Text", "PasswordText"];
+
+/// Move the caret of the text element at `path` to its start or end. Meant to
+/// run right after the focusing click, which leaves the caret wherever it landed.
+pub fn set_caret(path: &str, placement: CaretPlacement) -> Result<()> {
+    let rt = Runtime::new()?;
+    rt.block_on(async {
+        let conn = AccessibilityConnection::new()
+            .await
+            .map_err(a11y_bus_unavailable)?;
+        let text = TextProxy::builder(conn.connection())
+            .path(OwnedObjectPath::try_from(path)?)?
+            .build()
+            .await?;
+        let offset = match placement {
+            CaretPlacement::None => return Ok(()),
+            CaretPlacement::Start => 0,
+            CaretPlacement::End => text.character_count().await?,
+        };
+        if !text.set_caret_offset(offset).await? {
+            return Err(anyhow!("element refused caret offset {offset}"));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn join_limited_caps_the_futures_in_flight() {
+        let mut cfg = Config::default();
+        cfg.backends.atspi.max_concurrent = 3;
+        let backend = AtspiBackend::new(cfg, WindowSystem::detect("x11").unwrap());
+        let (in_flight, most) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let counting = |i: usize| {
+            let (in_flight, most) = (&in_flight, &most);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                // Stay pending for a few polls so the others get started
+                for _ in 0..(10 - i) {
+                    tokio::task::yield_now().await;
+                }
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        };
+        let done = backend
+            .rt
+            .block_on(backend.join_limited((0..10).map(counting)));
+        // Later futures finish first, yet the results keep their order
+        assert_eq!(done, (0..10).collect::<Vec<_>>());
+        assert_eq!(most.load(Ordering::SeqCst), 3);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn closest_app_matches_the_compositor_window_geometry() {
+        let target = (100, 50, 800, 600);
+        let apps = [
+            ("terminal", vec![(0, 0, 1920, 1080)]),
+            ("no windows", vec![]),
+            // Its second window is off by 20px; the tie with "twin" goes to the first
+            ("editor", vec![(0, 0, 400, 300), (110, 60, 800, 600)]),
+            ("twin", vec![(90, 40, 800, 600)]),
+        ];
+        assert_eq!(closest_app(target, apps.clone()), Some(("editor", 20)));
+        assert_eq!(
+            closest_app(target, apps[..2].to_vec()),
+            Some(("terminal", 100 + 50 + 1120 + 480))
+        );
+        assert_eq!(closest_app(target, [("no windows", vec![])]), None);
+    }
+
+    #[test]
+    fn focused_only_puts_the_focused_element_first() {
+        let element = |path: &str, y| Child {
+            absolute_x: 0,
+            absolute_y: y,
+            width: 200,
+            height: 20,
+            role: Some("Entry".into()),
+            accessible_path: Some(path.into()),
+            name: None,
+            source: Some("atspi".into()),
+        };
+        let paths = |children: &[Child]| {
+            children
+                .iter()
+                .map(|c| c.accessible_path.clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+        let form = vec![
+            element("/a11y/name", 0),
+            element("/a11y/email", 30),
+            element("/a11y/phone", 60),
+        ];
+        let hinted = focused_first(form, "/a11y/email").unwrap();
+        assert_eq!(paths(&hinted), ["/a11y/email", "/a11y/name", "/a11y/phone"]);
+
+        // Without readable siblings only the focused element is hinted
+        let alone = focused_first(vec![element("/a11y/email", 30)], "/a11y/email").unwrap();
+        assert_eq!(paths(&alone), ["/a11y/email"]);
+        assert!(focused_first(Vec::new(), "/a11y/email").is_err());
+    }
+
+    #[test]
+    fn unavailable_bus_error_says_how_to_enable_it() {
+        let err = a11y_bus_unavailable(atspi::AtspiError::Zbus(
+            "org.freedesktop.DBus.Error.ServiceUnknown".into(),
+        ))
+        .to_string();
+        assert!(err.starts_with("accessibility bus unavailable ("));
+        assert!(err.contains("ServiceUnknown"));
+        assert!(
+            err.contains("gsettings set org.gnome.desktop.interface toolkit-accessibility true")
+        );
+    }
+
+    #[test]
+    fn visible_only_skips_hidden_and_scrolled_out_elements() {
+        let showing = StateSet::new(State::Showing);
+        let element = (10, 500, 100, 20);
+        assert!(!is_hidden(showing, element, None));
+        // A collapsed page's widgets keep their extents but lose Showing
+        assert!(is_hidden(StateSet::empty(), element, None));
+        assert!(is_hidden(StateSet::new(State::Visible), element, None));
+
+        // A 400px tall scroll pane: the element sits below its visible part
+        let pane = (0, 0, 800, 400);
+        let clip = clip_below(Some(Role::ScrollPane), pane, None);
+        assert_eq!(clip, Some(pane));
+        assert!(is_hidden(showing, element, clip));
+        assert!(!is_hidden(showing, (10, 390, 100, 20), clip));
+        // Other containers pass their clip on unchanged
+        assert_eq!(clip_below(Some(Role::Panel), element, clip), clip);
+        assert_eq!(clip_below(None, element, None), None);
+        // Nested panes only show where both do
+        assert_eq!(
+            clip_below(Some(Role::Viewport), (0, 200, 800, 400), clip),
+            Some((0, 200, 800, 200))
+        );
+        assert_eq!(
+            clip_below(Some(Role::Viewport), (0, 600, 800, 400), clip),
+            Some((0, 600, 0, 0))
+        );
+    }
+
+    #[test]
+    fn popup_open_for_expanded_menus_and_dropdowns_only() {
+        let expanded = StateSet::new(State::Showing | State::Expanded);
+        let collapsed = StateSet::new(State::Showing);
+        assert_eq!(popup_open(Role::PopupMenu, expanded), Some(true));
+        assert_eq!(popup_open(Role::Menu, expanded), Some(true));
+        assert_eq!(popup_open(Role::ComboBox, expanded), Some(true));
+        assert_eq!(popup_open(Role::ComboBox, collapsed), Some(false));
+        // A collapsed menu is decided by its items
+        assert_eq!(popup_open(Role::Menu, collapsed), None);
+        assert_eq!(popup_open(Role::PopupMenu, StateSet::empty()), None);
+        assert_eq!(popup_open(Role::Button, expanded), Some(false));
+        assert_eq!(popup_open(Role::Frame, expanded), Some(false));
+    }
+}
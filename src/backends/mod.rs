@@ -1,26 +1,93 @@
 use crate::config::Config;
 use crate::hints::Child;
 use crate::window_system::WindowSystem;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 
 pub mod atspi_backend;
 #[cfg(feature = "opencv-backend")]
 pub mod opencv_backend;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackendResult {
     pub children: Vec<Child>,
     pub focus_extents: Option<(i32, i32, i32, i32)>,
+    /// Set when the backend stopped at the collection deadline before finishing.
+    pub partial: bool,
+}
+
+impl BackendResult {
+    /// Flip negative extents and drop children without a usable box, whichever
+    /// backend produced them, and tag untagged children with that backend.
+    fn sanitized(mut self, backend: &str) -> Self {
+        let before = self.children.len();
+        self.children = self
+            .children
+            .into_iter()
+            .filter_map(Child::normalized)
+            .map(|mut child| {
+                child.source.get_or_insert_with(|| backend.into());
+                child
+            })
+            .collect();
+        if self.children.len() < before {
+            log::warn!(
+                "backend {backend}: dropped {} children with empty or overflowing extents",
+                before - self.children.len()
+            );
+        }
+        self
+    }
+
+    /// `children` with positions relative to the focused window (unchanged when
+    /// there are no focus extents).
+    pub fn window_relative_children(&self) -> Vec<Child> {
+        let (wx, wy) = self.focus_extents.map_or((0, 0), |(x, y, _, _)| (x, y));
+        self.children
+            .iter()
+            .map(|child| child.translated(-wx, -wy))
+            .collect()
+    }
 }
 
 pub trait Backend {
     fn name(&self) -> &'static str;
-    fn get_children(&mut self) -> Result<BackendResult>;
+    /// Gather hintable elements. Backends that can stop early should return what
+    /// they have (with `partial` set) once `deadline` passes.
+    fn get_children(&mut self, deadline: Option<Instant>) -> Result<BackendResult>;
+}
+
+/// Lowercased backend names from a comma-separated `HINTSX_DISABLE_BACKENDS` value.
+fn disabled_backends(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
 }
 
 pub fn build_backends(cfg: &Config, window_system: &WindowSystem) -> Vec<Box<dyn Backend + Send>> {
+    // Debug override: HINTSX_DISABLE_BACKENDS=opencv,atspi drops backends for one run
+    let disabled = std::env::var("HINTSX_DISABLE_BACKENDS")
+        .map(|v| disabled_backends(&v))
+        .unwrap_or_default();
+    build_backends_without(cfg, window_system, &disabled)
+}
+
+/// The backends in `backends.enable` that this build has, minus the lowercased
+/// names in `disabled`.
+fn build_backends_without(
+    cfg: &Config,
+    window_system: &WindowSystem,
+    disabled: &[String],
+) -> Vec<Box<dyn Backend + Send>> {
     let mut list: Vec<Box<dyn Backend + Send>> = Vec::new();
     for name in &cfg.backends.enable {
+        if disabled.contains(&name.to_lowercase()) {
+            log::info!("backend {name} disabled via HINTSX_DISABLE_BACKENDS");
+            continue;
+        }
         match name.as_str() {
             "atspi" => {
                 list.push(Box::new(atspi_backend::AtspiBackend::new(
@@ -40,3 +107,232 @@ pub fn build_backends(cfg: &Config, window_system: &WindowSystem) -> Vec<Box<dyn
     }
     list
 }
+
+/// Run the enabled backends in order and return the first non-empty result
+/// together with the name of the backend that produced it. A `timeout` bounds the
+/// whole collection; backends still running at the deadline hand back a partial set.
+/// With `backends.show_all`, every backend runs and their results are combined.
+pub fn collect(
+    cfg: &Config,
+    window_system: &WindowSystem,
+    timeout: Option<Duration>,
+) -> Result<(&'static str, BackendResult)> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let backends = build_backends(cfg, window_system);
+    if cfg.backends.show_all {
+        return collect_all(backends, deadline);
+    }
+    collect_first(backends, deadline)
+}
+
+/// The first non-empty result of `backends`, tried in order.
+fn collect_first(
+    backends: Vec<Box<dyn Backend + Send>>,
+    deadline: Option<Instant>,
+) -> Result<(&'static str, BackendResult)> {
+    for mut backend in backends {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            log::warn!(
+                "collection deadline passed before backend {}",
+                backend.name()
+            );
+            break;
+        }
+        let t_backend = Instant::now();
+        match backend
+            .get_children(deadline)
+            .map(|result| result.sanitized(backend.name()))
+        {
+            Ok(result) if !result.children.is_empty() => {
+                log::debug!(
+                    "backend {} succeeded in {:?}",
+                    backend.name(),
+                    t_backend.elapsed()
+                );
+                if result.partial {
+                    log::warn!(
+                        "backend {} timed out; using {} partial children",
+                        backend.name(),
+                        result.children.len()
+                    );
+                }
+                return Ok((backend.name(), result));
+            }
+            Ok(_) => {
+                log::warn!(
+                    "backend {} returned zero children in {:?}",
+                    backend.name(),
+                    t_backend.elapsed()
+                );
+            }
+            Err(err) => {
+                log::warn!(
+                    "backend {} failed after {:?}: {err}",
+                    backend.name(),
+                    t_backend.elapsed()
+                );
+            }
+        }
+    }
+    Err(anyhow!(
+        "no children gathered from any backend; check accessibility setup"
+    ))
+}
+
+/// Every enabled backend's children in one result (each child keeps its
+/// `source` backend), with the first focus extents any backend reported.
+fn collect_all(
+    backends: Vec<Box<dyn Backend + Send>>,
+    deadline: Option<Instant>,
+) -> Result<(&'static str, BackendResult)> {
+    let mut combined = BackendResult {
+        children: Vec::new(),
+        focus_extents: None,
+        partial: false,
+    };
+    for mut backend in backends {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            log::warn!(
+                "collection deadline passed before backend {}",
+                backend.name()
+            );
+            combined.partial = true;
+            break;
+        }
+        let t_backend = Instant::now();
+        match backend
+            .get_children(deadline)
+            .map(|result| result.sanitized(backend.name()))
+        {
+            Ok(result) => {
+                log::debug!(
+                    "backend {} found {} children in {:?}",
+                    backend.name(),
+                    result.children.len(),
+                    t_backend.elapsed()
+                );
+                combined.focus_extents = combined.focus_extents.or(result.focus_extents);
+                combined.partial |= result.partial;
+                combined.children.extend(result.children);
+            }
+            Err(err) => {
+                log::warn!(
+                    "backend {} failed after {:?}: {err}",
+                    backend.name(),
+                    t_backend.elapsed()
+                );
+            }
+        }
+    }
+    if combined.children.is_empty() {
+        return Err(anyhow!(
+            "no children gathered from any backend; check accessibility setup"
+        ));
+    }
+    Ok(("all", combined))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backend that hands back `count` fixed children, or with `stall` waits out
+    /// the deadline and returns them as a partial set.
+    struct FakeBackend {
+        name: &'static str,
+        count: i32,
+        stall: bool,
+    }
+
+    impl Backend for FakeBackend {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn get_children(&mut self, deadline: Option<Instant>) -> Result<BackendResult> {
+            if self.stall
+                && let Some(deadline) = deadline
+            {
+                std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            }
+            let children = (0..self.count)
+                .map(|i| Child {
+                    absolute_x: i * 20,
+                    absolute_y: 0,
+                    width: 10,
+                    height: 10,
+                    role: None,
+                    accessible_path: None,
+                    name: None,
+                    source: None,
+                })
+                .collect();
+            Ok(BackendResult {
+                children,
+                focus_extents: None,
+                partial: self.stall,
+            })
+        }
+    }
+
+    fn backends(list: &[(&'static str, i32, bool)]) -> Vec<Box<dyn Backend + Send>> {
+        list.iter()
+            .map(|&(name, count, stall)| {
+                Box::new(FakeBackend { name, count, stall }) as Box<dyn Backend + Send>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disabled_backends_are_trimmed_and_lowercased() {
+        assert_eq!(disabled_backends(" OpenCV, atspi ,,"), ["opencv", "atspi"]);
+        assert!(disabled_backends("").is_empty());
+    }
+
+    #[test]
+    fn disabled_backends_are_left_out_of_the_build() {
+        let mut cfg = Config::default();
+        cfg.backends.enable = vec!["atspi".to_string(), "unknown".to_string()];
+        let window_system = WindowSystem::detect("x11").unwrap();
+        let names = |disabled: &str| {
+            build_backends_without(&cfg, &window_system, &disabled_backends(disabled))
+                .iter()
+                .map(|backend| backend.name())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(""), ["atspi"]);
+        assert_eq!(names("opencv"), ["atspi"]);
+        assert!(names(" ATSPI ").is_empty());
+    }
+
+    #[test]
+    fn deadline_returns_the_partial_set() {
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        let (name, result) =
+            collect_first(backends(&[("slow", 2, true), ("fast", 3, false)]), deadline).unwrap();
+        assert_eq!(name, "slow");
+        assert!(result.partial);
+        assert_eq!(result.children.len(), 2);
+
+        // With show_all the backends after the deadline are skipped, not waited on
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        let (_, result) =
+            collect_all(backends(&[("slow", 2, true), ("fast", 3, false)]), deadline).unwrap();
+        assert!(result.partial);
+        assert_eq!(result.children.len(), 2);
+    }
+
+    #[test]
+    fn show_all_tags_children_with_their_source() {
+        let (name, result) =
+            collect_all(backends(&[("atspi", 2, false), ("opencv", 1, false)]), None).unwrap();
+        assert_eq!(name, "all");
+        assert!(!result.partial);
+        let sources: Vec<_> = result
+            .children
+            .iter()
+            .map(|child| child.source.as_deref())
+            .collect();
+        assert_eq!(sources, [Some("atspi"), Some("atspi"), Some("opencv")]);
+    }
+}
@@ -1,49 +1,73 @@
 #![cfg(feature = "opencv-backend")]
 use crate::backends::{Backend, BackendResult};
-use crate::config::Config;
-use crate::hints::Child;
-use crate::window_system::WindowSystem;
+use crate::config::{Config, HintsStyle};
+use crate::consts::default_cache_dir;
+use crate::hints::{Child, DrawnHints, HintMap, normalize_extents};
+use crate::window_system::{WindowSystem, WindowSystemType};
 use anyhow::{Context, Result, anyhow};
 use opencv::core::{self, Point, Size};
 use opencv::imgcodecs;
 use opencv::imgproc;
 use opencv::prelude::*;
 use std::process::Command;
+use std::time::Instant;
 use tempfile::NamedTempFile;
 
+/// Pixel height of `FONT_HERSHEY_SIMPLEX` at scale 1, to size annotation labels
+/// like `hint_font_size`.
+const ANNOTATION_FONT_PX: f64 = 22.0;
+
 pub struct OpenCvBackend {
     cfg: Config,
     window_system: WindowSystem,
+    /// Hint boxes of an overlay still on screen (global coordinates)
+    drawn_hints: Vec<(i32, i32, i32, i32)>,
 }
 
 impl OpenCvBackend {
     pub fn new(cfg: Config, window_system: WindowSystem) -> Self {
-        Self { cfg, window_system }
+        Self {
+            cfg,
+            window_system,
+            drawn_hints: DrawnHints::load_live(),
+        }
     }
 
-    fn screenshot(&self) -> Result<Mat> {
+    /// Capture the screen, or only the focused window with `HINTSX_WINDOW_CAPTURE`.
+    /// Returns the image and where its top-left corner is on screen.
+    fn screenshot(&self) -> Result<(Mat, (i32, i32))> {
         // Only use window-specific capture if explicitly enabled (faster but may miss elements)
         if std::env::var("HINTSX_WINDOW_CAPTURE").is_ok()
             && self.cfg.overlay_target == crate::config::OverlayTarget::Window
+            && let Some((x, y, w, h)) = self.window_system.active_window_geometry()
         {
-            if let Some((x, y, w, h)) = self.get_active_window_geometry() {
-                // Use grim with specific geometry for faster capture
-                let geometry = format!("{},{} {}x{}", x, y, w, h);
-                let output = Command::new("grim")
-                    .args(["-g", &geometry, "-t", "ppm", "-"]) // PPM is faster than PNG
-                    .output()?;
-
-                if output.status.success() && !output.stdout.is_empty() {
-                    // Decode PPM directly from memory
-                    let img_vec = opencv::core::Vector::<u8>::from_iter(output.stdout.into_iter());
-                    let mat = imgcodecs::imdecode(&img_vec, imgcodecs::IMREAD_COLOR)?;
-                    if !mat.empty() {
-                        return Ok(mat);
-                    }
+            match self.capture_window((x, y, w, h)) {
+                Ok(mat) => return Ok((mat, (x, y))),
+                Err(err) => log::warn!("opencv backend: {err}; capturing the whole screen"),
+            }
+        }
+        Ok((self.capture_screen()?, (0, 0)))
+    }
+
+    /// Capture only the window at `region`, so there's a smaller image to decode
+    /// and search. Tries each tool of `region_capture_commands` in turn.
+    fn capture_window(&self, region: (i32, i32, i32, i32)) -> Result<Mat> {
+        for (tool, args) in region_capture_commands(self.window_system.window_system_type, region) {
+            let Ok(output) = Command::new(tool).args(&args).output() else {
+                continue;
+            };
+            if output.status.success() && !output.stdout.is_empty() {
+                let img_vec = opencv::core::Vector::<u8>::from_iter(output.stdout.into_iter());
+                let mat = imgcodecs::imdecode(&img_vec, imgcodecs::IMREAD_COLOR)?;
+                if !mat.empty() {
+                    return Ok(mat);
                 }
             }
         }
+        Err(anyhow!("no tool could capture the window at {region:?}"))
+    }
 
+    fn capture_screen(&self) -> Result<Mat> {
         // Try fast stdout capture first (avoids file I/O)
         match self.window_system.window_system_type {
             crate::window_system::WindowSystemType::Wayland => {
@@ -123,29 +147,123 @@ impl OpenCvBackend {
         Err(last_error.unwrap_or_else(|| anyhow!("no suitable screenshot tool found")))
     }
 
-    fn get_active_window_geometry(&self) -> Option<(i32, i32, i32, i32)> {
-        if self.window_system.window_system_type == crate::window_system::WindowSystemType::Wayland
+    /// Outputs to capture one by one: every active output in screen mode on a
+    /// multi-monitor Wayland session, so one failing capture doesn't lose the
+    /// others. Empty means a single capture of everything.
+    fn capture_outputs(&self) -> Vec<(String, (i32, i32, i32, i32))> {
+        if self.window_system.window_system_type != crate::window_system::WindowSystemType::Wayland
+            || self.cfg.overlay_target != crate::config::OverlayTarget::Screen
         {
-            self.window_system
-                .get_active_window_geometry_wayland()
-                .or_else(|| self.window_system.get_active_window_geometry_x11())
+            return Vec::new();
+        }
+        let outputs = self.window_system.outputs();
+        if outputs.len() > 1 {
+            outputs
         } else {
-            self.window_system.get_active_window_geometry_x11()
+            Vec::new()
         }
     }
-}
 
-impl Backend for OpenCvBackend {
-    fn name(&self) -> &'static str {
-        "opencv"
+    /// Capture the logical region `(x, y, w, h)` at scale 1, so image pixels are
+    /// logical pixels relative to the region.
+    fn capture_region(&self, (x, y, w, h): (i32, i32, i32, i32)) -> Result<Mat> {
+        let geometry = format!("{},{} {}x{}", x, y, w, h);
+        let output = Command::new("grim")
+            .args(["-g", &geometry, "-s", "1", "-t", "ppm", "-"])
+            .output()
+            .context("run grim")?;
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(anyhow!(
+                "grim -g {geometry:?} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let img_vec = opencv::core::Vector::<u8>::from_iter(output.stdout.into_iter());
+        let mat = imgcodecs::imdecode(&img_vec, imgcodecs::IMREAD_COLOR)?;
+        if mat.empty() {
+            return Err(anyhow!("grim -g {geometry:?} returned an empty image"));
+        }
+        Ok(mat)
     }
 
-    fn get_children(&mut self) -> Result<BackendResult> {
-        let cfg = self.cfg.backends.opencv.clone();
-        let img = self.screenshot()?;
+    /// Mask of pixels that differ from the previous capture cached at `cache`,
+    /// grown by the kernel so edges of newly drawn elements fall inside it. `None`
+    /// when there is no comparable previous frame or nothing changed.
+    fn change_mask(&self, img: &Mat, cache: &std::path::Path) -> Result<Option<Mat>> {
+        let cfg = &self.cfg.backends.opencv;
+        let prev = match imgcodecs::imread(&cache.to_string_lossy(), imgcodecs::IMREAD_COLOR) {
+            Ok(prev) if !prev.empty() => prev,
+            _ => return Ok(None),
+        };
+        if prev.size()? != img.size()? {
+            return Ok(None);
+        }
+
+        let mut diff = Mat::default();
+        core::absdiff(img, &prev, &mut diff)?;
+        let mut diff_gray = Mat::default();
+        imgproc::cvt_color(
+            &diff,
+            &mut diff_gray,
+            imgproc::COLOR_BGR2GRAY,
+            0,
+            core::AlgorithmHint::ALGO_HINT_DEFAULT,
+        )?;
+        let mut mask = Mat::default();
+        imgproc::threshold(
+            &diff_gray,
+            &mut mask,
+            cfg.diff_threshold,
+            255.0,
+            imgproc::THRESH_BINARY,
+        )?;
+        if core::count_non_zero(&mask)? == 0 {
+            return Ok(None);
+        }
+
+        let kernel = imgproc::get_structuring_element(
+            imgproc::MORPH_RECT,
+            Size::new(cfg.kernel_size, cfg.kernel_size),
+            Point::new(-1, -1),
+        )?;
+        let mut grown = Mat::default();
+        imgproc::dilate(
+            &mask,
+            &mut grown,
+            &kernel,
+            Point::new(-1, -1),
+            2,
+            core::BORDER_DEFAULT,
+            imgproc::morphology_default_border_value()?,
+        )?;
+        Ok(Some(grown))
+    }
+
+    /// Edge-detect `img`, whose top-left is at `origin` on screen, and return the
+    /// bounding boxes of its contours as children, in image coordinates. Diff mode
+    /// compares against and then replaces the frame cached at `cache`.
+    fn detect(&self, img: &Mat, cache: &std::path::Path, origin: (i32, i32)) -> Result<Vec<Child>> {
+        let cfg = &self.cfg.backends.opencv;
+        // Everything below works on the downscaled image, diff cache included
+        let downscaled;
+        let img = if cfg.downscale < 1.0 {
+            let mut resized = Mat::default();
+            imgproc::resize(
+                img,
+                &mut resized,
+                Size::new(0, 0),
+                cfg.downscale,
+                cfg.downscale,
+                imgproc::INTER_AREA,
+            )?;
+            downscaled = resized;
+            &downscaled
+        } else {
+            img
+        };
         let mut gray = Mat::default();
         imgproc::cvt_color(
-            &img,
+            img,
             &mut gray,
             imgproc::COLOR_BGR2GRAY,
             0,
@@ -162,6 +280,31 @@ impl Backend for OpenCvBackend {
             false,
         )?;
 
+        if cfg.diff_mode {
+            // Only keep edges in regions that changed since the previous capture
+            match self.change_mask(img, cache) {
+                Ok(Some(mask)) => {
+                    let mut masked = Mat::new_size_with_default(
+                        edges.size()?,
+                        edges.typ(),
+                        core::Scalar::all(0.0),
+                    )?;
+                    core::bitwise_and(&edges, &edges, &mut masked, &mask)?;
+                    edges = masked;
+                }
+                Ok(None) => {
+                    log::info!("opencv diff: no usable previous frame; scanning everything")
+                }
+                Err(err) => log::warn!("opencv diff: comparing frames failed: {err}"),
+            }
+            if let Some(dir) = cache.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            if let Err(err) = imgcodecs::imwrite_def(&cache.to_string_lossy(), img) {
+                log::warn!("opencv diff: failed to cache frame: {err}");
+            }
+        }
+
         let kernel = imgproc::get_structuring_element(
             imgproc::MORPH_RECT,
             Size::new(cfg.kernel_size, cfg.kernel_size),
@@ -187,44 +330,263 @@ impl Backend for OpenCvBackend {
             Point::new(0, 0),
         )?;
 
+        // Dilation grows contours by about a kernel, in downscaled pixels
+        let slack = (cfg.kernel_size as f64 / cfg.downscale.min(1.0)).ceil() as i32;
         let mut children = Vec::new();
         for contour in contours {
             let rect = imgproc::bounding_rect(&contour)?;
+            let Some((x, y, width, height)) = normalize_extents(upscale_rect(
+                (rect.x, rect.y, rect.width, rect.height),
+                cfg.downscale,
+            )) else {
+                continue;
+            };
             // filter tiny rects
-            if rect.width < 5 || rect.height < 5 {
+            if width < 5 || height < 5 {
+                continue;
+            }
+            // Don't hint our own hint boxes (or their letters) if an overlay is
+            // mapped during capture
+            if inside_drawn_hint(
+                (x + origin.0, y + origin.1, width, height),
+                &self.drawn_hints,
+                slack,
+            ) {
                 continue;
             }
             children.push(Child {
-                absolute_x: rect.x,
-                absolute_y: rect.y,
-                width: rect.width,
-                height: rect.height,
+                absolute_x: x,
+                absolute_y: y,
+                width,
+                height,
+                role: None,
+                accessible_path: None,
+                name: None,
+                source: Some("opencv".into()),
             });
         }
+        Ok(children)
+    }
+}
 
-        let mut focus_extents = None;
-        if self.cfg.overlay_target == crate::config::OverlayTarget::Window {
-            let extents = if self.window_system.window_system_type
-                == crate::window_system::WindowSystemType::Wayland
-            {
-                self.window_system
-                    .get_active_window_geometry_wayland()
-                    .or_else(|| self.window_system.get_active_window_geometry_x11())
-            } else {
-                self.window_system.get_active_window_geometry_x11()
-            };
+/// Commands (tool, args) that write the screen region `(x, y, w, h)` to stdout,
+/// in order of preference: `grim -g` on Wayland, `shotgun -g`/`maim -g` on X11.
+pub fn region_capture_commands(
+    window_system_type: WindowSystemType,
+    (x, y, w, h): (i32, i32, i32, i32),
+) -> Vec<(&'static str, Vec<String>)> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+    match window_system_type {
+        WindowSystemType::Wayland => {
+            let geometry = format!("{x},{y} {w}x{h}");
+            // PPM is faster to encode and decode than PNG
+            vec![("grim", args(&["-g", &geometry, "-t", "ppm", "-"]))]
+        }
+        WindowSystemType::X11 => {
+            let geometry = format!("{w}x{h}+{x}+{y}");
+            vec![
+                ("shotgun", args(&["-g", &geometry, "-f", "ppm", "-"])),
+                ("maim", args(&["-g", &geometry, "-f", "png"])),
+            ]
+        }
+    }
+}
+
+/// Whether `rect` lies within one of the overlay's `drawn` hint boxes, grown by
+/// `slack` pixels on each side.
+fn inside_drawn_hint(
+    (x, y, w, h): (i32, i32, i32, i32),
+    drawn: &[(i32, i32, i32, i32)],
+    slack: i32,
+) -> bool {
+    drawn.iter().any(|&(dx, dy, dw, dh)| {
+        x >= dx - slack && y >= dy - slack && x + w <= dx + dw + slack && y + h <= dy + dh + slack
+    })
+}
+
+/// Map `rect` (x, y, width, height) found on an image downscaled by `factor`
+/// back to full-resolution pixels, rounding outwards so the element stays covered.
+pub fn upscale_rect(rect: (i32, i32, i32, i32), factor: f64) -> (i32, i32, i32, i32) {
+    if factor >= 1.0 {
+        return rect;
+    }
+    let (x, y, w, h) = rect;
+    let left = (x as f64 / factor).floor() as i32;
+    let top = (y as f64 / factor).floor() as i32;
+    let right = ((x + w) as f64 / factor).ceil() as i32;
+    let bottom = ((y + h) as f64 / factor).ceil() as i32;
+    (left, top, right - left, bottom - top)
+}
+
+/// Capture the screen like the backend does and draw `hints` (screen coordinates)
+/// onto it, then write the result to `path`. Returns how many hints were drawn.
+pub fn annotate_screenshot(
+    cfg: &Config,
+    window_system: &WindowSystem,
+    hints: &HintMap,
+    path: &std::path::Path,
+) -> Result<usize> {
+    let backend = OpenCvBackend::new(cfg.clone(), window_system.clone());
+    let (mut img, origin) = backend.screenshot()?;
+    let drawn = annotate(&mut img, hints, origin, &cfg.hints)?;
+    imgcodecs::imwrite_def(&path.to_string_lossy(), &img)
+        .with_context(|| format!("write {}", path.display()))?;
+    Ok(drawn)
+}
+
+/// Outline every element of `hints` on `img`, whose top-left is at `origin` on
+/// screen, and put its label in a box at the element's top-left corner, in the
+/// overlay's colors. Returns how many hints were drawn.
+pub fn annotate(
+    img: &mut Mat,
+    hints: &HintMap,
+    origin: (i32, i32),
+    style: &HintsStyle,
+) -> Result<usize> {
+    // Screenshots are BGR, 0-255
+    let bgr = |(r, g, b, _): (f64, f64, f64, f64)| {
+        core::Scalar::new(b * 255.0, g * 255.0, r * 255.0, 0.0)
+    };
+    let background = bgr(style.hint_background_color);
+    let font_color = bgr(style.hint_font_color);
+    let font_scale = style.hint_font_size as f64 / ANNOTATION_FONT_PX;
+
+    let mut labels: Vec<(&String, &Child)> = hints.iter().collect();
+    labels.sort_by(|a, b| a.0.cmp(b.0));
+    for (label, child) in &labels {
+        let element = core::Rect::new(
+            child.absolute_x - origin.0,
+            child.absolute_y - origin.1,
+            child.width,
+            child.height,
+        );
+        imgproc::rectangle(img, element, background, 2, imgproc::LINE_8, 0)?;
+
+        let text = if style.hint_uppercase {
+            label.to_uppercase()
+        } else {
+            label.to_string()
+        };
+        let mut baseline = 0;
+        let size = imgproc::get_text_size(
+            &text,
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            font_scale,
+            1,
+            &mut baseline,
+        )?;
+        let label_box = core::Rect::new(
+            element.x,
+            element.y,
+            size.width + 2 * style.hint_padding_x,
+            size.height + baseline + style.hint_padding_y,
+        );
+        imgproc::rectangle(
+            img,
+            label_box,
+            background,
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )?;
+        imgproc::put_text(
+            img,
+            &text,
+            Point::new(
+                label_box.x + style.hint_padding_x,
+                label_box.y + label_box.height - baseline - style.hint_padding_y / 2,
+            ),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            font_scale,
+            font_color,
+            1,
+            imgproc::LINE_AA,
+            false,
+        )?;
+    }
+    Ok(labels.len())
+}
+
+/// Where diff mode keeps the previous capture between `hintsx` runs; captures of
+/// single outputs are kept per output.
+fn diff_cache_path(output: Option<&str>) -> std::path::PathBuf {
+    match output {
+        Some(name) => default_cache_dir().join(format!("opencv_last_frame-{name}.png")),
+        None => default_cache_dir().join("opencv_last_frame.png"),
+    }
+}
 
-            if let Some((fx, fy, fw, fh)) = extents {
-                focus_extents = Some((fx, fy, fw, fh));
-                // Filter children to be inside the active window
-                children.retain(|c| {
-                    c.absolute_x >= fx
-                        && c.absolute_y >= fy
-                        && (c.absolute_x + c.width) <= (fx + fw)
-                        && (c.absolute_y + c.height) <= (fy + fh)
-                });
+/// Children found in each output's capture (relative to that output's `origin`)
+/// in global coordinates. Outputs whose capture or detection failed are logged
+/// and skipped; it's only an error when all of them failed.
+fn merge_captures(captures: Vec<(String, (i32, i32), Result<Vec<Child>>)>) -> Result<Vec<Child>> {
+    let total = captures.len();
+    let mut children = Vec::new();
+    let mut failed = Vec::new();
+    for (name, (x, y), found) in captures {
+        match found {
+            Ok(found) => children.extend(found.iter().map(|child| child.translated(x, y))),
+            Err(err) => {
+                log::warn!("opencv: output {name} failed: {err}");
+                failed.push(name);
             }
         }
+    }
+    if failed.len() == total {
+        return Err(anyhow!(
+            "opencv: capturing every output failed ({failed:?})"
+        ));
+    }
+    if !failed.is_empty() {
+        log::info!(
+            "opencv: hinting {} of {} outputs; skipped {:?}",
+            total - failed.len(),
+            total,
+            failed
+        );
+    }
+    Ok(children)
+}
+
+impl Backend for OpenCvBackend {
+    fn name(&self) -> &'static str {
+        "opencv"
+    }
+
+    fn get_children(&mut self, _deadline: Option<Instant>) -> Result<BackendResult> {
+        let outputs = self.capture_outputs();
+        let mut children = if outputs.is_empty() {
+            let (img, (x, y)) = self.screenshot()?;
+            self.detect(&img, &diff_cache_path(None), (x, y))?
+                .into_iter()
+                .map(|child| child.translated(x, y))
+                .collect()
+        } else {
+            let captures = outputs
+                .into_iter()
+                .map(|(name, (x, y, w, h))| {
+                    let found = self
+                        .capture_region((x, y, w, h))
+                        .and_then(|img| self.detect(&img, &diff_cache_path(Some(&name)), (x, y)));
+                    (name, (x, y), found)
+                })
+                .collect();
+            merge_captures(captures)?
+        };
+
+        let mut focus_extents = None;
+        if self.cfg.overlay_target == crate::config::OverlayTarget::Window
+            && let Some((fx, fy, fw, fh)) = self.window_system.active_window_geometry()
+        {
+            focus_extents = Some((fx, fy, fw, fh));
+            // Filter children to be inside the active window
+            children.retain(|c| {
+                c.absolute_x >= fx
+                    && c.absolute_y >= fy
+                    && (c.absolute_x + c.width) <= (fx + fw)
+                    && (c.absolute_y + c.height) <= (fy + fh)
+            });
+        }
 
         if children.is_empty() {
             Err(anyhow!("opencv backend found zero contours"))
@@ -232,7 +594,144 @@ impl Backend for OpenCvBackend {
             Ok(BackendResult {
                 children,
                 focus_extents,
+                partial: false,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank(rows: i32, cols: i32) -> Mat {
+        Mat::new_rows_cols_with_default(rows, cols, core::CV_8UC3, core::Scalar::all(255.0))
+            .unwrap()
+    }
+
+    #[test]
+    fn change_mask_covers_only_what_changed() {
+        let backend = OpenCvBackend::new(Config::default(), WindowSystem::detect("x11").unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("frame.png");
+        let before = blank(200, 300);
+        // Nothing to compare against yet
+        assert!(backend.change_mask(&before, &cache).unwrap().is_none());
+        imgcodecs::imwrite_def(&cache.to_string_lossy(), &before).unwrap();
+        assert!(backend.change_mask(&before, &cache).unwrap().is_none());
+        assert!(
+            backend
+                .change_mask(&blank(100, 300), &cache)
+                .unwrap()
+                .is_none()
+        );
+
+        let mut after = before.try_clone().unwrap();
+        imgproc::rectangle(
+            &mut after,
+            core::Rect::new(100, 50, 40, 30),
+            core::Scalar::all(0.0),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )
+        .unwrap();
+        let mask = backend.change_mask(&after, &cache).unwrap().unwrap();
+        let at = |row, col| *mask.at_2d::<u8>(row, col).unwrap();
+        assert_eq!(at(65, 120), 255);
+        // Grown past the edge by the kernel, but not across the image
+        assert_eq!(at(48, 98), 255);
+        assert_eq!(at(0, 0), 0);
+        assert_eq!(at(190, 290), 0);
+    }
+
+    #[test]
+    fn contours_inside_drawn_hints_are_skipped() {
+        let drawn = [(150, 100, 30, 20)];
+        // The box itself and a letter inside it, grown by the dilation kernel
+        assert!(inside_drawn_hint((148, 98, 34, 24), &drawn, 3));
+        assert!(inside_drawn_hint((155, 104, 8, 12), &drawn, 3));
+        // An element that only overlaps the box, and one elsewhere
+        assert!(!inside_drawn_hint((140, 90, 60, 40), &drawn, 3));
+        assert!(!inside_drawn_hint((20, 20, 60, 40), &drawn, 3));
+        assert!(!inside_drawn_hint((150, 100, 30, 20), &[], 3));
+    }
+
+    #[test]
+    fn merge_captures_skips_failed_outputs() {
+        let captures = vec![
+            (
+                "DP-1".to_string(),
+                (0, 0),
+                Ok(vec![Child::for_window((10, 10, 5, 5))]),
+            ),
+            (
+                "DP-2".to_string(),
+                (1920, 0),
+                Ok(vec![Child::for_window((10, 10, 5, 5))]),
+            ),
+            ("HDMI-1".to_string(), (3840, 0), Err(anyhow!("grim failed"))),
+        ];
+        let positions: Vec<_> = merge_captures(captures)
+            .unwrap()
+            .iter()
+            .map(|child| (child.absolute_x, child.absolute_y))
+            .collect();
+        assert_eq!(positions, [(10, 10), (1930, 10)]);
+
+        let all_failed = vec![
+            ("DP-1".to_string(), (0, 0), Err(anyhow!("grim failed"))),
+            ("DP-2".to_string(), (1920, 0), Err(anyhow!("grim failed"))),
+        ];
+        assert!(merge_captures(all_failed).is_err());
+    }
+
+    #[test]
+    fn annotate_outlines_and_labels_each_hint() {
+        let mut img = blank(200, 300);
+        let hints: HintMap = [
+            ("a".to_string(), Child::for_window((1050, 520, 60, 40))),
+            ("s".to_string(), Child::for_window((1200, 600, 40, 30))),
+        ]
+        .into();
+        let style = HintsStyle::default();
+        assert_eq!(annotate(&mut img, &hints, (1000, 500), &style).unwrap(), 2);
+
+        let white = core::Vec3b::all(255);
+        let px = |row, col| *img.at_2d::<core::Vec3b>(row, col).unwrap();
+        // Label box at the top-left of "a", outline along the bottom of "s"
+        assert_ne!(px(22, 52), white);
+        assert_ne!(px(129, 235), white);
+        // Inside "s" to the right of its label, and away from both hints
+        assert_eq!(px(120, 235), white);
+        assert_eq!(px(180, 20), white);
+    }
+
+    #[test]
+    fn upscale_rect_rounds_outwards() {
+        assert_eq!(upscale_rect((10, 20, 5, 7), 0.5), (20, 40, 10, 14));
+        assert_eq!(upscale_rect((1, 1, 1, 1), 0.3), (3, 3, 4, 4));
+        assert_eq!(upscale_rect((10, 20, 5, 7), 1.0), (10, 20, 5, 7));
+    }
+
+    #[test]
+    fn region_capture_commands_per_window_system() {
+        let commands = |ws| -> Vec<String> {
+            region_capture_commands(ws, (10, 20, 300, 200))
+                .into_iter()
+                .map(|(tool, args)| format!("{tool} {}", args.join(" ")))
+                .collect()
+        };
+        assert_eq!(
+            commands(WindowSystemType::Wayland),
+            ["grim -g 10,20 300x200 -t ppm -"]
+        );
+        assert_eq!(
+            commands(WindowSystemType::X11),
+            [
+                "shotgun -g 300x200+10+20 -f ppm -",
+                "maim -g 300x200+10+20 -f png"
+            ]
+        );
+    }
+}
@@ -1,20 +1,36 @@
-use anyhow::Result;
-use gdk4;
-use gtk4;
+use anyhow::{Result, anyhow};
+use evdev::KeyCode;
 use gtk4::prelude::{Cast, DisplayExt, ListModelExt, MonitorExt};
-use rust_hintsx::consts::UNIX_DOMAIN_SOCKET_FILE;
-use rust_hintsx::ipc::{Request, Response};
-use rust_hintsx::mouse::{MouseButton, MouseButtonState, VirtualMouse};
-use std::fs;
+use rust_hintsx::backends;
+use rust_hintsx::config::{Config, SessionState};
+use rust_hintsx::consts::{UNIX_DOMAIN_SOCKET_FILE, default_config_path};
+use rust_hintsx::hints::Child;
+use rust_hintsx::ipc::{Request, Response, bind_socket, inherited_listener, lock_daemon_instance};
+use rust_hintsx::mouse::{
+    GestureAction, MouseButton, MouseButtonState, OutputScale, Pointer, build_pointer,
+    cancel_pending, check_cancel, clear_cancel, gesture_action, held_after, request_cancel,
+};
+use rust_hintsx::window_system::WindowSystem;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::os::unix::net::UnixListener;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
 
 fn main() -> Result<()> {
-    env_logger::init();
+    let logger = env_logger::Builder::from_default_env().build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(RequestLogger(logger)))?;
+    log::set_max_level(max_level);
 
-    if std::path::Path::new(UNIX_DOMAIN_SOCKET_FILE).exists() {
-        fs::remove_file(UNIX_DOMAIN_SOCKET_FILE)?;
-    }
+    // Before any devices exist or the socket is touched: a second daemon must
+    // neither add a second cursor nor unlink the first one's live socket
+    let _instance_lock = lock_daemon_instance()?;
+
+    let mut cfg = Config::load();
+    let window_system = WindowSystem::detect(&cfg.window_system)?;
 
     gtk4::init().ok();
     let display = gdk4::Display::default().expect("no display");
@@ -27,64 +43,291 @@ fn main() -> Result<()> {
     let screen_width = geo.width();
     let screen_height = geo.height();
     let scale_factor = monitor.scale_factor();
+    // Per-output origin and scale for clicks the overlay tags with an output name
+    let outputs: HashMap<String, OutputScale> = (0..monitor_list.n_items())
+        .filter_map(|idx| monitor_list.item(idx)?.downcast::<gdk4::Monitor>().ok())
+        .filter_map(|monitor| {
+            let geo = monitor.geometry();
+            let scale = OutputScale {
+                origin: (geo.x(), geo.y()),
+                scale: monitor.scale_factor(),
+            };
+            Some((monitor.connector()?.to_string(), scale))
+        })
+        .collect();
+    log::info!("hintsd outputs: {:?}", outputs);
 
-    let mut mouse = VirtualMouse::new(screen_width, screen_height, scale_factor)?;
-    let listener = UnixListener::bind(UNIX_DOMAIN_SOCKET_FILE)?;
-    log::info!("hintsd listening on {}", UNIX_DOMAIN_SOCKET_FILE);
+    let mut mouse = build_pointer(&cfg.mouse, screen_width, screen_height, scale_factor)?;
+    log::info!("hintsd using {} pointer backend", mouse.name());
+    let layout = (0..monitor_list.n_items())
+        .filter_map(|idx| monitor_list.item(idx)?.downcast::<gdk4::Monitor>().ok())
+        .map(|monitor| {
+            let geo = monitor.geometry();
+            (geo.x(), geo.y(), geo.width(), geo.height())
+        })
+        .reduce(|a, b| {
+            let (x, y) = (a.0.min(b.0), a.1.min(b.1));
+            let right = (a.0 + a.2).max(b.0 + b.2);
+            let bottom = (a.1 + a.3).max(b.1 + b.3);
+            (x, y, right - x, bottom - y)
+        });
+    if let Some(layout) = layout {
+        log::info!("hintsd output layout: {:?}", layout);
+        mouse.set_layout(layout);
+    }
+    let listener = match inherited_listener()? {
+        Some(listener) => {
+            log::info!("hintsd using socket passed by systemd (LISTEN_FDS)");
+            listener
+        }
+        None => {
+            let listener = bind_socket(Path::new(UNIX_DOMAIN_SOCKET_FILE))?;
+            log::info!("hintsd listening on {}", UNIX_DOMAIN_SOCKET_FILE);
+            listener
+        }
+    };
+
+    // Requests are read on their own thread so a Cancel can flag the action
+    // running here while it is still in progress
+    let (requests_tx, requests) = mpsc::channel();
+    std::thread::spawn(move || accept_requests(listener, requests_tx));
+
+    // Children of the last Collect, which ClickElement ids index into
+    let mut collected: Vec<Child> = Vec::new();
+    // Button pressed by a Click without a matching release yet (a drag in progress)
+    let mut held: Option<MouseButton> = None;
+    for (mut stream, req) in requests {
+        if let Err(err) = handle_connection(
+            &mut stream,
+            req,
+            mouse.as_mut(),
+            &mut cfg,
+            &window_system,
+            &outputs,
+            &mut collected,
+            &mut held,
+        ) {
+            log::warn!("connection error: {err}");
+        }
+    }
+    Ok(())
+}
 
+/// Read each connection's request and queue it for the main thread, flagging a
+/// Cancel right away so the action currently running stops at its next step.
+fn accept_requests(listener: UnixListener, requests: mpsc::Sender<(UnixStream, Request)>) {
     for stream in listener.incoming() {
-        match stream {
-            Ok(mut stream) => {
-                if let Err(err) = handle_connection(&mut stream, &mut mouse) {
-                    log::warn!("connection error: {err}");
-                }
-            }
+        let mut stream = match stream {
+            Ok(stream) => stream,
             Err(err) => {
                 log::warn!("listener error: {err}");
+                continue;
+            }
+        };
+        match read_request(&mut stream) {
+            Ok(req) => {
+                if req.is_cancel() {
+                    log::info!("DAEMON: Cancel received; flagging the running action");
+                    request_cancel();
+                }
+                if requests.send((stream, req)).is_err() {
+                    return;
+                }
             }
+            Err(err) => log::warn!("connection error: {err}"),
         }
     }
-    Ok(())
 }
 
-fn handle_connection(
-    stream: &mut std::os::unix::net::UnixStream,
-    mouse: &mut VirtualMouse,
-) -> Result<()> {
-    log::info!("════════════════════════════════════════════════════════════════");
-    log::info!("DAEMON: New connection received on socket");
-
+fn read_request(stream: &mut UnixStream) -> Result<Request> {
     let mut len_bytes = [0u8; 4];
     stream.read_exact(&mut len_bytes)?;
     let len = u32::from_le_bytes(len_bytes) as usize;
-    log::info!("DAEMON: Request length: {} bytes", len);
+    log::debug!("DAEMON: Request length: {} bytes", len);
 
     let mut buf = vec![0u8; len];
     stream.read_exact(&mut buf)?;
-    log::info!("DAEMON: Request data received");
-
-    let req: Request = bincode::deserialize(&buf)?;
-    log::info!("DAEMON: Request deserialized successfully");
-    log::info!(
-        "DAEMON: Request type: {:?}",
-        match &req {
-            Request::Move { .. } => "Move",
-            Request::Scroll { .. } => "Scroll",
-            Request::Click { .. } => "Click",
-            Request::DoMouseAction { .. } => "DoMouseAction",
-        }
-    );
-
-    let result = match req {
-        Request::Move { x, y, absolute } => {
+    Ok(bincode::deserialize(&buf)?)
+}
+
+thread_local! {
+    /// Set while this thread handles a request that asked for terse logs.
+    static TERSE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Wraps the daemon's logger so that, while a [`TerseLogGuard`] is alive on a
+/// thread, info and lower records from that thread (the request banners of
+/// `execute` and the pointer backends) are dropped. Warnings, errors and other
+/// threads, like the accept loop, log as usual.
+struct RequestLogger<L>(L);
+
+impl<L: log::Log> log::Log for RequestLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        (metadata.level() <= log::Level::Warn || !TERSE.get()) && self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.0.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Keeps this thread's logs terse for a request that isn't `verbose`, until dropped.
+struct TerseLogGuard(bool);
+
+impl TerseLogGuard {
+    fn new(verbose: bool) -> Self {
+        Self(TERSE.replace(!verbose))
+    }
+}
+
+impl Drop for TerseLogGuard {
+    fn drop(&mut self) {
+        TERSE.set(self.0);
+    }
+}
+
+/// Absolute point `(dx_frac, dy_frac)` of the way across element `id` of
+/// `collected`, fractions clamped to the element.
+fn element_point(collected: &[Child], id: usize, dx_frac: f64, dy_frac: f64) -> Result<(i32, i32)> {
+    let child = collected.get(id).ok_or_else(|| {
+        anyhow!(
+            "no element {id}; the last collection had {} (send Collect first)",
+            collected.len()
+        )
+    })?;
+    Ok(child.point_at(dx_frac.clamp(0.0, 1.0), dy_frac.clamp(0.0, 1.0)))
+}
+
+/// Pause before a click for the overlay to release its input grab; GTK and
+/// layer-shell surfaces take a moment even after hiding.
+const SETTLE_WAIT: Duration = Duration::from_millis(40);
+
+/// How long to wait before a click that asked to `settle` (or didn't).
+fn settle_wait(settle: bool) -> Duration {
+    if settle { SETTLE_WAIT } else { Duration::ZERO }
+}
+
+/// Wheel amount for a scroll of `(x, y)` repeated `count` times (0 meaning once),
+/// at most `limit` times.
+fn scaled_scroll((x, y): (i32, i32), count: u32, limit: u32) -> (i32, i32) {
+    let count = count.clamp(1, limit.clamp(1, i32::MAX as u32)) as i32;
+    (x.saturating_mul(count), y.saturating_mul(count))
+}
+
+/// Pause between the wheel steps of a drag held near an edge, for the view to
+/// move under the dragged item.
+const DRAG_EDGE_SCROLL_PAUSE: Duration = Duration::from_millis(50);
+
+/// Wheel direction, as `Pointer::scroll` takes it, for a drag dropped at
+/// `target`: towards each side of `area` the drop is within `margin` pixels
+/// of, so -1 (down) near the bottom and +1 (right) near the right edge.
+fn drag_edge_scroll(target: (i32, i32), area: (i32, i32, i32, i32), margin: i32) -> (i32, i32) {
+    if margin <= 0 {
+        return (0, 0);
+    }
+    let (x, y, w, h) = area;
+    let axis = |v: i32, start: i32, len: i32| {
+        if v < start + margin {
+            -1
+        } else if v >= start + len - margin {
+            1
+        } else {
+            0
+        }
+    };
+    // Screen y grows downwards, the wheel's upwards
+    (axis(target.0, x, w), -axis(target.1, y, h))
+}
+
+/// Mouse button of a request's `button` number.
+fn request_button(button: u16) -> MouseButton {
+    match button {
+        2 => MouseButton::Right,
+        1 => MouseButton::Middle,
+        _ => MouseButton::Left,
+    }
+}
+
+/// Re-read the config file at `path` into `cfg`; a file that doesn't parse is an
+/// error and leaves `cfg` as it was.
+fn reload_from(path: &Path, cfg: &mut Config, mouse: &mut dyn Pointer) -> Result<()> {
+    let new_cfg = Config::try_load_from(path, &SessionState::load())?;
+    reload_config(cfg, new_cfg, mouse);
+    Ok(())
+}
+
+/// Switch to `new_cfg`, handing its pointer settings to `mouse`. Only a different
+/// `mouse.backend` needs a restart.
+fn reload_config(cfg: &mut Config, new_cfg: Config, mouse: &mut dyn Pointer) {
+    if new_cfg.mouse.backend != cfg.mouse.backend {
+        log::warn!(
+            "DAEMON: mouse.backend changed to {:?}; restart hintsd to apply it",
+            new_cfg.mouse.backend
+        );
+    }
+    mouse.configure(&new_cfg.mouse);
+    *cfg = new_cfg;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute(
+    req: Request,
+    mouse: &mut dyn Pointer,
+    cfg: &mut Config,
+    window_system: &WindowSystem,
+    outputs: &HashMap<String, OutputScale>,
+    collected: &mut Vec<Child>,
+    held: &mut Option<MouseButton>,
+) -> Result<Response> {
+    log::info!("DAEMON: Request type: {:?}", req.name());
+    // Requests queued ahead of a Cancel are answered with an error instead of run
+    if cancel_pending() && !req.is_cancel() {
+        return Err(anyhow!("dropped by a pending Cancel"));
+    }
+
+    match req {
+        Request::Move {
+            x,
+            y,
+            absolute,
+            duration_ms,
+        } => {
             log::info!("DAEMON: Processing Move request");
-            log::info!("  x={}, y={}, absolute={}", x, y, absolute);
-            mouse.r#move(x, y, absolute)
+            log::info!(
+                "  x={}, y={}, absolute={}, duration_ms={:?}",
+                x,
+                y,
+                absolute,
+                duration_ms
+            );
+            match duration_ms {
+                Some(ms) if ms > 0 => mouse.glide(x, y, absolute, Duration::from_millis(ms as u64)),
+                _ => mouse.r#move(x, y, absolute),
+            }
+            .map(|(x, y)| Response::Position { x, y })
         }
-        Request::Scroll { x, y } => {
+        Request::Scroll { x, y, at, count } => {
             log::info!("DAEMON: Processing Scroll request");
-            log::info!("  x={}, y={}", x, y);
-            mouse.scroll(x, y)
+            log::info!("  x={}, y={}, at={:?}, count={}", x, y, at, count);
+            // No more than the overlay sends for a page scroll with the largest repeat
+            let limit = cfg
+                .mouse
+                .max_repeat
+                .saturating_mul(cfg.mouse.page_scroll_steps);
+            let (x, y) = scaled_scroll((x, y), count, limit);
+            let anchored = match at {
+                Some((ax, ay)) => mouse.r#move(ax, ay, true).map(|_| ()),
+                None => Ok(()),
+            };
+            anchored
+                .and_then(|_| mouse.scroll(x, y))
+                .map(|_| Response::Ok)
         }
         Request::Click {
             x,
@@ -93,31 +336,39 @@ fn handle_connection(
             button_states,
             repeat,
             absolute,
+            move_first,
+            output,
+            settle,
         } => {
             log::info!("DAEMON: Processing Click request");
             log::info!(
-                "  x={}, y={}, button={}, button_states={:?}, repeat={}, absolute={}",
+                "  x={}, y={}, button={}, button_states={:?}, repeat={}, absolute={}, move_first={}, output={:?}, settle={}",
                 x,
                 y,
                 button,
                 button_states,
                 repeat,
-                absolute
+                absolute,
+                move_first,
+                output,
+                settle
             );
 
-            // Wait for overlay to fully close and release input grab
-            // GTK/layer-shell windows take time to release, especially on Wayland
-            // Keep this short: overlay hides before sending the request. This wait
-            // just lets the compositor release focus/input grabs.
-            log::info!("DAEMON: Waiting 40ms for overlay to close and focus to settle...");
-            std::thread::sleep(std::time::Duration::from_millis(40));
-            log::info!("DAEMON: Wait complete, proceeding with click");
-
-            let btn = match button {
-                2 => MouseButton::Right,
-                1 => MouseButton::Middle,
-                _ => MouseButton::Left,
-            };
+            // Wait for overlay to fully close and release input grab, unless the
+            // client says it is already gone
+            let wait = settle_wait(settle);
+            if wait.is_zero() {
+                log::info!("DAEMON: Overlay already unmapped; clicking without waiting");
+            } else {
+                log::info!(
+                    "DAEMON: Waiting {:?} for overlay to close and focus to settle...",
+                    wait
+                );
+                std::thread::sleep(wait);
+                log::info!("DAEMON: Wait complete, proceeding with click");
+            }
+
+            let btn = request_button(button);
             log::info!("DAEMON: Button mapped: {} -> {:?}", button, btn);
 
             let states: Vec<MouseButtonState> = button_states
@@ -132,20 +383,203 @@ fn handle_connection(
                 .collect();
             log::info!("DAEMON: Button states converted: {:?}", states);
 
-            mouse.click(x, y, btn, &states, repeat, absolute)
+            let output_scale = output.as_deref().and_then(|name| {
+                let found = outputs.get(name).copied();
+                if found.is_none() {
+                    log::warn!("DAEMON: unknown output {:?}; using the default scale", name);
+                }
+                found
+            });
+            mouse.set_output(output_scale);
+            *held = held_after(*held, btn, &states);
+            let result = mouse
+                .click(move_first.then_some((x, y)), btn, &states, repeat, absolute)
+                .map(|emitted| match emitted {
+                    Some((x, y)) => Response::Position { x, y },
+                    None => Response::Ok,
+                });
+            mouse.set_output(None);
+            result
         }
         Request::DoMouseAction { key, mode } => {
             log::info!("DAEMON: Processing DoMouseAction request (not implemented)");
             log::info!("  key={}, mode={:?}", key, mode);
-            Ok(())
+            Ok(Response::Ok)
         }
-    };
+        Request::Key { key } => {
+            log::info!("DAEMON: Processing Key request");
+            log::info!("  key={}", key);
+            key.parse::<KeyCode>()
+                .map_err(|_| anyhow!("unknown key name {key:?}"))
+                .and_then(|code| mouse.key(code))
+                .map(|_| Response::Ok)
+        }
+        Request::Collect { timeout_ms } => {
+            log::info!("DAEMON: Processing Collect request");
+            log::info!("  timeout_ms={}", timeout_ms);
+            let timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+            backends::collect(cfg, window_system, timeout).map(|(backend, result)| {
+                log::info!(
+                    "DAEMON: Collected {} children via {} (partial={})",
+                    result.children.len(),
+                    backend,
+                    result.partial
+                );
+                collected.clone_from(&result.children);
+                Response::Children(result)
+            })
+        }
+        Request::ClickElement {
+            id,
+            dx_frac,
+            dy_frac,
+        } => {
+            log::info!("DAEMON: Processing ClickElement request");
+            log::info!("  id={}, dx_frac={}, dy_frac={}", id, dx_frac, dy_frac);
+            element_point(collected, id, dx_frac, dy_frac).and_then(|(x, y)| {
+                log::info!("DAEMON: Element {} resolves to ({}, {})", id, x, y);
+                mouse
+                    .click(
+                        Some((x, y)),
+                        MouseButton::Left,
+                        &[MouseButtonState::Down, MouseButtonState::Up],
+                        1,
+                        true,
+                    )
+                    .map(|emitted| match emitted {
+                        Some((x, y)) => Response::Position { x, y },
+                        None => Response::Ok,
+                    })
+            })
+        }
+        Request::ReloadConfig => {
+            log::info!("DAEMON: Processing ReloadConfig request");
+            reload_from(&default_config_path(), cfg, mouse).map(|_| {
+                log::info!("DAEMON: Config reloaded");
+                Response::Ok
+            })
+        }
+        Request::Cancel => {
+            log::info!("DAEMON: Processing Cancel request");
+            clear_cancel();
+            match held.take() {
+                Some(btn) => {
+                    log::info!(
+                        "DAEMON: Releasing {:?}, still held by an interrupted drag",
+                        btn
+                    );
+                    mouse
+                        .click(None, btn, &[MouseButtonState::Up], 1, true)
+                        .map(|_| Response::Ok)
+                }
+                None => Ok(Response::Ok),
+            }
+        }
+        Request::Sequence { steps } => {
+            log::info!("DAEMON: Processing Sequence of {} steps", steps.len());
+            let total = steps.len() as i32;
+            let mut last = Ok(Response::Ok);
+            for (idx, step) in steps.into_iter().enumerate() {
+                check_cancel(idx as i32, total)?;
+                last = execute(step, mouse, cfg, window_system, outputs, collected, held);
+                if last.is_err() {
+                    break;
+                }
+            }
+            last
+        }
+        Request::Gesture { kind, at } => {
+            log::info!("DAEMON: Processing Gesture request");
+            log::info!("  kind={:?}, at={:?}", kind, at);
+            let action = gesture_action(kind)?;
+            log::info!("DAEMON: Gesture emulated as {:?}", action);
+            if let Some((ax, ay)) = at {
+                mouse.r#move(ax, ay, true)?;
+            }
+            match action {
+                GestureAction::Scroll(x, y) => mouse.scroll(x, y),
+                GestureAction::Zoom(notches) => mouse.zoom(notches),
+            }
+            .map(|_| Response::Ok)
+        }
+        Request::Drag { x, y, area, settle } => {
+            log::info!("DAEMON: Processing Drag request");
+            log::info!("  x={}, y={}, area={:?}, settle={}", x, y, area, settle);
+            std::thread::sleep(settle_wait(settle));
+            let btn = MouseButton::Left;
+            let press = [MouseButtonState::Down];
+            mouse.click(None, btn, &press, 1, false)?;
+            *held = held_after(*held, btn, &press);
+
+            let (sx, sy) = area.map_or((0, 0), |area| {
+                drag_edge_scroll((x, y), area, cfg.mouse.drag_edge_margin)
+            });
+            let steps = if (sx, sy) == (0, 0) {
+                0
+            } else {
+                cfg.mouse.drag_edge_scroll_steps
+            };
+            let step = cfg.mouse.scroll_pixel_sensitivity;
+            let dropped = mouse.r#move(x, y, true).and_then(|emitted| {
+                if steps > 0 {
+                    log::info!(
+                        "DAEMON: Drop near the edge; scrolling {:?} x{}",
+                        (sx, sy),
+                        steps
+                    );
+                }
+                for idx in 0..steps {
+                    check_cancel(idx as i32, steps as i32)?;
+                    mouse.scroll(sx * step, sy * step)?;
+                    std::thread::sleep(DRAG_EDGE_SCROLL_PAUSE);
+                }
+                Ok(emitted)
+            });
+            // A Cancel on its way releases the button itself
+            if dropped.is_err() && cancel_pending() {
+                return dropped.map(|(x, y)| Response::Position { x, y });
+            }
+
+            let release = [MouseButtonState::Up];
+            *held = held_after(*held, btn, &release);
+            mouse.click(None, btn, &release, 1, true)?;
+            dropped.map(|(x, y)| Response::Position { x, y })
+        }
+        Request::Logged { request, .. } => execute(
+            *request,
+            mouse,
+            cfg,
+            window_system,
+            outputs,
+            collected,
+            held,
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_connection(
+    stream: &mut UnixStream,
+    req: Request,
+    mouse: &mut dyn Pointer,
+    cfg: &mut Config,
+    window_system: &WindowSystem,
+    outputs: &HashMap<String, OutputScale>,
+    collected: &mut Vec<Child>,
+    held: &mut Option<MouseButton>,
+) -> Result<()> {
+    let (req, verbose) = req.verbosity();
+    let name = req.name();
+    let guard = TerseLogGuard::new(verbose);
+    log::info!("════════════════════════════════════════════════════════════════");
+    log::info!("DAEMON: New connection received on socket");
+    let result = execute(req, mouse, cfg, window_system, outputs, collected, held);
 
     log::info!("DAEMON: Request processing completed");
     let resp = match result {
-        Ok(_) => {
-            log::info!("DAEMON: Request successful, sending OK response");
-            Response::Ok
+        Ok(resp) => {
+            log::info!("DAEMON: Request successful, sending {:?} response", resp);
+            resp
         }
         Err(err) => {
             log::error!("DAEMON: Request failed with error: {}", err);
@@ -161,5 +595,471 @@ fn handle_connection(
     stream.flush()?;
     log::info!("DAEMON: Response sent successfully");
     log::info!("════════════════════════════════════════════════════════════════");
+    drop(guard);
+    if !verbose {
+        log::info!("DAEMON: {name} -> {resp:?}");
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_hintsx::config::{AbsoluteMapping, MouseConfig, MoveTool, ScrollMode};
+    use std::sync::Mutex;
+    use std::thread::ThreadId;
+
+    /// Pointer that records what it was asked to emit, optionally flagging a
+    /// cancel once it has moved `cancel_after_moves` times.
+    #[derive(Default)]
+    struct RecordingPointer {
+        events: Vec<String>,
+        /// Outputs the following moves were mapped through, in order
+        outputs: Vec<Option<OutputScale>>,
+        cancel_after_moves: Option<usize>,
+    }
+
+    impl Pointer for RecordingPointer {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        fn scroll(&mut self, x: i32, y: i32) -> Result<()> {
+            self.events.push(format!("scroll {x} {y}"));
+            Ok(())
+        }
+
+        fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)> {
+            self.events.push(format!("move {x} {y} {absolute}"));
+            if self.cancel_after_moves == Some(self.events.len()) {
+                request_cancel();
+            }
+            Ok((x, y))
+        }
+
+        fn click(
+            &mut self,
+            target: Option<(i32, i32)>,
+            button: MouseButton,
+            button_states: &[MouseButtonState],
+            _repeat: u32,
+            _absolute: bool,
+        ) -> Result<Option<(i32, i32)>> {
+            self.events
+                .push(format!("click {target:?} {button:?} {button_states:?}"));
+            Ok(target)
+        }
+
+        fn key(&mut self, key: KeyCode) -> Result<()> {
+            self.events.push(format!("key {key:?}"));
+            Ok(())
+        }
+
+        fn set_output(&mut self, output: Option<OutputScale>) {
+            self.outputs.push(output);
+        }
+
+        fn configure(&mut self, cfg: &MouseConfig) {
+            let (move_tool, click_tool) = cfg.tools();
+            self.events.push(format!(
+                "configure {move_tool:?} {click_tool:?} {:?} {} {:?}",
+                cfg.scroll_mode, cfg.smooth_scroll_steps, cfg.absolute_mapping
+            ));
+        }
+    }
+
+    /// Logger keeping every record with the thread that logged it.
+    struct Capture(Mutex<Vec<(ThreadId, String)>>);
+
+    impl log::Log for Capture {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let line = format!("{}", record.args());
+            let thread = std::thread::current().id();
+            self.0.lock().unwrap().push((thread, line));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    /// Held by tests that run requests, since a pending cancel drops every request.
+    static DAEMON: Mutex<()> = Mutex::new(());
+
+    /// Lines this thread logged while running `request` through `handle_connection`.
+    fn logged_lines(request: Request) -> Vec<String> {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_boxed_logger(Box::new(RequestLogger(&CAPTURE))).unwrap();
+            log::set_max_level(log::LevelFilter::Info);
+        });
+        let _daemon = DAEMON.lock().unwrap();
+        let this = std::thread::current().id();
+        CAPTURE
+            .0
+            .lock()
+            .unwrap()
+            .retain(|(thread, _)| *thread != this);
+
+        let (mut stream, _client) = UnixStream::pair().unwrap();
+        let window_system = WindowSystem::detect("x11").unwrap();
+        handle_connection(
+            &mut stream,
+            request,
+            &mut RecordingPointer::default(),
+            &mut Config::default(),
+            &window_system,
+            &HashMap::new(),
+            &mut Vec::new(),
+            &mut None,
+        )
+        .unwrap();
+
+        let lines = CAPTURE.0.lock().unwrap();
+        lines
+            .iter()
+            .filter(|(thread, _)| *thread == this)
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+
+    fn move_request() -> Request {
+        Request::Move {
+            x: 10,
+            y: 20,
+            absolute: false,
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn terse_request_logs_no_banner_lines() {
+        let lines = logged_lines(Request::Logged {
+            verbose: false,
+            request: Box::new(move_request()),
+        });
+        assert_eq!(lines, ["DAEMON: Move -> Position { x: 10, y: 20 }"]);
+
+        let lines = logged_lines(move_request());
+        assert!(lines.iter().any(|line| line.starts_with("════")));
+        assert!(lines.contains(&"DAEMON: Processing Move request".to_string()));
+        assert!(lines.contains(&"DAEMON: Response sent successfully".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("Move ->")));
+    }
+
+    #[test]
+    fn scaled_scroll_repeats_up_to_the_limit() {
+        assert_eq!(scaled_scroll((0, 1), 0, 20), (0, 1));
+        assert_eq!(scaled_scroll((2, -1), 3, 20), (6, -3));
+        assert_eq!(scaled_scroll((0, 1), 500, 20), (0, 20));
+        assert_eq!(scaled_scroll((0, i32::MAX), 2, 20), (0, i32::MAX));
+        assert_eq!(scaled_scroll((0, 1), 4, 0), (0, 1));
+    }
+
+    #[test]
+    fn drag_edge_scroll_only_near_the_edges() {
+        let area = (100, 100, 400, 300);
+        assert_eq!(drag_edge_scroll((300, 250), area, 20), (0, 0));
+        // Bottom-left corner: left and down
+        assert_eq!(drag_edge_scroll((105, 390), area, 20), (-1, -1));
+        // Top-right corner: right and up
+        assert_eq!(drag_edge_scroll((499, 100), area, 20), (1, 1));
+        assert_eq!(drag_edge_scroll((105, 105), area, 0), (0, 0));
+        // The margin covers [100, 120) on the left and [480, 500) on the right
+        assert_eq!(drag_edge_scroll((119, 250), area, 20), (-1, 0));
+        assert_eq!(drag_edge_scroll((120, 250), area, 20), (0, 0));
+        assert_eq!(drag_edge_scroll((479, 250), area, 20), (0, 0));
+        assert_eq!(drag_edge_scroll((480, 250), area, 20), (1, 0));
+        assert_eq!(drag_edge_scroll((300, 119), area, 20), (0, 1));
+        assert_eq!(drag_edge_scroll((300, 380), area, 20), (0, -1));
+        // Off by default, so a drop on the very edge just releases
+        let margin = Config::default().mouse.drag_edge_margin;
+        assert_eq!(drag_edge_scroll((100, 100), area, margin), (0, 0));
+    }
+
+    #[test]
+    fn drag_near_the_bottom_scrolls_down_before_releasing() {
+        let _daemon = DAEMON.lock().unwrap();
+        let mut cfg = Config::default();
+        cfg.mouse.drag_edge_margin = 20;
+        cfg.mouse.drag_edge_scroll_steps = 2;
+        let window_system = WindowSystem::detect("x11").unwrap();
+        let mut drag = |y: i32| {
+            let mut mouse = RecordingPointer::default();
+            let mut held = None;
+            let request = Request::Drag {
+                x: 300,
+                y,
+                area: Some((100, 100, 400, 300)),
+                settle: false,
+            };
+            let response = execute(
+                request,
+                &mut mouse,
+                &mut cfg,
+                &window_system,
+                &HashMap::new(),
+                &mut Vec::new(),
+                &mut held,
+            )
+            .unwrap();
+            assert!(matches!(response, Response::Position { x: 300, .. }));
+            assert!(held.is_none());
+            mouse.events
+        };
+
+        let step = Config::default().mouse.scroll_pixel_sensitivity;
+        let down = format!("scroll 0 {}", -step);
+        assert_eq!(
+            drag(390),
+            [
+                "click None Left [Down]",
+                "move 300 390 true",
+                down.as_str(),
+                down.as_str(),
+                "click None Left [Up]",
+            ]
+        );
+        // Mid-area drops just release
+        assert_eq!(
+            drag(250),
+            [
+                "click None Left [Down]",
+                "move 300 250 true",
+                "click None Left [Up]",
+            ]
+        );
+    }
+
+    #[test]
+    fn settle_wait_only_when_asked() {
+        assert_eq!(settle_wait(true), SETTLE_WAIT);
+        assert_eq!(settle_wait(false), Duration::ZERO);
+
+        // An unmapped overlay's click goes out without the pause
+        let _daemon = DAEMON.lock().unwrap();
+        let started = std::time::Instant::now();
+        run(
+            click_request(true),
+            &mut RecordingPointer::default(),
+            &mut None,
+        )
+        .unwrap();
+        assert!(started.elapsed() < SETTLE_WAIT);
+    }
+
+    #[test]
+    fn element_point_resolves_fractions_of_a_collected_element() {
+        let collected = [Child {
+            absolute_x: 100,
+            absolute_y: 200,
+            width: 40,
+            height: 20,
+            role: None,
+            accessible_path: None,
+            name: None,
+            source: None,
+        }];
+        assert_eq!(element_point(&collected, 0, 0.25, 0.5).unwrap(), (110, 210));
+        assert_eq!(element_point(&collected, 0, 1.5, -1.0).unwrap(), (139, 200));
+        let err = element_point(&collected, 1, 0.5, 0.5).unwrap_err();
+        assert!(err.to_string().contains("no element 1"), "{err}");
+        assert!(element_point(&[], 0, 0.5, 0.5).is_err());
+    }
+
+    fn run(
+        request: Request,
+        mouse: &mut RecordingPointer,
+        held: &mut Option<MouseButton>,
+    ) -> Result<Response> {
+        let window_system = WindowSystem::detect("x11").unwrap();
+        execute(
+            request,
+            mouse,
+            &mut Config::default(),
+            &window_system,
+            &HashMap::new(),
+            &mut Vec::new(),
+            held,
+        )
+    }
+
+    #[test]
+    fn cancel_mid_glide_stops_emits_and_releases_the_button() {
+        let _daemon = DAEMON.lock().unwrap();
+        let mut mouse = RecordingPointer {
+            cancel_after_moves: Some(3),
+            ..Default::default()
+        };
+        // A drag pressed the button and is now gliding to its drop point
+        let mut held = Some(MouseButton::Left);
+        let glide = Request::Move {
+            x: 100,
+            y: 0,
+            absolute: false,
+            duration_ms: Some(160),
+        };
+        assert!(run(glide, &mut mouse, &mut held).is_err());
+        assert_eq!(mouse.events.len(), 3);
+
+        // Queued before the Cancel reached the main thread: dropped, not run
+        assert!(run(move_request(), &mut mouse, &mut held).is_err());
+        assert_eq!(mouse.events.len(), 3);
+
+        run(Request::Cancel, &mut mouse, &mut held).unwrap();
+        assert_eq!(mouse.events[3..], ["click None Left [Up]"]);
+        assert!(held.is_none());
+        assert!(!cancel_pending());
+
+        // The same glide over twice the distance gets twice as far by each step
+        let short = glide_positions(&mouse.events[..3]);
+        let mut mouse = RecordingPointer {
+            cancel_after_moves: Some(3),
+            ..Default::default()
+        };
+        let glide = Request::Move {
+            x: 200,
+            y: 0,
+            absolute: false,
+            duration_ms: Some(160),
+        };
+        assert!(run(glide, &mut mouse, &mut None).is_err());
+        run(Request::Cancel, &mut mouse, &mut None).unwrap();
+        let long = glide_positions(&mouse.events);
+        assert_eq!(long.len(), 3);
+        assert!(short.windows(2).all(|pair| pair[0] < pair[1]));
+        for (short, long) in short.iter().zip(&long) {
+            assert!((long - 2 * short).abs() <= 1, "{long} vs 2 * {short}");
+        }
+    }
+
+    /// How far right the relative moves among recorded `events` got after each one.
+    fn glide_positions(events: &[String]) -> Vec<i32> {
+        events
+            .iter()
+            .filter_map(|event| event.strip_prefix("move "))
+            .filter_map(|event| event.split(' ').next()?.parse::<i32>().ok())
+            .scan(0, |x, dx| {
+                *x += dx;
+                Some(*x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reload_hands_pointer_settings_to_the_pointer() {
+        let mut mouse = RecordingPointer::default();
+        let mut cfg = Config::default();
+        let mut new_cfg = Config::default();
+        new_cfg.mouse.move_tool = MoveTool::Uinput;
+        new_cfg.mouse.scroll_mode = ScrollMode::Smooth;
+        new_cfg.mouse.smooth_scroll_steps = 6;
+        new_cfg.mouse.absolute_mapping = AbsoluteMapping::Layout;
+        reload_config(&mut cfg, new_cfg, &mut mouse);
+        assert_eq!(mouse.events, ["configure Uinput Ydotool Smooth 6 Layout"]);
+        assert_eq!(cfg.mouse.scroll_mode, ScrollMode::Smooth);
+
+        // Turning on force_uinput switches both tools without a restart
+        let mut new_cfg = cfg.clone();
+        new_cfg.mouse.force_uinput = true;
+        reload_config(&mut cfg, new_cfg, &mut mouse);
+        assert_eq!(mouse.events[1], "configure Uinput Uinput Smooth 6 Layout");
+    }
+
+    #[test]
+    fn reload_applies_an_edited_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let mut mouse = RecordingPointer::default();
+        let mut cfg = Config::default();
+
+        std::fs::write(
+            &path,
+            r#"{"mouse": {"max_repeat": 7, "scroll_mode": "smooth"}}"#,
+        )
+        .unwrap();
+        reload_from(&path, &mut cfg, &mut mouse).unwrap();
+        assert_eq!(cfg.mouse.max_repeat, 7);
+        assert_eq!(cfg.mouse.scroll_mode, ScrollMode::Smooth);
+        assert_eq!(mouse.events.len(), 1);
+
+        // A broken edit is reported and the running config stays
+        std::fs::write(&path, "{ not json").unwrap();
+        let err = reload_from(&path, &mut cfg, &mut mouse).unwrap_err();
+        assert!(err.to_string().contains("invalid config"), "{err}");
+        assert_eq!(cfg.mouse.max_repeat, 7);
+        assert_eq!(mouse.events.len(), 1);
+    }
+
+    fn click_request(move_first: bool) -> Request {
+        Request::Click {
+            x: 30,
+            y: 40,
+            button: 0,
+            button_states: vec![1, 0],
+            repeat: 1,
+            absolute: true,
+            move_first,
+            output: None,
+            settle: false,
+        }
+    }
+
+    #[test]
+    fn click_without_move_first_clicks_in_place() {
+        let _daemon = DAEMON.lock().unwrap();
+        let mut mouse = RecordingPointer::default();
+        let response = run(click_request(false), &mut mouse, &mut None).unwrap();
+        assert!(matches!(response, Response::Ok));
+        assert_eq!(mouse.events, ["click None Left [Down, Up]"]);
+
+        let mut mouse = RecordingPointer::default();
+        let response = run(click_request(true), &mut mouse, &mut None).unwrap();
+        assert!(matches!(response, Response::Position { x: 30, y: 40 }));
+        assert_eq!(mouse.events, ["click Some((30, 40)) Left [Down, Up]"]);
+    }
+
+    #[test]
+    fn click_on_a_named_output_uses_its_scale() {
+        let _daemon = DAEMON.lock().unwrap();
+        let outputs = HashMap::from([(
+            "DP-2".to_string(),
+            OutputScale {
+                origin: (1920, 0),
+                scale: 2,
+            },
+        )]);
+        let window_system = WindowSystem::detect("x11").unwrap();
+        let click = |output: &str| {
+            let mut mouse = RecordingPointer::default();
+            let mut request = click_request(true);
+            if let Request::Click { output: tagged, .. } = &mut request {
+                *tagged = Some(output.to_string());
+            }
+            execute(
+                request,
+                &mut mouse,
+                &mut Config::default(),
+                &window_system,
+                &outputs,
+                &mut Vec::new(),
+                &mut None,
+            )
+            .unwrap();
+            mouse.outputs
+        };
+
+        // Mapped through DP-2 for the click, then back to the default
+        let dp2 = OutputScale {
+            origin: (1920, 0),
+            scale: 2,
+        };
+        assert_eq!(click("DP-2"), [Some(dp2), None]);
+        // Unknown outputs fall back to the default scale
+        assert_eq!(click("HDMI-A-9"), [None, None]);
+    }
+}
@@ -1,17 +1,86 @@
 use anyhow::{Result, anyhow};
-use rust_hintsx::backends::build_backends;
-use rust_hintsx::config::Config;
-use rust_hintsx::generate_hints;
-use rust_hintsx::ipc::ensure_daemon_running;
-use rust_hintsx::ui::overlay::launch_overlay;
-use rust_hintsx::window_system::WindowSystem;
+use rust_hintsx::backends::{BackendResult, collect};
+use rust_hintsx::config::{Config, FocusChange, OverlayTarget, SessionState};
+use rust_hintsx::consts::UNIX_DOMAIN_SOCKET_FILE;
+use rust_hintsx::coords::{intersect, sized_extents};
+use rust_hintsx::hints::{
+    Child, CoordSpace, LabelCache, drop_clustered, filter_roles, fit_label_length,
+    generate_partitioned, merge_text_runs, retain_in_area, sort_by_distance, sort_rtl,
+    stable_hints,
+};
+use rust_hintsx::ipc::{Request, Response, ensure_daemon_running, send, set_verbose};
+use rust_hintsx::ui::layout::physical_alphabet;
+use rust_hintsx::ui::notice::launch_notice;
+use rust_hintsx::ui::overlay::{launch_overlay, monitor_geometry};
+use rust_hintsx::window_system::{WindowSystem, focus_changed};
+
+/// Collections repeated because focus moved during them before giving up.
+const MAX_RECOLLECT: u32 = 2;
 
 fn main() -> Result<()> {
     env_logger::init();
     let start_total = std::time::Instant::now();
 
+    // --reload: have the running daemon re-read the config file, then exit
+    if std::env::args().skip(1).any(|arg| arg == "--reload") {
+        require_daemon(&Config::load(), daemon_socket(), notify_send)?;
+        return match send(Request::ReloadConfig)? {
+            Response::Error(err) => Err(anyhow!("hintsd rejected the config: {err}")),
+            _ => {
+                println!("hintsd reloaded its config");
+                Ok(())
+            }
+        };
+    }
+
+    // --cancel: stop whatever the daemon is doing and release held buttons
+    if std::env::args().skip(1).any(|arg| arg == "--cancel") {
+        require_daemon(&Config::load(), daemon_socket(), notify_send)?;
+        return match send(Request::Cancel)? {
+            Response::Error(err) => Err(anyhow!("hintsd failed to cancel: {err}")),
+            _ => Ok(()),
+        };
+    }
+
     let t0 = std::time::Instant::now();
-    let cfg = Config::load();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut cfg = Config::load();
+
+    // --doctor: report missing tools and permissions, failing if any probe failed
+    if std::env::args().skip(1).any(|arg| arg == "--doctor") {
+        let report = rust_hintsx::doctor::run(&cfg);
+        println!("{report}");
+        if report.overall() == rust_hintsx::doctor::Status::Fail {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let target_override = overlay_target_override();
+
+    // --print-config: show the config this run would use (defaults, config file,
+    // session state, env, flags) and exit without touching anything
+    if args.iter().any(|arg| arg == "--print-config") {
+        if let Some(target) = target_override {
+            cfg.overlay_target = target;
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&effective_config(cfg, &args)?)?
+        );
+        return Ok(());
+    }
+    apply_flags(&mut cfg, &args);
+
+    // An explicit target for this run also becomes the default for the next ones
+    if let Some(target) = target_override {
+        cfg.overlay_target = target;
+        let session = SessionState {
+            overlay_target: Some(cfg.overlay_target.clone()),
+        };
+        if let Err(err) = session.save() {
+            log::warn!("failed to save session state: {err}");
+        }
+    }
     println!("[BENCH] Config load: {:?}", t0.elapsed());
 
     let t1 = std::time::Instant::now();
@@ -30,48 +99,51 @@ fn main() -> Result<()> {
     let debug_overlay = std::env::var("HINTSX_DEBUG_OVERLAY")
         .map(|v| v.eq_ignore_ascii_case("1") || v.eq_ignore_ascii_case("true"))
         .unwrap_or(false);
+    set_verbose(cfg.overlay.verbose_requests || debug_overlay);
 
     let t2 = std::time::Instant::now();
-    ensure_daemon_running()?;
+    require_daemon(&cfg, daemon_socket(), notify_send)?;
     println!("[BENCH] Daemon check: {:?}", t2.elapsed());
 
-    let mut children = Vec::new();
-    let mut focus_extents = None;
-    let mut backend_used = String::new();
-
     let t3 = std::time::Instant::now();
-    for mut backend in build_backends(&cfg, &window_system) {
-        let t_backend = std::time::Instant::now();
-        match backend.get_children() {
-            Ok(result) if !result.children.is_empty() => {
-                println!(
-                    "[BENCH] Backend {} success: {:?}",
-                    backend.name(),
-                    t_backend.elapsed()
-                );
-                children = result.children;
-                focus_extents = result.focus_extents;
-                backend_used = backend.name().into();
-                break;
-            }
-            Ok(_) => {
-                println!(
-                    "[BENCH] Backend {} empty: {:?}",
-                    backend.name(),
-                    t_backend.elapsed()
-                );
-                log::warn!("backend {} returned zero children", backend.name());
+    let focused_id = || match cfg.backends.focus_change {
+        FocusChange::Ignore => None,
+        _ => window_system.active_window_id(),
+    };
+    let mut recollects = 0;
+    let (backend_used, result, collected_for) = if cfg.hints.whole_window {
+        let Some(extents) = window_system.active_window_geometry() else {
+            let err = anyhow!("could not get the focused window's geometry");
+            return no_hints(&cfg, &window_system, err);
+        };
+        let result = BackendResult {
+            children: vec![Child::for_window(extents)],
+            focus_extents: Some(extents),
+            partial: false,
+        };
+        ("window", result, focused_id())
+    } else {
+        loop {
+            let before = focused_id();
+            let collected = match collect(&cfg, &window_system, cfg.backends.collect_timeout()) {
+                Ok(collected) => collected,
+                Err(err) => return no_hints(&cfg, &window_system, err),
+            };
+            let after = focused_id();
+            if !focus_changed(before.as_deref(), after.as_deref()) {
+                break (collected.0, collected.1, after);
             }
-            Err(err) => {
-                println!(
-                    "[BENCH] Backend {} failed: {:?}",
-                    backend.name(),
-                    t_backend.elapsed()
-                );
-                log::warn!("backend {} failed: {err}", backend.name());
+            if cfg.backends.focus_change == FocusChange::Abort || recollects == MAX_RECOLLECT {
+                return focus_lost(&cfg, &window_system);
             }
+            recollects += 1;
+            println!(
+                "DEBUG: focus moved from {before:?} to {after:?} during collection; collecting again"
+            );
         }
-    }
+    };
+    let mut children = result.children;
+    let mut focus_extents = sized_extents(result.focus_extents);
     println!("[BENCH] Total backend search: {:?}", t3.elapsed());
 
     // If no extents came back but we still want window-scoped overlay, try xdotool geometry.
@@ -89,14 +161,103 @@ fn main() -> Result<()> {
         println!("[BENCH] Fallback geometry: {:?}", t_fallback.elapsed());
     }
 
+    let collected = children.len();
+
+    // --monitor <name>: hint only what lies on that output, and keep the overlay there
+    if let Some(name) = arg_value(&args, "--monitor") {
+        gtk4::init()?;
+        let area = monitor_geometry(&name).ok_or_else(|| anyhow!("no monitor named {name:?}"))?;
+        retain_in_area(&mut children, area);
+        focus_extents = match focus_extents {
+            Some(extents) => intersect(extents, area),
+            None => Some(area),
+        };
+        if children.is_empty() || focus_extents.is_none() {
+            let err = anyhow!("nothing to hint on monitor {name:?}");
+            return no_hints(&cfg, &window_system, err);
+        }
+    }
+
+    if cfg.hints.scoped {
+        let mut containers = children.clone();
+        filter_roles(&mut containers, &cfg.hints.container_roles);
+        containers.retain(|c| c.accessible_path.is_some());
+        if containers.is_empty() {
+            println!("DEBUG: no containers to scope to; hinting everything");
+            cfg.hints.scoped = false;
+        } else {
+            children = containers;
+        }
+    }
+    if !cfg.hints.scoped && !cfg.hints.whole_window {
+        filter_roles(&mut children, &cfg.hints.only_roles);
+        if children.is_empty() && collected > 0 {
+            let err = anyhow!(
+                "no elements match hints.only_roles {:?}",
+                cfg.hints.only_roles
+            );
+            return no_hints(&cfg, &window_system, err);
+        }
+    }
+    if cfg.hints.merge_text_runs {
+        children = merge_text_runs(children, cfg.hints.merge_text_gap);
+    }
+    children = drop_clustered(children, cfg.hints.min_distance);
     if children.is_empty() {
-        return Err(anyhow!(
-            "no children gathered from any backend; check accessibility setup"
-        ));
+        return no_hints(&cfg, &window_system, anyhow!("nothing to hint"));
+    }
+
+    if cfg.hints.rtl {
+        sort_rtl(&mut children);
+    }
+    if cfg.hints.cursor_first {
+        match window_system.cursor_position() {
+            Some(cursor) => sort_by_distance(&mut children, cursor),
+            None => log::warn!("no pointer position to order hints by; using reading order"),
+        }
     }
 
+    if cfg.hints.physical_alphabet && gtk4::init().is_ok() {
+        cfg.alphabet = physical_alphabet(&cfg.alphabet);
+    }
+    cfg.alphabet = fit_label_length(
+        &mut children,
+        &cfg.alphabet,
+        &cfg.hints.fallback_alphabet,
+        cfg.hints.max_label_length,
+    );
+
     let t4 = std::time::Instant::now();
-    let hints = generate_hints(&children, &cfg.alphabet);
+    let previous = cfg
+        .hints
+        .stable_labels
+        .then(LabelCache::load)
+        .flatten()
+        .filter(|_| cfg.hints.role_prefixes.is_empty() && !cfg.hints.scoped)
+        .filter(|cache| cache.focus_extents == focus_extents);
+    let hints = match previous {
+        Some(cache) => stable_hints(
+            &cache.hints,
+            &children,
+            &cfg.alphabet,
+            &cfg.hints.reserved_labels,
+        ),
+        None => generate_partitioned(
+            &children,
+            &cfg.alphabet,
+            &cfg.hints.reserved_labels,
+            &cfg.hints.role_prefixes,
+        ),
+    };
+    if cfg.hints.stable_labels && !cfg.hints.scoped {
+        let cache = LabelCache {
+            focus_extents,
+            hints: hints.clone(),
+        };
+        if let Err(err) = cache.save() {
+            log::warn!("failed to save hint labels: {err}");
+        }
+    }
     println!("[BENCH] Hint generation: {:?}", t4.elapsed());
 
     log::info!(
@@ -105,7 +266,230 @@ fn main() -> Result<()> {
         backend_used
     );
 
+    // Too late to collect again; stale hints would click into the wrong window
+    if focus_changed(collected_for.as_deref(), focused_id().as_deref()) {
+        return focus_lost(&cfg, &window_system);
+    }
+
+    // --annotate <out.png>: draw the hints onto a screenshot instead of showing them
+    if let Some(path) = arg_value(&args, "--annotate") {
+        return annotate(&cfg, &window_system, &hints, std::path::Path::new(&path));
+    }
+
     println!("[BENCH] Pre-launch total: {:?}", start_total.elapsed());
-    launch_overlay(cfg, window_system, focus_extents, hints, debug_overlay);
+    launch_overlay(
+        cfg,
+        window_system,
+        focus_extents,
+        hints,
+        children,
+        CoordSpace::Screen,
+        debug_overlay,
+    );
+    Ok(())
+}
+
+/// Where `hintsd` listens.
+fn daemon_socket() -> &'static std::path::Path {
+    std::path::Path::new(UNIX_DOMAIN_SOCKET_FILE)
+}
+
+/// Fail when `hintsd` isn't listening on `socket`, also passing the error to
+/// `notify` when `overlay.notify_missing_daemon` is set: launched from a keybind,
+/// `hintsx` has no terminal for the error to show up in.
+fn require_daemon(cfg: &Config, socket: &std::path::Path, notify: impl FnOnce(&str)) -> Result<()> {
+    ensure_daemon_running(socket).inspect_err(|err| {
+        if cfg.overlay.notify_missing_daemon {
+            notify(&err.to_string());
+        }
+    })
+}
+
+/// Desktop notification through `notify-send`.
+fn notify_send(body: &str) {
+    let shown = std::process::Command::new("notify-send")
+        .args(["--app-name=hintsx", "hintsx: start hintsd", body])
+        .status()
+        .is_ok_and(|status| status.success());
+    if !shown {
+        log::warn!("could not show a desktop notification (is notify-send installed?)");
+    }
+}
+
+/// What `hintsx` does when it ends up with no hints to show.
+#[derive(Debug, PartialEq)]
+enum EmptyAction {
+    /// Flash this notice, so the user sees that `hintsx` ran
+    Notice(&'static str),
+    /// Exit with the error
+    Fail,
+}
+
+/// `Notice(notice)`, or `Fail` when `overlay.empty_notice_ms` is 0.
+fn empty_action(cfg: &Config, notice: &'static str) -> EmptyAction {
+    if cfg.overlay.empty_notice_ms == 0 {
+        EmptyAction::Fail
+    } else {
+        EmptyAction::Notice(notice)
+    }
+}
+
+/// Nothing to hint: flash a notice, or fail with `err`; see [`empty_action`].
+fn no_hints(cfg: &Config, window_system: &WindowSystem, err: anyhow::Error) -> Result<()> {
+    let EmptyAction::Notice(notice) = empty_action(cfg, "no hints found") else {
+        return Err(err);
+    };
+    log::warn!("no hints to show: {err}");
+    launch_notice(cfg, window_system, notice);
+    Ok(())
+}
+
+/// The focused window changed under the collected hints: drop them, flashing a
+/// notice or failing like [`no_hints`].
+fn focus_lost(cfg: &Config, window_system: &WindowSystem) -> Result<()> {
+    let EmptyAction::Notice(notice) = empty_action(cfg, "focus changed") else {
+        return Err(anyhow!("focused window changed while collecting hints"));
+    };
+    log::warn!("focused window changed while collecting hints; not showing them");
+    launch_notice(cfg, window_system, notice);
+    Ok(())
+}
+
+/// Write a screenshot with every hint drawn on it to `path`, for bug reports.
+#[cfg(feature = "opencv-backend")]
+fn annotate(
+    cfg: &Config,
+    window_system: &WindowSystem,
+    hints: &rust_hintsx::HintMap,
+    path: &std::path::Path,
+) -> Result<()> {
+    let drawn = rust_hintsx::backends::opencv_backend::annotate_screenshot(
+        cfg,
+        window_system,
+        hints,
+        path,
+    )?;
+    println!("wrote {} hints to {}", drawn, path.display());
     Ok(())
 }
+
+#[cfg(not(feature = "opencv-backend"))]
+fn annotate(
+    _cfg: &Config,
+    _window_system: &WindowSystem,
+    _hints: &rust_hintsx::HintMap,
+    _path: &std::path::Path,
+) -> Result<()> {
+    Err(anyhow!(
+        "--annotate needs hintsx built with the `opencv-backend` feature"
+    ))
+}
+
+/// Value of `flag` given in `args` as `--flag <value>` or `--flag=<value>`.
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .enumerate()
+        .find_map(|(idx, arg)| match arg.strip_prefix(flag) {
+            Some("") => args.get(idx + 1).cloned(),
+            Some(rest) => rest.strip_prefix('=').map(str::to_string),
+            None => None,
+        })
+}
+
+/// Turn on the settings `args` asks for on this run.
+fn apply_flags(cfg: &mut Config, args: &[String]) {
+    for arg in args {
+        match arg.as_str() {
+            // One hint covering the focused window, no backend traversal
+            "--whole-window" => cfg.hints.whole_window = true,
+            // The focused element and its siblings only
+            "--focused" => cfg.hints.focused_only = true,
+            // Hint containers first; the overlay hints inside the picked one
+            "--scoped" => cfg.hints.scoped = true,
+            _ => {}
+        }
+    }
+}
+
+/// `cfg` with the flags in `args` applied, as `--print-config` shows it: the
+/// output `--monitor` restricts hinting to goes under `"monitor"`.
+fn effective_config(mut cfg: Config, args: &[String]) -> Result<serde_json::Value> {
+    apply_flags(&mut cfg, args);
+    let mut value = serde_json::to_value(&cfg)?;
+    if let Some(name) = arg_value(args, "--monitor") {
+        value["monitor"] = name.into();
+    }
+    Ok(value)
+}
+
+/// Overlay target requested for this run through `HINTSX_OVERLAY_TARGET`.
+fn overlay_target_override() -> Option<OverlayTarget> {
+    let target = std::env::var("HINTSX_OVERLAY_TARGET").ok()?;
+    let parsed = serde_json::from_value(serde_json::Value::String(target.to_lowercase())).ok();
+    if parsed.is_none() {
+        log::warn!("ignoring unknown HINTSX_OVERLAY_TARGET {target:?}");
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_results_show_a_notice_unless_disabled() {
+        let mut cfg = Config::default();
+        assert_eq!(
+            empty_action(&cfg, "no hints found"),
+            EmptyAction::Notice("no hints found")
+        );
+        cfg.overlay.empty_notice_ms = 0;
+        assert_eq!(empty_action(&cfg, "no hints found"), EmptyAction::Fail);
+    }
+
+    #[test]
+    fn missing_daemon_fails_and_notifies_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("hints.socket");
+        let mut cfg = Config::default();
+        cfg.overlay.notify_missing_daemon = true;
+        let mut shown = None;
+        let err = require_daemon(&cfg, &socket, |body| shown = Some(body.to_string())).unwrap_err();
+        assert!(err.to_string().contains("hints.socket"));
+        assert_eq!(shown, Some(err.to_string()));
+
+        cfg.overlay.notify_missing_daemon = false;
+        let mut notified = false;
+        assert!(require_daemon(&cfg, &socket, |_| notified = true).is_err());
+        assert!(!notified);
+
+        std::fs::write(&socket, "").unwrap();
+        cfg.overlay.notify_missing_daemon = true;
+        assert!(require_daemon(&cfg, &socket, |_| panic!("hintsd is running")).is_ok());
+    }
+
+    #[test]
+    fn print_config_applies_the_run_flags() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let plain = effective_config(Config::default(), &args(&["--print-config"])).unwrap();
+        assert_eq!(plain["hints"]["whole_window"], false);
+        assert_eq!(plain["hints"]["focused_only"], false);
+        assert!(plain.get("monitor").is_none());
+
+        let flags = args(&[
+            "--print-config",
+            "--whole-window",
+            "--focused",
+            "--monitor",
+            "DP-2",
+        ]);
+        let value = effective_config(Config::default(), &flags).unwrap();
+        assert_eq!(value["hints"]["whole_window"], true);
+        assert_eq!(value["hints"]["focused_only"], true);
+        assert_eq!(value["hints"]["scoped"], false);
+        assert_eq!(value["monitor"], "DP-2");
+
+        let value = effective_config(Config::default(), &args(&["--monitor=HDMI-A-1"])).unwrap();
+        assert_eq!(value["monitor"], "HDMI-A-1");
+    }
+}
@@ -1,6 +1,9 @@
 use crate::consts::{DEFAULT_ALPHABET, default_config_path};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -21,6 +24,15 @@ pub struct Config {
 #[serde(default)]
 pub struct BackendsConfig {
     pub enable: Vec<String>,
+    /// Upper bound on hint collection in milliseconds (0 = no limit). When hit, the
+    /// children gathered so far are used.
+    pub collect_timeout_ms: u64,
+    /// What to do when the focused window changes while hints are collected
+    pub focus_change: FocusChange,
+    /// Run every enabled backend and show all their hints at once, each drawn in
+    /// its backend's `hints.source_background_colors`, instead of stopping at the
+    /// first backend that finds something
+    pub show_all: bool,
     pub atspi: AtspiConfig,
     pub opencv: OpencvConfig,
 }
@@ -31,6 +43,12 @@ pub struct AtspiConfig {
     pub states: Vec<String>,
     pub roles: Vec<String>,
     pub scale_factor: f32,
+    /// Most accessible proxies queried at once per tree level; thousands in flight
+    /// at once swamp the bus and end up slower
+    pub max_concurrent: usize,
+    /// Skip elements that aren't Showing or are scrolled out of their scroll
+    /// container, even when they still report positive extents
+    pub visible_only: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +57,14 @@ pub struct OpencvConfig {
     pub kernel_size: i32,
     pub canny_min_val: f64,
     pub canny_max_val: f64,
+    /// Only hint regions that changed since the previous run's screenshot
+    pub diff_mode: bool,
+    /// Per-pixel grayscale difference (0-255) counted as a change in diff mode
+    pub diff_threshold: f64,
+    /// Resize screenshots by this factor (0-1] before edge detection; 0.5 on a 4K
+    /// screen runs several times faster at the cost of a pixel or two of precision.
+    /// Hint rects are scaled back to full resolution
+    pub downscale: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,55 +82,353 @@ pub struct OverlayConfig {
     pub layer_shell_namespace: String,
     /// Whether to set exclusive zone (-1 for transparency)
     pub layer_shell_exclusive_zone: i32,
+    /// Set an explicit cursor on the overlay so it stays visible under exclusive keyboard grabs
+    pub keep_cursor_visible: bool,
+    /// Cursor name (CSS cursor names, e.g. "default", "crosshair")
+    pub cursor_name: String,
+    /// How long to show a "no hints found" notice when nothing was collected;
+    /// 0 exits with an error instead
+    pub empty_notice_ms: u64,
+    /// Opacity (0.0-1.0) of the whole hint layer, multiplied into every color's alpha
+    pub opacity: f64,
+    /// On X11, let pointer events outside the hint boxes through to the windows below
+    pub x11_shape_input: bool,
+    /// What the layer-shell overlay does when the window spans several monitors
+    pub spanning_window: SpanningWindow,
+    /// Fade the hints in over this many milliseconds; 0 shows them at once
+    pub animation_ms: u64,
+    /// Close the overlay after this many milliseconds without a key press; 0
+    /// keeps it open until dismissed
+    pub auto_dismiss_ms: u64,
+    /// Ignore key presses for this many milliseconds after the overlay appears, so
+    /// a key of the launch binding that is still down can't pick a hint; 0 disables it
+    pub input_grace_ms: u64,
+    /// Never animate, even with `animation_ms` set (GTK's own
+    /// `gtk-enable-animations = false` has the same effect)
+    pub reduce_motion: bool,
+    /// Publish the hints on the session bus as `xyz.hintsx.Hints`, with an
+    /// `Activate(label)` method that clicks one like typing it would
+    pub dbus_service: bool,
+    /// Have `hintsd` log each click and scroll in full instead of one summary
+    /// line; on anyway with `HINTSX_DEBUG_OVERLAY`
+    pub verbose_requests: bool,
+    /// Raise a desktop notification (through `notify-send`) when `hintsd` isn't
+    /// running, for keybind launches where the error on stderr goes unseen
+    pub notify_missing_daemon: bool,
     /// Debug overlay settings
     pub debug_overlay_enabled: bool,
     pub debug_overlay_color: (f64, f64, f64, f64),
+    /// Keyval that switches the debug tint and per-frame draw logging on and off
+    /// while the overlay is open; 0 disables it
+    pub debug_toggle_key: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct HintsStyle {
+    /// Minimum height of a hint box; boxes grow to fit the text plus `hint_padding_y`
     pub hint_height: i32,
-    pub hint_width_padding: i32,
+    /// Space between the label text and the left/right edges of its box
+    #[serde(alias = "hint_width_padding")]
+    pub hint_padding_x: i32,
+    /// Space between the label text and the top/bottom edges of its box
+    pub hint_padding_y: i32,
     pub hint_font_size: i32,
     pub hint_font_face: String,
     pub hint_font_color: (f64, f64, f64, f64),
     pub hint_pressed_font_color: (f64, f64, f64, f64),
     pub hint_background_color: (f64, f64, f64, f64),
     pub hint_uppercase: bool,
+    /// Read `alphabet` as physical key positions on a US layout and label hints with
+    /// whatever the active keyboard layout types on those keys
+    pub physical_alphabet: bool,
+    /// Multiply `hint_font_size` by the scale factor of the monitor the overlay is on
+    pub font_scale_with_dpi: bool,
+    /// When non-empty, only hint elements with these roles (e.g. ["Link"]), independent of
+    /// the roles the atspi backend traverses.
+    pub only_roles: Vec<String>,
+    /// Hint in two stages: first only the containers in `container_roles`, then,
+    /// once one is picked, the elements inside it (atspi backend only)
+    pub scoped: bool,
+    /// Roles offered as containers in the first stage of `scoped` hinting
+    pub container_roles: Vec<String>,
+    /// Skip the backends and hint the focused window itself as one element, for
+    /// quick clicks into the middle of a window
+    pub whole_window: bool,
+    /// Hint only the focused element and its siblings, e.g. to check where Tab
+    /// moved the focus in a form (atspi backend only)
+    pub focused_only: bool,
+    /// Fire a hint as soon as the typed prefix matches exactly one label.
+    pub activate_on_unique: bool,
+    /// Merge adjacent `Text` elements on the same line into a single hint
+    pub merge_text_runs: bool,
+    /// Largest horizontal gap in pixels between text elements that still merge
+    pub merge_text_gap: i32,
+    /// Labels kept out of the generated pool so the overlay can bind them to
+    /// special actions (e.g. ["z"]).
+    pub reserved_labels: Vec<String>,
+    /// Keep labels of elements that are still there when the same window is hinted
+    /// again (e.g. after scrolling a list)
+    pub stable_labels: bool,
+    /// Width in pixels of a contrasting outline stroked around label glyphs; 0 disables it
+    pub text_outline_width: f64,
+    pub text_outline_color: (f64, f64, f64, f64),
+    /// Label elements in right-to-left reading order (top-right first)
+    pub rtl: bool,
+    /// Give the first labels to the elements nearest the mouse pointer, the
+    /// likeliest targets (Hyprland and X11; elsewhere reading order is kept)
+    pub cursor_first: bool,
+    /// Draw each element's accessible name under its label (atspi backend only;
+    /// costs one extra bus call per element)
+    pub show_names: bool,
+    /// Names longer than this many characters are cut off with an ellipsis
+    pub name_max_chars: usize,
+    /// Longest label to generate; with more elements than that allows, a small
+    /// alphabet is swapped for `fallback_alphabet`, or the extra elements go
+    /// unhinted. 0 means no limit
+    pub max_label_length: u32,
+    /// Larger alphabet to label with for a run whose elements `alphabet` can't
+    /// label within `max_label_length` characters
+    pub fallback_alphabet: String,
+    /// Drop hints whose elements' centers are closer than this many pixels to
+    /// another hinted element; 0 keeps them all
+    pub min_distance: i32,
+    /// Label prefix per role (e.g. {"Link": "f", "PushButton": "d"}): those
+    /// elements get labels starting with it and no other label does. Takes
+    /// precedence over `stable_labels`
+    pub role_prefixes: HashMap<String, String>,
+    /// Remember the last clicked element and draw its hint in
+    /// `last_clicked_background_color` on the next launch, while it's still there
+    pub mark_last_clicked: bool,
+    pub last_clicked_background_color: (f64, f64, f64, f64),
+    /// Background of hints picked with `mouse.multi_select_modifier`
+    pub selected_background_color: (f64, f64, f64, f64),
+    /// More alphabets the overlay switches to with `alphabet_cycle_key`, after
+    /// the main `alphabet` (e.g. [{"name": "numbers", "alphabet": "0123456789"}])
+    pub alphabets: Vec<NamedAlphabet>,
+    /// Keyval that relabels the hints with the next of `alphabets`
+    pub alphabet_cycle_key: u32,
+    /// Hint background per backend (e.g. {"opencv": [0.6, 0.9, 0.6, 0.8]}) with
+    /// `backends.show_all`, telling apart hints from different sources; backends
+    /// without an entry use `hint_background_color`
+    pub source_background_colors: HashMap<String, (f64, f64, f64, f64)>,
+    /// Character typed by keyvals GTK can't turn into one itself (keypad keys,
+    /// dead keys), e.g. {"65456": "0"} for KP_0; takes precedence over GTK
+    pub key_aliases: HashMap<u32, char>,
+    /// JSON file whose `hints` and `overlay` style settings (colors, fonts,
+    /// padding, opacity, outline, animation) are merged over these at load, so
+    /// visual themes can be swapped without touching the rest of the config.
+    /// Relative paths are resolved against the config file's directory
+    pub theme: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedAlphabet {
+    pub name: String,
+    pub alphabet: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct MouseConfig {
+    /// Pointer backend used by `hintsd`: "uinput" (default), "ei" (libei, needs the `ei`
+    /// feature) or "virtual-pointer" (wlroots, needs the `virtual-pointer` feature).
+    pub backend: String,
     pub move_left: String,
     pub move_right: String,
     pub move_up: String,
     pub move_down: String,
+    /// Optional diagonal move keys (e.g. "y", "u", "b", "n"); empty disables them
+    pub move_up_left: String,
+    pub move_up_right: String,
+    pub move_down_left: String,
+    pub move_down_right: String,
     pub scroll_left: String,
     pub scroll_right: String,
     pub scroll_up: String,
     pub scroll_down: String,
+    /// Keyvals that scroll a page down/up (`page_scroll_steps` scroll units; a
+    /// repeat count multiplies them)
+    pub page_down_key: u32,
+    pub page_up_key: u32,
+    pub page_scroll_steps: u32,
     pub move_pixel_sensitivity: i32,
     pub move_rampup_time: f32,
     pub scroll_pixel_sensitivity: i32,
     pub scroll_rampup_time: f32,
+    /// Upper bound for a typed repeat prefix ("5j", "3<hint>")
+    pub max_repeat: u32,
     pub exit_key: u32,
+    /// Clears the typed hint prefix and repeat count without closing the overlay
+    pub cancel_key: u32,
+    /// Removes the last typed hint char (or repeat digit)
+    pub backspace_key: u32,
     pub hover_modifier: u32,
     pub grab_modifier: u32,
+    /// Treat Shift / uppercase hint chars as right-click. When false, typed case is kept
+    /// for alphabets with uppercase labels and `right_click_modifier` selects right-click.
+    pub shift_is_right_click: bool,
+    pub right_click_modifier: u32,
+    /// Modifier that focuses the hinted element and sends `activate_key` instead of clicking
+    pub key_modifier: u32,
+    /// evdev key name sent for keyboard activation, e.g. "KEY_ENTER" or "KEY_SPACE"
+    pub activate_key: String,
+    /// When non-zero, hover moves glide to the hint over this many milliseconds
+    pub hover_glide_ms: u32,
+    /// Roles whose hint anchors scrolling instead of clicking: the pointer parks on
+    /// the element and Shift+hjkl scroll it while the overlay stays open
+    pub scroll_roles: Vec<String>,
+    /// Where to click inside elements of a role, as fractions of the element's
+    /// width and height (e.g. {"ScrollBar": [0.5, 0.1]}); other roles click the center
+    pub click_offsets: HashMap<String, (f64, f64)>,
+    /// Caret position set through AT-SPI after clicking an entry or text hint
+    pub caret_placement: CaretPlacement,
+    /// Modifier that warps the pointer to the hint without clicking; 0 (the
+    /// default) disables it
+    pub warp_modifier: u32,
+    /// Keep the overlay open after a warp so another hint can follow
+    pub warp_stay_open: bool,
+    /// Modifier that scrolls the hinted element into view through AT-SPI instead
+    /// of clicking it; 0 disables it
+    pub scroll_to_modifier: u32,
+    /// Modifier that clicks a tab hint (role "PageTab") at `tab_close_offset`,
+    /// where its close button is, instead of the center; 0 disables it
+    pub tab_close_modifier: u32,
+    /// Close-button position inside a tab, as fractions of its width and height
+    pub tab_close_offset: (f64, f64),
+    /// Modifier that adds the hint to a selection instead of acting on it; 0
+    /// disables multi-select
+    pub multi_select_modifier: u32,
+    /// Key that clicks every selected hint in the order chosen
+    pub multi_select_confirm_key: u32,
+    /// Characters typed after a complete label that click it with another button
+    /// (0 left, 1 middle, 2 right), e.g. `{".": 2, ",": 1}`. When set, a complete
+    /// label waits for its suffix, or Return for a left click; empty disables it
+    pub button_suffixes: HashMap<char, u16>,
+    /// A drag dropped within this many pixels of the overlay's edge scrolls towards
+    /// that edge before releasing; 0 (the default) disables edge scrolling
+    pub drag_edge_margin: i32,
+    /// Scroll steps sent while holding a drag near the edge
+    pub drag_edge_scroll_steps: u32,
+    /// Skip the hyprctl (move) and ydotool (click) attempts of the uinput backend
+    /// and go straight to its own evdev devices; overrides `move_tool`/`click_tool`
+    pub force_uinput: bool,
+    /// What the uinput backend tries first for absolute moves
+    pub move_tool: MoveTool,
+    /// What the uinput backend tries first for button clicks
+    pub click_tool: ClickTool,
+    /// How the uinput backend sends wheel scrolls
+    pub scroll_mode: ScrollMode,
+    /// Hi-res wheel events one notch is spread over in smooth mode
+    pub smooth_scroll_steps: u32,
+    /// What the uinput tablet device's axes cover for absolute moves
+    pub absolute_mapping: AbsoluteMapping,
+}
+
+/// Mechanism for absolute pointer moves in the uinput backend. External tools
+/// fall back to uinput when they fail.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveTool {
+    /// `hyprctl dispatch movecursor` (exact on Hyprland)
+    #[default]
+    Hyprctl,
+    /// The virtual tablet device
+    Uinput,
+}
+
+/// Mechanism for button clicks in the uinput backend. External tools fall back
+/// to uinput when they fail.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClickTool {
+    /// `ydotool click` through `ydotoold`
+    #[default]
+    Ydotool,
+    /// The virtual mouse device
+    Uinput,
+}
+
+/// Wheel events the uinput backend emits for a scroll.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollMode {
+    /// Whole detents, like a notched wheel
+    #[default]
+    Notch,
+    /// Many small hi-res deltas that taper off, like a kinetic touchpad scroll
+    Smooth,
+}
+
+/// How the uinput backend turns an absolute target into tablet axis values.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsoluteMapping {
+    /// Device pixels of the first monitor; right when the compositor maps the
+    /// tablet onto that monitor alone
+    #[default]
+    Monitor,
+    /// Fractions of the bounding box of all outputs, for compositors that stretch
+    /// the tablet over the whole layout, so monitors of other sizes stay correct
+    Layout,
+}
+
+impl MouseConfig {
+    /// Move and click tools in effect, with `force_uinput` taking precedence.
+    pub fn tools(&self) -> (MoveTool, ClickTool) {
+        if self.force_uinput {
+            (MoveTool::Uinput, ClickTool::Uinput)
+        } else {
+            (self.move_tool, self.click_tool)
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum OverlayTarget {
+    #[default]
     Window,
     Screen,
 }
 
-impl Default for OverlayTarget {
-    fn default() -> Self {
-        OverlayTarget::Window
-    }
+/// Where to put the caret after clicking a text field hint (atspi backend only).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaretPlacement {
+    /// Leave it where the click put it
+    #[default]
+    None,
+    Start,
+    End,
+}
+
+/// How a layer-shell overlay, which lives on a single monitor, covers a window
+/// that spans several.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanningWindow {
+    /// Only the monitor holding the window's top-left corner; hints elsewhere are clipped
+    Origin,
+    /// The monitor showing the largest part of the window
+    Largest,
+    /// One overlay surface per monitor the window covers
+    #[default]
+    Split,
+}
+
+/// Reaction to the focused window changing between hint collection and showing
+/// the overlay, which would leave the hints pointing at the wrong window.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusChange {
+    /// Collect again for the newly focused window; abort if focus keeps moving
+    #[default]
+    Recollect,
+    /// Show a notice instead of the hints
+    Abort,
+    /// Show the hints anyway (skips the focus checks)
+    Ignore,
 }
 
 impl Default for Config {
@@ -127,6 +451,9 @@ impl Default for BackendsConfig {
     fn default() -> Self {
         Self {
             enable: vec!["atspi".into(), "opencv".into()],
+            collect_timeout_ms: 0,
+            focus_change: FocusChange::default(),
+            show_all: false,
             atspi: AtspiConfig::default(),
             opencv: OpencvConfig::default(),
         }
@@ -149,6 +476,8 @@ impl Default for AtspiConfig {
                 "Entry".into(),
             ],
             scale_factor: 1.0,
+            max_concurrent: 32,
+            visible_only: true,
         }
     }
 }
@@ -159,6 +488,9 @@ impl Default for OpencvConfig {
             kernel_size: 6,
             canny_min_val: 100.0,
             canny_max_val: 200.0,
+            diff_mode: false,
+            diff_threshold: 25.0,
+            downscale: 1.0,
         }
     }
 }
@@ -172,8 +504,22 @@ impl Default for OverlayConfig {
             use_layer_shell: true,
             layer_shell_namespace: "hints".into(),
             layer_shell_exclusive_zone: -1,
+            keep_cursor_visible: true,
+            cursor_name: "default".into(),
+            empty_notice_ms: 1500,
+            opacity: 1.0,
+            x11_shape_input: true,
+            spanning_window: SpanningWindow::Split,
+            animation_ms: 0,
+            auto_dismiss_ms: 0,
+            input_grace_ms: 80,
+            reduce_motion: false,
+            dbus_service: false,
+            verbose_requests: false,
+            notify_missing_daemon: false,
             debug_overlay_enabled: false,
             debug_overlay_color: (1.0, 0.0, 1.0, 0.2),
+            debug_toggle_key: 65481, // GDK_KEY_F12
         }
     }
 }
@@ -182,13 +528,56 @@ impl Default for HintsStyle {
     fn default() -> Self {
         Self {
             hint_height: 30,
-            hint_width_padding: 10,
+            hint_padding_x: 10,
+            hint_padding_y: 6,
             hint_font_size: 15,
             hint_font_face: "Sans".into(),
             hint_font_color: (0.0, 0.0, 0.0, 1.0),
             hint_pressed_font_color: (0.7, 0.7, 0.4, 1.0),
             hint_background_color: (1.0, 1.0, 0.5, 0.8),
             hint_uppercase: true,
+            physical_alphabet: false,
+            font_scale_with_dpi: false,
+            only_roles: Vec::new(),
+            scoped: false,
+            whole_window: false,
+            focused_only: false,
+            container_roles: vec![
+                "Panel".into(),
+                "ToolBar".into(),
+                "MenuBar".into(),
+                "PageTabList".into(),
+                "ScrollPane".into(),
+                "Form".into(),
+                "Landmark".into(),
+            ],
+            activate_on_unique: false,
+            merge_text_runs: false,
+            merge_text_gap: 8,
+            reserved_labels: Vec::new(),
+            stable_labels: false,
+            text_outline_width: 0.0,
+            text_outline_color: (0.0, 0.0, 0.0, 1.0),
+            rtl: false,
+            cursor_first: false,
+            show_names: false,
+            name_max_chars: 24,
+            max_label_length: 4,
+            fallback_alphabet: DEFAULT_ALPHABET.to_string(),
+            min_distance: 0,
+            role_prefixes: HashMap::new(),
+            mark_last_clicked: false,
+            last_clicked_background_color: (0.5, 0.8, 1.0, 0.8),
+            selected_background_color: (1.0, 0.6, 0.2, 0.8),
+            alphabets: Vec::new(),
+            alphabet_cycle_key: 65289, // GDK_KEY_Tab
+            source_background_colors: HashMap::from([("opencv".to_string(), (0.6, 0.9, 0.6, 0.8))]),
+            // GDK_KEY_KP_0..GDK_KEY_KP_9
+            key_aliases: ('0'..='9')
+                .zip(0xffb0..)
+                .map(|(ch, keyval)| (keyval, ch))
+                .collect(),
+            theme: None,
         }
     }
 }
@@ -196,32 +585,660 @@ impl Default for HintsStyle {
 impl Default for MouseConfig {
     fn default() -> Self {
         Self {
+            backend: "uinput".into(),
             move_left: "h".into(),
             move_right: "l".into(),
             move_up: "k".into(),
             move_down: "j".into(),
+            move_up_left: String::new(),
+            move_up_right: String::new(),
+            move_down_left: String::new(),
+            move_down_right: String::new(),
             scroll_left: "h".into(),
             scroll_right: "l".into(),
             scroll_up: "k".into(),
             scroll_down: "j".into(),
+            page_down_key: 65366, // GDK_KEY_Page_Down
+            page_up_key: 65365,   // GDK_KEY_Page_Up
+            page_scroll_steps: 10,
             move_pixel_sensitivity: 10,
             move_rampup_time: 0.5,
             scroll_pixel_sensitivity: 5,
             scroll_rampup_time: 0.5,
+            max_repeat: 20,
             exit_key: 65307,        // GDK_KEY_Escape
+            cancel_key: 65535,      // GDK_KEY_Delete
+            backspace_key: 65288,   // GDK_KEY_BackSpace
             hover_modifier: 1 << 2, // Control
             grab_modifier: 1 << 3,  // Alt/Mod1
+            shift_is_right_click: true,
+            right_click_modifier: (1 << 2) | (1 << 0), // Control+Shift
+            key_modifier: 1 << 26,                     // Super
+            activate_key: "KEY_ENTER".into(),
+            hover_glide_ms: 0,
+            scroll_roles: vec![
+                "ScrollPane".into(),
+                "List".into(),
+                "ListBox".into(),
+                "Viewport".into(),
+            ],
+            click_offsets: HashMap::new(),
+            caret_placement: CaretPlacement::None,
+            warp_modifier: 0,
+            warp_stay_open: true,
+            scroll_to_modifier: 0,
+            tab_close_modifier: 0,
+            tab_close_offset: (0.9, 0.5),
+            multi_select_modifier: 0,
+            multi_select_confirm_key: 65293, // GDK_KEY_Return
+            button_suffixes: HashMap::new(),
+            drag_edge_margin: 0,
+            drag_edge_scroll_steps: 3,
+            force_uinput: false,
+            move_tool: MoveTool::Hyprctl,
+            click_tool: ClickTool::Ydotool,
+            scroll_mode: ScrollMode::Notch,
+            smooth_scroll_steps: 12,
+            absolute_mapping: AbsoluteMapping::Monitor,
         }
     }
 }
 
 impl Config {
     pub fn load() -> Self {
-        let path = default_config_path();
-        if let Ok(contents) = fs::read_to_string(&path) {
-            serde_json::from_str::<Config>(&contents).unwrap_or_default()
-        } else {
-            Config::default()
+        Self::try_load().unwrap_or_else(|err| {
+            log::warn!("{err:#}; using the default config");
+            let mut cfg = Config::default();
+            if let Some(target) = SessionState::load().overlay_target {
+                cfg.overlay_target = target;
+            }
+            cfg
+        })
+    }
+
+    /// Like `load`, but a config file that exists and doesn't parse is an error
+    /// instead of silently falling back to the defaults.
+    pub fn try_load() -> anyhow::Result<Self> {
+        Self::try_load_from(&default_config_path(), &SessionState::load())
+    }
+
+    /// `try_load` with the config file at `path`. The overlay target last picked
+    /// in `session` applies only when the file doesn't set one itself.
+    pub fn try_load_from(path: &std::path::Path, session: &SessionState) -> anyhow::Result<Self> {
+        let mut explicit_target = false;
+        let mut cfg = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let invalid = |err: serde_json::Error| {
+                    anyhow::anyhow!("invalid config {}: {err}", path.display())
+                };
+                let mut value: serde_json::Value =
+                    serde_json::from_str(&contents).map_err(invalid)?;
+                explicit_target = value.get("overlay_target").is_some();
+                if let Some(theme) = value.pointer("/hints/theme").and_then(|t| t.as_str()) {
+                    let theme_path = path.parent().unwrap_or(path).join(theme);
+                    match load_theme(&theme_path) {
+                        Ok(theme) => merge_theme(&mut value, theme),
+                        Err(err) => log::warn!("{err:#}; keeping the config's own style"),
+                    }
+                }
+                serde_json::from_value::<Config>(value).map_err(invalid)?
+            }
+            Err(_) => Config::default(),
+        };
+        cfg.clamp_ranges();
+        cfg.validate();
+        if !explicit_target && let Some(target) = session.overlay_target.clone() {
+            cfg.overlay_target = target;
+        }
+        Ok(cfg)
+    }
+}
+
+/// Read a `hints.theme` file.
+fn load_theme(path: &std::path::Path) -> anyhow::Result<serde_json::Value> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("cannot read theme {}: {err}", path.display()))?;
+    serde_json::from_str(&contents)
+        .map_err(|err| anyhow::anyhow!("invalid theme {}: {err}", path.display()))
+}
+
+/// Style keys a theme file may set, per config section. Behavior settings are
+/// deliberately missing, so a theme can't change what hinting does.
+const THEME_KEYS: &[(&str, &[&str])] = &[
+    (
+        "hints",
+        &[
+            "hint_height",
+            "hint_padding_x",
+            "hint_padding_y",
+            "hint_font_size",
+            "hint_font_face",
+            "hint_font_color",
+            "hint_pressed_font_color",
+            "hint_background_color",
+            "hint_uppercase",
+            "text_outline_width",
+            "text_outline_color",
+            "last_clicked_background_color",
+            "selected_background_color",
+            "source_background_colors",
+        ],
+    ),
+    (
+        "overlay",
+        &[
+            "background_color",
+            "opacity",
+            "animation_ms",
+            "debug_overlay_color",
+        ],
+    ),
+];
+
+/// Serde aliases of style keys, per config section, as `(alias, canonical)`.
+const THEME_KEY_ALIASES: &[(&str, &str, &str)] =
+    &[("hints", "hint_width_padding", "hint_padding_x")];
+
+/// Rename the aliased keys in [`THEME_KEY_ALIASES`] of the raw config `value`
+/// to their canonical names, so a merge never leaves both spellings for serde
+/// to reject as a duplicate field. The canonical key wins if both are set.
+fn canonicalize_theme_keys(value: &mut serde_json::Value) {
+    for (section, alias, canonical) in THEME_KEY_ALIASES {
+        let Some(serde_json::Value::Object(keys)) = value.get_mut(*section) else {
+            continue;
+        };
+        if let Some(aliased) = keys.remove(*alias) {
+            keys.entry(*canonical).or_insert(aliased);
         }
     }
 }
+
+/// Merge the style keys in [`THEME_KEYS`] of `theme` over those of the raw config
+/// `base`; anything else in the theme is ignored with a warning, so behavior
+/// settings always come from the main config.
+pub fn merge_theme(base: &mut serde_json::Value, mut theme: serde_json::Value) {
+    canonicalize_theme_keys(base);
+    canonicalize_theme_keys(&mut theme);
+    let (serde_json::Value::Object(theme), true) = (theme, base.is_object()) else {
+        log::warn!("config: theme or config is not a JSON object; ignoring the theme");
+        return;
+    };
+    for (section, style) in theme {
+        let Some((_, keys)) = THEME_KEYS.iter().find(|(name, _)| *name == section) else {
+            log::warn!("config: theme section {section:?} isn't a style section; ignoring it");
+            continue;
+        };
+        let serde_json::Value::Object(style) = style else {
+            log::warn!("config: theme section {section:?} is not a JSON object; ignoring it");
+            continue;
+        };
+        for (key, value) in style {
+            if !keys.contains(&key.as_str()) {
+                log::warn!("config: theme key {section}.{key} isn't a style setting; ignoring it");
+                continue;
+            }
+            merge_json(&mut base[section.as_str()][key.as_str()], value);
+        }
+    }
+}
+
+/// Recursively overwrite `base` with `overlay`, keeping fields `overlay` lacks.
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Reset `value` to `fallback`, with a warning, when it fails `valid`.
+fn clamp_field<T: Copy + std::fmt::Debug>(
+    name: &str,
+    value: &mut T,
+    valid: impl Fn(T) -> bool,
+    fallback: T,
+) {
+    if !valid(*value) {
+        log::warn!("config: {name} = {value:?} is out of range; using {fallback:?}");
+        *value = fallback;
+    }
+}
+
+impl Config {
+    /// Reset numeric settings that would break the overlay or the daemon (negative
+    /// sizes, zero divisors, an alphabet too small to label with) to their default
+    /// values, so a bad config degrades instead of failing later.
+    pub fn clamp_ranges(&mut self) {
+        let defaults = Config::default();
+
+        let distinct = self
+            .alphabet
+            .chars()
+            .collect::<std::collections::HashSet<_>>();
+        if distinct.len() < 2 {
+            log::warn!(
+                "config: alphabet {:?} needs at least two distinct characters; using {:?}",
+                self.alphabet,
+                defaults.alphabet
+            );
+            self.alphabet = defaults.alphabet.clone();
+        }
+        self.hints.alphabets.retain(|named| {
+            let distinct = named.alphabet.chars().collect::<std::collections::HashSet<_>>();
+            if distinct.len() < 2 {
+                log::warn!(
+                    "config: dropping alphabet {:?} ({:?}); it needs at least two distinct characters",
+                    named.name,
+                    named.alphabet
+                );
+            }
+            distinct.len() >= 2
+        });
+
+        let backends = &mut self.backends;
+        clamp_field(
+            "backends.atspi.max_concurrent",
+            &mut backends.atspi.max_concurrent,
+            |v| v >= 1,
+            defaults.backends.atspi.max_concurrent,
+        );
+        clamp_field(
+            "backends.opencv.kernel_size",
+            &mut backends.opencv.kernel_size,
+            |v| v >= 1,
+            defaults.backends.opencv.kernel_size,
+        );
+        clamp_field(
+            "backends.opencv.downscale",
+            &mut backends.opencv.downscale,
+            |v| v > 0.0 && v <= 1.0,
+            defaults.backends.opencv.downscale,
+        );
+        clamp_field(
+            "backends.opencv.diff_threshold",
+            &mut backends.opencv.diff_threshold,
+            |v| (0.0..=255.0).contains(&v),
+            defaults.backends.opencv.diff_threshold,
+        );
+
+        let hints = &mut self.hints;
+        clamp_field(
+            "hints.hint_height",
+            &mut hints.hint_height,
+            |v| v >= 0,
+            defaults.hints.hint_height,
+        );
+        clamp_field(
+            "hints.hint_font_size",
+            &mut hints.hint_font_size,
+            |v| v > 0,
+            defaults.hints.hint_font_size,
+        );
+        clamp_field(
+            "hints.hint_padding_x",
+            &mut hints.hint_padding_x,
+            |v| v >= 0,
+            defaults.hints.hint_padding_x,
+        );
+        clamp_field(
+            "hints.hint_padding_y",
+            &mut hints.hint_padding_y,
+            |v| v >= 0,
+            defaults.hints.hint_padding_y,
+        );
+        clamp_field(
+            "hints.merge_text_gap",
+            &mut hints.merge_text_gap,
+            |v| v >= 0,
+            defaults.hints.merge_text_gap,
+        );
+        clamp_field(
+            "hints.text_outline_width",
+            &mut hints.text_outline_width,
+            |v| v >= 0.0,
+            defaults.hints.text_outline_width,
+        );
+        clamp_field(
+            "hints.min_distance",
+            &mut hints.min_distance,
+            |v| v >= 0,
+            defaults.hints.min_distance,
+        );
+
+        let mouse = &mut self.mouse;
+        clamp_field(
+            "mouse.move_pixel_sensitivity",
+            &mut mouse.move_pixel_sensitivity,
+            |v| v > 0,
+            defaults.mouse.move_pixel_sensitivity,
+        );
+        clamp_field(
+            "mouse.scroll_pixel_sensitivity",
+            &mut mouse.scroll_pixel_sensitivity,
+            |v| v > 0,
+            defaults.mouse.scroll_pixel_sensitivity,
+        );
+        clamp_field(
+            "mouse.move_rampup_time",
+            &mut mouse.move_rampup_time,
+            |v| v >= 0.0,
+            defaults.mouse.move_rampup_time,
+        );
+        clamp_field(
+            "mouse.scroll_rampup_time",
+            &mut mouse.scroll_rampup_time,
+            |v| v >= 0.0,
+            defaults.mouse.scroll_rampup_time,
+        );
+        clamp_field(
+            "mouse.max_repeat",
+            &mut mouse.max_repeat,
+            |v| v >= 1,
+            defaults.mouse.max_repeat,
+        );
+        clamp_field(
+            "mouse.page_scroll_steps",
+            &mut mouse.page_scroll_steps,
+            |v| v >= 1,
+            defaults.mouse.page_scroll_steps,
+        );
+        clamp_field(
+            "mouse.smooth_scroll_steps",
+            &mut mouse.smooth_scroll_steps,
+            |v| v >= 1,
+            defaults.mouse.smooth_scroll_steps,
+        );
+        clamp_field(
+            "mouse.drag_edge_margin",
+            &mut mouse.drag_edge_margin,
+            |v| v >= 0,
+            defaults.mouse.drag_edge_margin,
+        );
+        clamp_field(
+            "mouse.drag_edge_scroll_steps",
+            &mut mouse.drag_edge_scroll_steps,
+            |v| v >= 1,
+            defaults.mouse.drag_edge_scroll_steps,
+        );
+        clamp_field(
+            "mouse.tab_close_offset",
+            &mut mouse.tab_close_offset,
+            |(x, y)| (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y),
+            defaults.mouse.tab_close_offset,
+        );
+
+        clamp_field(
+            "overlay.opacity",
+            &mut self.overlay.opacity,
+            |v| (0.0..=1.0).contains(&v),
+            defaults.overlay.opacity,
+        );
+    }
+
+    /// Warn about settings that parse and are in range but can't all work
+    /// together, such as two actions bound to the same modifier, where one of
+    /// them would silently never fire. Returns the warnings it logged.
+    pub fn validate(&self) -> Vec<String> {
+        let mouse = &self.mouse;
+        let modifiers = [
+            ("mouse.right_click_modifier", mouse.right_click_modifier),
+            ("mouse.scroll_to_modifier", mouse.scroll_to_modifier),
+            ("mouse.warp_modifier", mouse.warp_modifier),
+            ("mouse.tab_close_modifier", mouse.tab_close_modifier),
+            ("mouse.multi_select_modifier", mouse.multi_select_modifier),
+            ("mouse.key_modifier", mouse.key_modifier),
+        ];
+        let mut warnings = Vec::new();
+        for (i, (name, mask)) in modifiers.iter().enumerate() {
+            for (other, other_mask) in &modifiers[i + 1..] {
+                if *mask != 0 && mask == other_mask {
+                    warnings.push(format!(
+                        "config: {name} and {other} are both {mask}; only one of them will work"
+                    ));
+                }
+            }
+        }
+        for warning in &warnings {
+            log::warn!("{warning}");
+        }
+        warnings
+    }
+}
+
+/// What the last `hintsx` run chose, kept in `session.json` next to the config
+/// so the next launch starts in the same mode.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    /// Overlay target last picked with `HINTSX_OVERLAY_TARGET`
+    pub overlay_target: Option<OverlayTarget>,
+}
+
+impl SessionState {
+    pub fn path() -> PathBuf {
+        default_config_path().with_file_name("session.json")
+    }
+
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    /// The state saved at `path`; the defaults if it's missing or unreadable.
+    pub fn load_from(path: &std::path::Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    pub fn save_to(&self, path: &std::path::Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl BackendsConfig {
+    pub fn collect_timeout(&self) -> Option<Duration> {
+        (self.collect_timeout_ms > 0).then(|| Duration::from_millis(self.collect_timeout_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn session_state_round_trips_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hintsx").join("session.json");
+        // Nothing saved yet
+        assert_eq!(SessionState::load_from(&path).overlay_target, None);
+
+        let state = SessionState {
+            overlay_target: Some(OverlayTarget::Screen),
+        };
+        state.save_to(&path).unwrap();
+        assert_eq!(
+            SessionState::load_from(&path).overlay_target,
+            Some(OverlayTarget::Screen)
+        );
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(SessionState::load_from(&path).overlay_target, None);
+    }
+
+    #[test]
+    fn config_file_overlay_target_beats_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let session = SessionState {
+            overlay_target: Some(OverlayTarget::Screen),
+        };
+
+        // No config file, or one that leaves the target out: the session decides
+        let cfg = Config::try_load_from(&path, &session).unwrap();
+        assert_eq!(cfg.overlay_target, OverlayTarget::Screen);
+        fs::write(&path, r#"{"alphabet": "asdf"}"#).unwrap();
+        let cfg = Config::try_load_from(&path, &session).unwrap();
+        assert_eq!(cfg.overlay_target, OverlayTarget::Screen);
+
+        fs::write(&path, r#"{"overlay_target": "window"}"#).unwrap();
+        let cfg = Config::try_load_from(&path, &session).unwrap();
+        assert_eq!(cfg.overlay_target, OverlayTarget::Window);
+        let cfg = Config::try_load_from(&path, &SessionState::default()).unwrap();
+        assert_eq!(cfg.overlay_target, OverlayTarget::Window);
+    }
+
+    #[test]
+    fn force_uinput_overrides_both_tools() {
+        let mut mouse = MouseConfig {
+            move_tool: MoveTool::Hyprctl,
+            click_tool: ClickTool::Ydotool,
+            ..MouseConfig::default()
+        };
+        assert_eq!(mouse.tools(), (MoveTool::Hyprctl, ClickTool::Ydotool));
+        mouse.force_uinput = true;
+        assert_eq!(mouse.tools(), (MoveTool::Uinput, ClickTool::Uinput));
+    }
+
+    #[test]
+    fn clamp_ranges_resets_only_invalid_values() {
+        let mut cfg = Config {
+            alphabet: "aaa".into(),
+            ..Config::default()
+        };
+        cfg.hints.hint_font_size = -3;
+        cfg.hints.hint_padding_x = 4;
+        cfg.overlay.opacity = 2.0;
+        cfg.mouse.max_repeat = 0;
+        cfg.mouse.page_scroll_steps = 0;
+        cfg.mouse.scroll_pixel_sensitivity = 8;
+        cfg.mouse.tab_close_offset = (0.2, 1.5);
+        cfg.mouse.drag_edge_scroll_steps = 5;
+        cfg.hints.min_distance = -4;
+        cfg.backends.opencv.diff_threshold = 40.0;
+        cfg.backends.opencv.downscale = 0.5;
+        cfg.clamp_ranges();
+
+        let defaults = Config::default();
+        assert_eq!(cfg.alphabet, defaults.alphabet);
+        assert_eq!(cfg.hints.hint_font_size, defaults.hints.hint_font_size);
+        assert_eq!(cfg.hints.hint_padding_x, 4);
+        assert_eq!(cfg.overlay.opacity, defaults.overlay.opacity);
+        assert_eq!(cfg.mouse.max_repeat, defaults.mouse.max_repeat);
+        assert_eq!(
+            cfg.mouse.page_scroll_steps,
+            defaults.mouse.page_scroll_steps
+        );
+        assert_eq!(cfg.mouse.scroll_pixel_sensitivity, 8);
+        assert_eq!(cfg.mouse.tab_close_offset, defaults.mouse.tab_close_offset);
+        assert_eq!(cfg.mouse.drag_edge_scroll_steps, 5);
+        assert_eq!(cfg.hints.min_distance, defaults.hints.min_distance);
+        assert_eq!(cfg.backends.opencv.diff_threshold, 40.0);
+        assert_eq!(cfg.backends.opencv.downscale, 0.5);
+    }
+
+    /// Sets one field out of range on a default config and checks `clamp_ranges`
+    /// puts the default back.
+    macro_rules! assert_resets_to_default {
+        ($($($field:ident).+ = $invalid:expr;)+) => {$(
+            let mut cfg = Config::default();
+            cfg.$($field).+ = $invalid;
+            cfg.clamp_ranges();
+            assert_eq!(
+                cfg.$($field).+,
+                Config::default().$($field).+,
+                stringify!($($field).+)
+            );
+        )+};
+    }
+
+    #[test]
+    fn clamp_ranges_resets_each_field_to_its_default() {
+        assert_resets_to_default! {
+            backends.atspi.max_concurrent = 0;
+            backends.opencv.kernel_size = 0;
+            backends.opencv.downscale = 0.0;
+            backends.opencv.downscale = 1.5;
+            backends.opencv.diff_threshold = 300.0;
+            hints.hint_height = -1;
+            hints.hint_font_size = 0;
+            hints.hint_padding_x = -1;
+            hints.hint_padding_y = -1;
+            hints.merge_text_gap = -1;
+            hints.text_outline_width = -0.5;
+            hints.min_distance = -1;
+            mouse.move_pixel_sensitivity = 0;
+            mouse.scroll_pixel_sensitivity = -2;
+            mouse.move_rampup_time = -1.0;
+            mouse.scroll_rampup_time = -1.0;
+            mouse.max_repeat = 0;
+            mouse.page_scroll_steps = 0;
+            mouse.smooth_scroll_steps = 0;
+            mouse.drag_edge_margin = -1;
+            mouse.drag_edge_scroll_steps = 0;
+            mouse.tab_close_offset = (-0.1, 0.5);
+            overlay.opacity = -0.1;
+        }
+    }
+
+    #[test]
+    fn theme_sets_style_but_not_behavior() {
+        let mut value = json!({
+            "hints": {"activate_on_unique": false, "hint_font_size": 12},
+            "overlay": {"auto_dismiss_ms": 0},
+        });
+        let theme = json!({
+            "hints": {"activate_on_unique": true, "hint_font_size": 20, "rtl": true},
+            "overlay": {"auto_dismiss_ms": 500, "opacity": 0.5},
+            "mouse": {"force_uinput": true},
+        });
+        merge_theme(&mut value, theme);
+        let cfg: Config = serde_json::from_value(value).unwrap();
+
+        assert_eq!(cfg.hints.hint_font_size, 20);
+        assert_eq!(cfg.overlay.opacity, 0.5);
+        assert!(!cfg.hints.activate_on_unique);
+        assert!(!cfg.hints.rtl);
+        assert_eq!(cfg.overlay.auto_dismiss_ms, 0);
+        assert!(!cfg.mouse.force_uinput);
+    }
+
+    #[test]
+    fn validate_warns_when_scroll_to_takes_the_right_click_modifier() {
+        assert!(Config::default().validate().is_empty());
+
+        let mut cfg = Config::default();
+        cfg.mouse.scroll_to_modifier = 8;
+        assert!(cfg.validate().is_empty());
+
+        cfg.mouse.scroll_to_modifier = cfg.mouse.right_click_modifier;
+        let warnings = cfg.validate();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("mouse.right_click_modifier"));
+        assert!(warnings[0].contains("mouse.scroll_to_modifier"));
+    }
+
+    #[test]
+    fn theme_padding_alias_merges_with_the_canonical_key() {
+        let mut value = json!({"hints": {"hint_width_padding": 4}});
+        merge_theme(&mut value, json!({"hints": {"hint_padding_x": 12}}));
+        let cfg: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(cfg.hints.hint_padding_x, 12);
+
+        let mut value = json!({"hints": {"hint_padding_x": 4}});
+        merge_theme(&mut value, json!({"hints": {"hint_width_padding": 12}}));
+        let cfg: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(cfg.hints.hint_padding_x, 12);
+    }
+}
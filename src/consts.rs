@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
 pub const UNIX_DOMAIN_SOCKET_FILE: &str = "/tmp/hints.socket";
+/// Locked by the running `hintsd` for as long as it lives.
+pub const DAEMON_LOCK_FILE: &str = "/tmp/hints.socket.lock";
 pub const SOCKET_MESSAGE_SIZE: usize = 1024;
 pub const DEFAULT_ALPHABET: &str = "asdfgqwertzxcvbhjklyuiopnm";
 
@@ -10,3 +12,10 @@ pub fn default_config_path() -> PathBuf {
         .join("hints")
         .join("config.json")
 }
+
+pub fn default_cache_dir() -> PathBuf {
+    std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".cache"))
+        .join("hints")
+}
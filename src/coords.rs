@@ -0,0 +1,152 @@
+//! Shared coordinate rounding so the overlay and `hintsd` agree on which pixel a
+//! hint targets. Everything rounds half-up.
+
+/// Round to the nearest integer, with exact halves rounding towards +infinity.
+pub fn round_half_up(v: f64) -> i32 {
+    (v + 0.5).floor() as i32
+}
+
+/// Index of the pixel at the middle of the span `[origin, origin + extent)`.
+/// For even extents the two middle pixels tie and the later one wins.
+pub fn center_pixel(origin: i32, extent: i32) -> i32 {
+    fraction_pixel(origin, extent, 0.5)
+}
+
+/// Index of the pixel `fraction` of the way across `[origin, origin + extent)`:
+/// 0.0 is the first pixel, 1.0 the last. Out-of-range fractions are clamped.
+pub fn fraction_pixel(origin: i32, extent: i32, fraction: f64) -> i32 {
+    round_half_up(origin as f64 + (extent - 1).max(0) as f64 * fraction.clamp(0.0, 1.0))
+}
+
+/// Overlap of two (x, y, width, height) rectangles, if they share any area.
+pub fn intersect(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let left = a.0.max(b.0);
+    let top = a.1.max(b.1);
+    let right = (a.0 + a.2).min(b.0 + b.2);
+    let bottom = (a.1 + a.3).min(b.1 + b.3);
+    (right > left && bottom > top).then_some((left, top, right - left, bottom - top))
+}
+
+/// `extents` if they have any area; a window whose geometry couldn't be read
+/// comes back as (x, y, 0, 0).
+pub fn sized_extents(extents: Option<(i32, i32, i32, i32)>) -> Option<(i32, i32, i32, i32)> {
+    extents.filter(|&(_, _, w, h)| w > 0 && h > 0)
+}
+
+/// Scale a logical coordinate into device pixels.
+pub fn scale(v: i32, factor: f64) -> i32 {
+    round_half_up(v as f64 * factor)
+}
+
+/// `point` pulled onto an absolute device whose axes run from 0 to `axis_max`.
+pub fn clamp_to_axes(point: (i32, i32), axis_max: (i32, i32)) -> (i32, i32) {
+    (point.0.clamp(0, axis_max.0), point.1.clamp(0, axis_max.1))
+}
+
+/// Value on an absolute axis `[0, axis_max]` that a compositor stretching it over
+/// the logical span `[origin, origin + extent)` maps to logical coordinate `v`.
+pub fn layout_axis(v: i32, origin: i32, extent: i32, axis_max: i32) -> i32 {
+    round_half_up((v - origin) as f64 * axis_max as f64 / extent.max(1) as f64)
+}
+
+/// Scale a logical coordinate on an output that starts at logical `origin`: the
+/// origin is scaled by `base` like the rest of the layout, the offset into the
+/// output by the output's own `factor`.
+pub fn scale_on_output(v: i32, origin: i32, base: f64, factor: f64) -> i32 {
+    scale(origin, base) + round_half_up((v - origin) as f64 * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_pixel_of_odd_and_even_spans() {
+        // Width 3 at scale 1: pixels 10, 11, 12, and 11 is the middle one
+        assert_eq!(scale(center_pixel(10, 3), 1.0), 11);
+        // Width 4: 11 and 12 tie, the later one wins
+        assert_eq!(center_pixel(10, 4), 12);
+        assert_eq!(center_pixel(10, 1), 10);
+    }
+
+    #[test]
+    fn clamp_to_axes_keeps_points_on_the_monitor() {
+        // A 1920x1080 monitor at scale 2, as `VirtualMouse` sizes its axes
+        let axis_max = (1920 * 2, 1080 * 2);
+        assert_eq!(clamp_to_axes((3840, 2160), axis_max), (3840, 2160));
+        assert_eq!(clamp_to_axes((0, 0), axis_max), (0, 0));
+        assert_eq!(clamp_to_axes((3841, -1), axis_max), (3840, 0));
+        assert_eq!(clamp_to_axes((-5, 1000), axis_max), (0, 1000));
+        assert_eq!(clamp_to_axes((1200, 9999), axis_max), (1200, 2160));
+    }
+
+    #[test]
+    fn fraction_pixel_clamps_and_rounds_half_up() {
+        assert_eq!(fraction_pixel(10, 5, 0.0), 10);
+        assert_eq!(fraction_pixel(10, 5, 1.0), 14);
+        assert_eq!(fraction_pixel(10, 4, 0.5), 12);
+        assert_eq!(fraction_pixel(10, 5, 2.0), 14);
+        assert_eq!(fraction_pixel(10, 5, -1.0), 10);
+        assert_eq!(fraction_pixel(10, 0, 0.5), 10);
+        // Left of the primary output
+        assert_eq!(fraction_pixel(-20, 10, 0.25), -18);
+    }
+
+    #[test]
+    fn sized_extents_drop_zero_width_and_height() {
+        assert_eq!(sized_extents(Some((10, 20, 0, 300))), None);
+        assert_eq!(sized_extents(Some((10, 20, 400, 0))), None);
+        assert_eq!(sized_extents(Some((10, 20, 0, 0))), None);
+        assert_eq!(sized_extents(None), None);
+        assert_eq!(
+            sized_extents(Some((-10, 20, 400, 300))),
+            Some((-10, 20, 400, 300))
+        );
+    }
+
+    #[test]
+    fn scale_rounds_halves_towards_positive() {
+        assert_eq!(scale(5, 1.5), 8);
+        assert_eq!(scale(-5, 1.5), -7);
+        assert_eq!(scale(3, 1.25), 4);
+        assert_eq!(scale(-1280, 2.0), -2560);
+    }
+
+    #[test]
+    fn intersect_needs_shared_area() {
+        let screen = (0, 0, 1920, 1080);
+        assert_eq!(
+            intersect(screen, (1900, 1000, 100, 100)),
+            Some((1900, 1000, 20, 80))
+        );
+        assert_eq!(intersect(screen, (10, 10, 5, 5)), Some((10, 10, 5, 5)));
+        // Touching edges share no area
+        assert_eq!(intersect(screen, (1920, 0, 100, 100)), None);
+        assert_eq!(intersect(screen, (-1280, 0, 1280, 1024)), None);
+        assert_eq!(
+            intersect((-1280, 0, 1280, 1024), (-100, 500, 200, 50)),
+            Some((-100, 500, 100, 50))
+        );
+    }
+
+    #[test]
+    fn layout_axis_spans_every_output() {
+        // A 1920-wide output left of a 1920-wide primary, on a 0..=65535 axis
+        let axis = |v| layout_axis(v, -1920, 3840, 65535);
+        assert_eq!(axis(-1920), 0);
+        assert_eq!(axis(0), 32768);
+        assert_eq!(axis(1920), 65535);
+        // A degenerate layout doesn't divide by zero
+        assert_eq!(layout_axis(5, 0, 0, 100), 500);
+    }
+
+    #[test]
+    fn scale_on_output_scales_the_offset_by_the_output() {
+        // Scale-2 output right of a scale-1 primary
+        assert_eq!(scale_on_output(2000, 1920, 1.0, 2.0), 2080);
+        assert_eq!(scale_on_output(1920, 1920, 1.0, 2.0), 1920);
+        // Scale-1.5 output at a negative origin, with a scale-1 and a scale-2 base
+        assert_eq!(scale_on_output(-1000, -1280, 1.0, 1.5), -860);
+        assert_eq!(scale_on_output(-1000, -1280, 2.0, 1.5), -2140);
+    }
+}
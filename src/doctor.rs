@@ -0,0 +1,260 @@
+//! `hintsx --doctor`: probe the tools, sockets and permissions hintsx and hintsd
+//! assume at runtime and report each as pass/warn/fail with a way to fix it.
+use crate::config::Config;
+use crate::mouse::{YdotoolStatus, on_path, probe_ydotool, ydotool_socket};
+use crate::window_system::{WindowSystem, WindowSystemType};
+use std::fmt;
+
+/// How long the accessibility bus gets to answer.
+#[cfg(feature = "atspi-backend")]
+const A11Y_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    /// Works, but through a fallback or with a feature missing
+    Warn,
+    /// Something hints needs won't work
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub remedy: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            remedy: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: Status,
+        detail: impl Into<String>,
+        remedy: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Worst status among the checks; an empty report passes.
+    pub fn overall(&self) -> Status {
+        self.checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(Status::Pass)
+    }
+
+    /// Number of checks with `status`.
+    pub fn count(&self, status: Status) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "[{}] {}: {}", check.status, check.name, check.detail)?;
+            if let Some(remedy) = &check.remedy {
+                writeln!(f, "       -> {remedy}")?;
+            }
+        }
+        write!(
+            f,
+            "{}: {} passed, {} warnings, {} failures",
+            self.overall(),
+            self.count(Status::Pass),
+            self.count(Status::Warn),
+            self.count(Status::Fail)
+        )
+    }
+}
+
+/// Run every probe against the environment `cfg` describes.
+pub fn run(cfg: &Config) -> Report {
+    let window_system = WindowSystem::detect(&cfg.window_system).ok();
+    Report {
+        checks: vec![
+            check_compositor(window_system.as_ref()),
+            check_a11y_bus(),
+            check_screenshot_tool(window_system.as_ref()),
+            check_ydotool(),
+            check_uinput(),
+        ],
+    }
+}
+
+fn check_compositor(window_system: Option<&WindowSystem>) -> Check {
+    const NAME: &str = "compositor";
+    match window_system {
+        None => Check::problem(
+            NAME,
+            Status::Fail,
+            "could not detect the window system",
+            "set `window_system` to \"x11\" or \"wayland\" in the config",
+        ),
+        Some(ws)
+            if ws.window_system_type == WindowSystemType::Wayland
+                && ws.window_system_name == "unknown" =>
+        {
+            Check::problem(
+                NAME,
+                Status::Warn,
+                "Wayland session with an unrecognized compositor",
+                "overlays need layer-shell (Hyprland/Sway/Plasma); GNOME blocks them",
+            )
+        }
+        Some(ws) => Check::pass(
+            NAME,
+            format!("{:?} ({})", ws.window_system_type, ws.window_system_name),
+        ),
+    }
+}
+
+#[cfg(feature = "atspi-backend")]
+fn check_a11y_bus() -> Check {
+    const NAME: &str = "accessibility bus";
+    match crate::backends::atspi_backend::probe_bus(A11Y_PROBE_TIMEOUT) {
+        Ok(()) => Check::pass(NAME, "reachable"),
+        Err(err) => Check::problem(
+            NAME,
+            Status::Fail,
+            err.to_string(),
+            "enable accessibility (toolkit-accessibility) and restart your apps",
+        ),
+    }
+}
+
+#[cfg(not(feature = "atspi-backend"))]
+fn check_a11y_bus() -> Check {
+    Check::problem(
+        "accessibility bus",
+        Status::Warn,
+        "hintsx was built without the `atspi-backend` feature",
+        "rebuild with `--features atspi-backend` to hint through accessibility",
+    )
+}
+
+fn check_screenshot_tool(window_system: Option<&WindowSystem>) -> Check {
+    const NAME: &str = "screenshot tool";
+    let wayland =
+        window_system.is_some_and(|ws| ws.window_system_type == WindowSystemType::Wayland);
+    let candidates: &[&str] = if wayland {
+        &["grim"]
+    } else {
+        &["shotgun", "maim"]
+    };
+    match candidates.iter().find(|tool| on_path(tool)) {
+        Some(tool) => Check::pass(NAME, format!("{tool} found")),
+        None => Check::problem(
+            NAME,
+            Status::Warn,
+            format!("none of {candidates:?} on PATH"),
+            format!(
+                "install {} for the OpenCV fallback backend",
+                candidates.join(" or ")
+            ),
+        ),
+    }
+}
+
+fn check_ydotool() -> Check {
+    const NAME: &str = "ydotool";
+    let socket = ydotool_socket();
+    match probe_ydotool(&socket) {
+        YdotoolStatus::Ready => Check::pass(NAME, format!("ydotoold listening on {socket}")),
+        YdotoolStatus::NotInstalled => Check::problem(
+            NAME,
+            Status::Warn,
+            "not installed; clicks go through uinput",
+            "install ydotool, or set `\"mouse\": {\"click_tool\": \"uinput\"}`",
+        ),
+        YdotoolStatus::SocketMissing => Check::problem(
+            NAME,
+            Status::Warn,
+            format!("no socket at {socket}; clicks go through uinput"),
+            "start ydotoold (see start_ydotoold.sh) or set YDOTOOL_SOCKET",
+        ),
+    }
+}
+
+fn check_uinput() -> Check {
+    const NAME: &str = "uinput";
+    match std::fs::OpenOptions::new().write(true).open("/dev/uinput") {
+        Ok(_) => Check::pass(NAME, "/dev/uinput is writable"),
+        Err(err) => Check::problem(
+            NAME,
+            Status::Fail,
+            format!("cannot open /dev/uinput for writing ({err})"),
+            "run `sudo usermod -aG input $USER` and log in again, or add a udev rule \
+             (see check_permissions.sh)",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_takes_the_worst_status_and_counts_each() {
+        assert_eq!(Report::default().overall(), Status::Pass);
+
+        let mut report = Report {
+            checks: vec![
+                Check::pass("compositor", "Wayland (hyprland)"),
+                Check::problem("ydotool", Status::Warn, "not installed", "install it"),
+                Check::pass("uinput", "/dev/uinput is writable"),
+            ],
+        };
+        assert_eq!(report.overall(), Status::Warn);
+        assert_eq!(report.count(Status::Pass), 2);
+        assert_eq!(report.count(Status::Fail), 0);
+
+        report.checks.push(Check::problem(
+            "accessibility bus",
+            Status::Fail,
+            "unreachable",
+            "enable accessibility",
+        ));
+        assert_eq!(report.overall(), Status::Fail);
+        let text = report.to_string();
+        assert!(text.contains("[WARN] ydotool: not installed\n       -> install it\n"));
+        assert!(text.ends_with("FAIL: 2 passed, 1 warnings, 1 failures"));
+    }
+}
@@ -1,17 +1,374 @@
+use crate::consts::default_cache_dir;
+use crate::coords::{center_pixel, fraction_pixel};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Child {
     pub absolute_x: i32,
     pub absolute_y: i32,
     pub width: i32,
     pub height: i32,
+    /// Accessibility role name (e.g. "Link", "PushButton") when the backend knows it.
+    pub role: Option<String>,
+    /// AT-SPI object path, for follow-up calls on the element (atspi backend only).
+    pub accessible_path: Option<String>,
+    /// Accessible name, collected when `hints.show_names` is set (atspi backend only).
+    pub name: Option<String>,
+    /// Name of the backend that found this element ("atspi", "opencv").
+    pub source: Option<String>,
+}
+
+impl Child {
+    /// Pixel the hint points at; shared with `hintsd` via `coords` rounding.
+    pub fn center(&self) -> (i32, i32) {
+        (
+            center_pixel(self.absolute_x, self.width),
+            center_pixel(self.absolute_y, self.height),
+        )
+    }
+
+    /// Pixel a click on this child should land on: the fractional `(x, y)`
+    /// position configured for its role in `offsets`, or the center otherwise.
+    pub fn click_point(&self, offsets: &HashMap<String, (f64, f64)>) -> (i32, i32) {
+        match self.role.as_ref().and_then(|r| offsets.get(r)) {
+            Some(&(fx, fy)) => self.point_at(fx, fy),
+            None => self.center(),
+        }
+    }
+
+    /// Pixel at fractions `(fx, fy)` of this child's width and height, with
+    /// (0, 0) its top-left and (1, 1) its bottom-right corner.
+    pub fn point_at(&self, fx: f64, fy: f64) -> (i32, i32) {
+        (
+            fraction_pixel(self.absolute_x, self.width, fx),
+            fraction_pixel(self.absolute_y, self.height, fy),
+        )
+    }
+
+    /// `center` measured from `origin` instead of the screen's top-left.
+    pub fn center_in(&self, origin: (i32, i32)) -> (i32, i32) {
+        let (x, y) = self.center();
+        (x - origin.0, y - origin.1)
+    }
+
+    /// The focused window at `extents` as a single element, for `hints.whole_window`.
+    pub fn for_window((x, y, w, h): (i32, i32, i32, i32)) -> Child {
+        Child {
+            absolute_x: x,
+            absolute_y: y,
+            width: w,
+            height: h,
+            role: Some("Window".into()),
+            accessible_path: None,
+            name: None,
+            source: Some("window".into()),
+        }
+    }
+
+    /// This child with its position shifted by `(dx, dy)`.
+    pub fn translated(&self, dx: i32, dy: i32) -> Child {
+        Child {
+            absolute_x: self.absolute_x + dx,
+            absolute_y: self.absolute_y + dy,
+            ..self.clone()
+        }
+    }
+
+    /// This child with its extents passed through `normalize_extents`, or `None`
+    /// when they describe no usable box.
+    pub fn normalized(self) -> Option<Child> {
+        let (absolute_x, absolute_y, width, height) =
+            normalize_extents((self.absolute_x, self.absolute_y, self.width, self.height))?;
+        Some(Child {
+            absolute_x,
+            absolute_y,
+            width,
+            height,
+            ..self
+        })
+    }
+}
+
+/// Extents (x, y, width, height) with a negative width or height flipped, as
+/// some toolkits report them, so the box covers the same pixels from its
+/// top-left corner. Empty boxes and ones whose far edge overflows are `None`.
+pub fn normalize_extents((x, y, w, h): (i32, i32, i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let span = |start: i32, len: i32| match len {
+        0 => None,
+        len if len > 0 => start.checked_add(len).map(|_| (start, len)),
+        len => Some((start.checked_add(len)?, len.checked_neg()?)),
+    };
+    let (x, w) = span(x, w)?;
+    let (y, h) = span(y, h)?;
+    Some((x, y, w, h))
 }
 
 pub type HintMap = HashMap<String, Child>;
 
+/// What `Child` positions are measured from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoordSpace {
+    /// The screen (global compositor layout), as backends report them
+    #[default]
+    Screen,
+    /// The top-left corner of the focused window's extents
+    Window,
+}
+
+/// Re-express `hints` given in `from` space in `to` space, with the window at
+/// `focus_extents`. Without focus extents the two spaces are the same.
+pub fn convert_hints(
+    hints: HintMap,
+    focus_extents: Option<(i32, i32, i32, i32)>,
+    from: CoordSpace,
+    to: CoordSpace,
+) -> HintMap {
+    let Some((dx, dy)) = space_offset(focus_extents, from, to) else {
+        return hints;
+    };
+    hints
+        .into_iter()
+        .map(|(label, child)| (label, child.translated(dx, dy)))
+        .collect()
+}
+
+/// Like [`convert_hints`], for unlabeled `children`.
+pub fn convert_children(
+    children: Vec<Child>,
+    focus_extents: Option<(i32, i32, i32, i32)>,
+    from: CoordSpace,
+    to: CoordSpace,
+) -> Vec<Child> {
+    let Some((dx, dy)) = space_offset(focus_extents, from, to) else {
+        return children;
+    };
+    children
+        .into_iter()
+        .map(|child| child.translated(dx, dy))
+        .collect()
+}
+
+/// Translation from `from` to `to` space, or None when they coincide.
+fn space_offset(
+    focus_extents: Option<(i32, i32, i32, i32)>,
+    from: CoordSpace,
+    to: CoordSpace,
+) -> Option<(i32, i32)> {
+    let (wx, wy, _, _) = focus_extents?;
+    match (from, to) {
+        (CoordSpace::Screen, CoordSpace::Window) => Some((-wx, -wy)),
+        (CoordSpace::Window, CoordSpace::Screen) => Some((wx, wy)),
+        _ => None,
+    }
+}
+
+/// The single hint whose label starts with `prefix`, if exactly one does.
+pub fn unique_prefix_match<'a>(
+    hints: &'a HintMap,
+    prefix: &str,
+) -> Option<(&'a String, &'a Child)> {
+    let mut matches = hints.iter().filter(|(label, _)| label.starts_with(prefix));
+    let first = matches.next()?;
+    matches.next().is_none().then_some(first)
+}
+
+/// Keep only children whose role is listed in `only_roles`. An empty list keeps
+/// everything; children without a known role are dropped otherwise.
+pub fn filter_roles(children: &mut Vec<Child>, only_roles: &[String]) {
+    if only_roles.is_empty() {
+        return;
+    }
+    children.retain(|c| c.role.as_ref().is_some_and(|r| only_roles.contains(r)));
+}
+
+/// Drop children whose center is closer than `min_distance` pixels to one that is
+/// kept, so dense grids don't get hints too close to tell apart. Elements with a
+/// known role win over ones without, then smaller (more precise) over larger;
+/// the survivors keep their original order. `min_distance` of 0 keeps everything.
+pub fn drop_clustered(children: Vec<Child>, min_distance: i32) -> Vec<Child> {
+    if min_distance <= 0 || children.len() < 2 {
+        return children;
+    }
+    let cell_of = |(x, y): (i32, i32)| (x.div_euclid(min_distance), y.div_euclid(min_distance));
+    let too_close = |a: (i32, i32), b: (i32, i32)| {
+        let (dx, dy) = ((a.0 - b.0) as i64, (a.1 - b.1) as i64);
+        dx * dx + dy * dy < (min_distance as i64) * (min_distance as i64)
+    };
+
+    let mut order: Vec<usize> = (0..children.len()).collect();
+    order.sort_by_key(|&idx| {
+        let child = &children[idx];
+        (
+            child.role.is_none(),
+            child.width as i64 * child.height as i64,
+        )
+    });
+
+    // Kept centers bucketed by `min_distance`-sized cells; only the 3x3 block
+    // around a center can hold one that is too close
+    let mut grid: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    let mut keep = vec![false; children.len()];
+    for idx in order {
+        let center = children[idx].center();
+        let (cx, cy) = cell_of(center);
+        let crowded = (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|cell| grid.get(&cell))
+            .flatten()
+            .any(|&other| too_close(center, other));
+        if !crowded {
+            grid.entry((cx, cy)).or_default().push(center);
+            keep[idx] = true;
+        }
+    }
+
+    let before = children.len();
+    let kept: Vec<Child> = children
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(child, keep)| keep.then_some(child))
+        .collect();
+    if kept.len() < before {
+        log::info!(
+            "dropped {} of {} hints closer than {}px to another",
+            before - kept.len(),
+            before,
+            min_distance
+        );
+    }
+    kept
+}
+
+/// Keep only children whose center lies inside `area` (x, y, width, height).
+pub fn retain_in_area(children: &mut Vec<Child>, area: (i32, i32, i32, i32)) {
+    let (x, y, w, h) = area;
+    children.retain(|child| {
+        let (cx, cy) = child.center();
+        cx >= x && cy >= y && cx < x + w && cy < y + h
+    });
+}
+
+/// Order children for right-to-left reading: top to bottom, and within a row
+/// from the right edge leftwards, so the top-right element gets the first label.
+pub fn sort_rtl(children: &mut [Child]) {
+    children.sort_by_key(|c| (c.absolute_y, std::cmp::Reverse(c.absolute_x + c.width)));
+}
+
+/// Order children by the distance of their centers from `point`, nearest first;
+/// equally distant ones keep their order.
+pub fn sort_by_distance(children: &mut [Child], (x, y): (i32, i32)) {
+    children.sort_by_key(|c| {
+        let (cx, cy) = c.center();
+        let (dx, dy) = ((cx - x) as i64, (cy - y) as i64);
+        dx * dx + dy * dy
+    });
+}
+
+/// Merge `Text`-role children that sit on the same line and are at most
+/// `max_gap` pixels apart into one child covering the whole run.
+pub fn merge_text_runs(children: Vec<Child>, max_gap: i32) -> Vec<Child> {
+    let (mut texts, mut result): (Vec<Child>, Vec<Child>) = children
+        .into_iter()
+        .partition(|c| c.role.as_deref() == Some("Text"));
+    texts.sort_by_key(|c| (c.absolute_y, c.absolute_x));
+
+    let mut runs: Vec<Child> = Vec::new();
+    for child in texts {
+        let adjacent = runs.iter_mut().rev().find(|run| {
+            let gap = child.absolute_x - (run.absolute_x + run.width);
+            same_line(run, &child)
+                && gap <= max_gap
+                && child.absolute_x + child.width >= run.absolute_x
+        });
+        match adjacent {
+            Some(run) => {
+                let right = (run.absolute_x + run.width).max(child.absolute_x + child.width);
+                let bottom = (run.absolute_y + run.height).max(child.absolute_y + child.height);
+                run.absolute_x = run.absolute_x.min(child.absolute_x);
+                run.absolute_y = run.absolute_y.min(child.absolute_y);
+                run.width = right - run.absolute_x;
+                run.height = bottom - run.absolute_y;
+            }
+            None => runs.push(child),
+        }
+    }
+
+    result.extend(runs);
+    result
+}
+
+/// Two boxes share a line when they overlap vertically by at least half the
+/// shorter one's height.
+fn same_line(a: &Child, b: &Child) -> bool {
+    let overlap =
+        (a.absolute_y + a.height).min(b.absolute_y + b.height) - a.absolute_y.max(b.absolute_y);
+    overlap * 2 >= a.height.min(b.height)
+}
+
+/// Shortest label length that gives `count` distinct labels over `radix` characters.
+fn label_length(count: usize, radix: usize) -> u32 {
+    let mut width = 1;
+    while (radix as u64)
+        .checked_pow(width)
+        .is_some_and(|pool| pool < count as u64)
+    {
+        width += 1;
+    }
+    width
+}
+
+/// Keep labels at most `max_len` characters long (0 = no limit). When `children`
+/// would need longer labels over `alphabet`, label with `fallback` if it is
+/// larger and fits them; otherwise drop the children past what `max_len`
+/// characters can label. Returns the alphabet to label with.
+pub fn fit_label_length(
+    children: &mut Vec<Child>,
+    alphabet: &str,
+    fallback: &str,
+    max_len: u32,
+) -> String {
+    let radix = alphabet.chars().count();
+    if max_len == 0 || radix < 2 || label_length(children.len(), radix) <= max_len {
+        return alphabet.to_string();
+    }
+
+    let fallback_radix = fallback.chars().count();
+    if fallback_radix > radix && label_length(children.len(), fallback_radix) <= max_len {
+        log::warn!(
+            "{} hints would need labels over {} characters with alphabet {:?}; using {:?}",
+            children.len(),
+            max_len,
+            alphabet,
+            fallback
+        );
+        return fallback.to_string();
+    }
+
+    let (radix, alphabet) = if fallback_radix > radix {
+        (fallback_radix, fallback)
+    } else {
+        (radix, alphabet)
+    };
+    let cap = (radix as u64)
+        .checked_pow(max_len)
+        .map_or(usize::MAX, |pool| pool.min(usize::MAX as u64) as usize);
+    log::warn!(
+        "{} hints don't fit in labels of {} characters; hinting only the first {}",
+        children.len(),
+        max_len,
+        cap
+    );
+    children.truncate(cap);
+    alphabet.to_string()
+}
+
 /// Generate hint labels for a set of children using the provided alphabet.
-pub fn generate_hints(children: &[Child], alphabet: &str) -> HintMap {
+///
+/// Labels in `reserved` are never generated, and neither is any label that is a
+/// prefix of a reserved one or has one as a prefix, so typing a reserved label is
+/// never ambiguous with a hint. Labels grow longer if needed to fit everything.
+pub fn generate_hints(children: &[Child], alphabet: &str, reserved: &[String]) -> HintMap {
     let mut result = HintMap::new();
     if children.is_empty() || alphabet.is_empty() {
         return result;
@@ -19,23 +376,730 @@ pub fn generate_hints(children: &[Child], alphabet: &str) -> HintMap {
 
     let base: Vec<char> = alphabet.chars().collect();
     let radix = base.len() as u32;
-    let needed = (children.len() as f64).log(radix as f64).ceil() as u32;
+    // A lone child still needs a character to type
+    let needed = ((children.len() as f64).log(radix as f64).ceil() as u32).max(1);
 
-    for (idx, child) in children.iter().enumerate() {
-        let mut n = idx as u32;
-        let mut label_chars = Vec::new();
-        for _ in 0..needed {
-            let digit = n % radix;
-            label_chars.push(base[digit as usize]);
-            n /= radix;
+    let reserved: Vec<&str> = reserved
+        .iter()
+        .map(String::as_str)
+        .filter(|r| !r.is_empty())
+        .collect();
+    let conflicts = |label: &str| {
+        reserved
+            .iter()
+            .any(|r| r.starts_with(label) || label.starts_with(r))
+    };
+
+    // Past the longest reserved label, extra width no longer frees up labels.
+    let max_width = needed
+        + reserved
+            .iter()
+            .map(|r| r.chars().count() as u32)
+            .max()
+            .unwrap_or(0);
+    let mut labels = Vec::new();
+    for width in needed..=max_width {
+        let Some(pool) = (radix as u64).checked_pow(width) else {
+            break;
+        };
+        labels = (0..pool.max(1))
+            .map(|idx| label_for(idx, &base, width))
+            .filter(|label| !conflicts(label))
+            .take(children.len())
+            .collect();
+        if labels.len() == children.len() {
+            break;
         }
-        if n > 0 {
-            label_chars.push(base[(n % radix) as usize]);
+    }
+    if labels.len() < children.len() {
+        log::warn!(
+            "reserved labels leave room for only {} of {} hints",
+            labels.len(),
+            children.len()
+        );
+    }
+
+    for (label, child) in labels.into_iter().zip(children) {
+        result.insert(label, child.clone());
+    }
+
+    result
+}
+
+/// Hint boxes the overlay last drew, in global coordinates, so the OpenCV
+/// backend of a later run doesn't hint them while that overlay is still up.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DrawnHints {
+    /// The overlay process that drew them
+    pub pid: u32,
+    pub rects: Vec<(i32, i32, i32, i32)>,
+}
+
+impl DrawnHints {
+    pub fn path() -> std::path::PathBuf {
+        default_cache_dir().join("drawn_hints.json")
+    }
+
+    /// The boxes of another overlay that is still running; empty once it exits.
+    pub fn load_live() -> Vec<(i32, i32, i32, i32)> {
+        let Some(drawn) = std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+        else {
+            return Vec::new();
+        };
+        let running = drawn.pid != std::process::id()
+            && std::path::Path::new(&format!("/proc/{}", drawn.pid)).exists();
+        if running { drawn.rects } else { Vec::new() }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
         }
-        label_chars.reverse();
-        let label: String = label_chars.into_iter().collect();
-        result.insert(label, *child);
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+/// Like `generate_hints`, but elements whose role has an entry in `role_prefixes`
+/// (e.g. {"Link": "f", "PushButton": "d"}) get labels starting with that prefix,
+/// and nothing else does, so the first key already says what kind of element a
+/// hint targets.
+pub fn generate_partitioned(
+    children: &[Child],
+    alphabet: &str,
+    reserved: &[String],
+    role_prefixes: &HashMap<String, String>,
+) -> HintMap {
+    let prefixes: Vec<&String> = role_prefixes.values().filter(|p| !p.is_empty()).collect();
+    if prefixes.is_empty() {
+        return generate_hints(children, alphabet, reserved);
     }
 
+    // Everything without a prefix labels around the prefixes
+    let mut general_reserved = reserved.to_vec();
+    general_reserved.extend(prefixes.iter().map(|p| p.to_string()));
+    let (grouped, general): (Vec<Child>, Vec<Child>) = children.iter().cloned().partition(|c| {
+        c.role
+            .as_ref()
+            .and_then(|r| role_prefixes.get(r))
+            .is_some_and(|p| !p.is_empty())
+    });
+    let mut result = generate_hints(&general, alphabet, &general_reserved);
+
+    let mut groups: Vec<(&String, Vec<Child>)> = Vec::new();
+    for child in grouped {
+        let prefix = &role_prefixes[child.role.as_ref().unwrap()];
+        match groups.iter_mut().find(|(p, _)| *p == prefix) {
+            Some((_, members)) => members.push(child),
+            None => groups.push((prefix, vec![child])),
+        }
+    }
+    for (prefix, members) in groups {
+        // Reserved labels inside this prefix's space still have to stay free
+        let inner_reserved: Vec<String> = reserved
+            .iter()
+            .filter_map(|r| r.strip_prefix(prefix.as_str()).map(str::to_string))
+            .collect();
+        for (label, child) in generate_hints(&members, alphabet, &inner_reserved) {
+            result.insert(format!("{prefix}{label}"), child);
+        }
+    }
     result
 }
+
+/// Label `children` so that elements which were already hinted in `previous` keep
+/// their label, e.g. after the list they sit in scrolled.
+///
+/// An element counts as the same when role and size match and it sits either at
+/// its old position or shifted by the displacement most same-shaped elements
+/// moved by (a scroll moves everything in the pane alike). Everything else gets a
+/// fresh label that doesn't clash with the kept ones.
+pub fn stable_hints(
+    previous: &HintMap,
+    children: &[Child],
+    alphabet: &str,
+    reserved: &[String],
+) -> HintMap {
+    let same_shape =
+        |a: &Child, b: &Child| a.role == b.role && a.width == b.width && a.height == b.height;
+
+    // Most common displacement between same-shaped old/new pairs, ignoring
+    // elements that didn't move at all
+    let mut votes: HashMap<(i32, i32), usize> = HashMap::new();
+    for child in children {
+        for old in previous.values().filter(|old| same_shape(old, child)) {
+            let shift = (
+                child.absolute_x - old.absolute_x,
+                child.absolute_y - old.absolute_y,
+            );
+            if shift != (0, 0) {
+                *votes.entry(shift).or_default() += 1;
+            }
+        }
+    }
+    let scroll_shift = votes
+        .into_iter()
+        .max_by_key(|&(_, n)| n)
+        .map(|(shift, _)| shift);
+
+    let mut result = HintMap::new();
+    let mut fresh = Vec::new();
+    for child in children {
+        let kept = [Some((0, 0)), scroll_shift]
+            .into_iter()
+            .flatten()
+            .find_map(|(dx, dy)| {
+                previous.iter().find(|(label, old)| {
+                    same_shape(old, child)
+                        && old.absolute_x + dx == child.absolute_x
+                        && old.absolute_y + dy == child.absolute_y
+                        && !result.contains_key(*label)
+                })
+            });
+        match kept {
+            Some((label, _)) => {
+                result.insert(label.clone(), child.clone());
+            }
+            None => fresh.push(child.clone()),
+        }
+    }
+
+    let mut taken: Vec<String> = result.keys().cloned().collect();
+    taken.extend(reserved.iter().cloned());
+    result.extend(generate_hints(&fresh, alphabet, &taken));
+    result
+}
+
+/// Labels from the last run, so `hints.stable_labels` can carry them over when
+/// the same window is hinted again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LabelCache {
+    pub focus_extents: Option<(i32, i32, i32, i32)>,
+    pub hints: HintMap,
+}
+
+impl LabelCache {
+    pub fn path() -> std::path::PathBuf {
+        default_cache_dir().join("last_hints.json")
+    }
+
+    pub fn load() -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path()).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+/// The element the overlay last clicked, kept for `hints.mark_last_clicked`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LastClick {
+    pub child: Child,
+}
+
+impl LastClick {
+    pub fn path() -> std::path::PathBuf {
+        default_cache_dir().join("last_click.json")
+    }
+
+    pub fn load() -> Option<Self> {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn load_from(path: &std::path::Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    pub fn save_to(&self, path: &std::path::Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+/// Whether `a` and `b` are the same element: the same AT-SPI object when both
+/// have a path, otherwise the same role at the same bounds.
+pub fn same_element(a: &Child, b: &Child) -> bool {
+    if a.role != b.role {
+        return false;
+    }
+    match (&a.accessible_path, &b.accessible_path) {
+        (Some(a), Some(b)) => a == b,
+        _ => {
+            (a.absolute_x, a.absolute_y, a.width, a.height)
+                == (b.absolute_x, b.absolute_y, b.width, b.height)
+        }
+    }
+}
+
+/// Label of the hint on `last` if that element is among `hints`.
+pub fn last_clicked_label(hints: &HintMap, last: &Child) -> Option<String> {
+    hints
+        .iter()
+        .find(|(_, child)| same_element(child, last))
+        .map(|(label, _)| label.clone())
+}
+
+/// Spell `idx` in base `alphabet` using `width` digits (most significant first).
+fn label_for(idx: u64, base: &[char], width: u32) -> String {
+    let radix = base.len() as u64;
+    let mut n = idx;
+    let mut label_chars = Vec::new();
+    for _ in 0..width {
+        label_chars.push(base[(n % radix) as usize]);
+        n /= radix;
+    }
+    if n > 0 {
+        label_chars.push(base[(n % radix) as usize]);
+    }
+    label_chars.reverse();
+    label_chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn child((x, y, w, h): (i32, i32, i32, i32), role: Option<&str>) -> Child {
+        Child {
+            absolute_x: x,
+            absolute_y: y,
+            width: w,
+            height: h,
+            role: role.map(Into::into),
+            accessible_path: None,
+            name: None,
+            source: None,
+        }
+    }
+
+    fn extents(children: &[Child]) -> Vec<(i32, i32, i32, i32)> {
+        children
+            .iter()
+            .map(|c| (c.absolute_x, c.absolute_y, c.width, c.height))
+            .collect()
+    }
+
+    #[test]
+    fn whole_window_is_one_child_with_a_one_char_label() {
+        let extents = (100, 50, 800, 600);
+        let hints = generate_hints(&[Child::for_window(extents)], "asdf", &[]);
+        assert_eq!(hints.len(), 1);
+        let (label, child) = hints.iter().next().unwrap();
+        assert_eq!(label, "a");
+        assert_eq!(
+            (
+                child.absolute_x,
+                child.absolute_y,
+                child.width,
+                child.height
+            ),
+            extents
+        );
+    }
+
+    #[test]
+    fn filter_roles_keeps_only_listed_roles() {
+        let mut children = vec![
+            child((0, 0, 10, 10), Some("Link")),
+            child((20, 0, 10, 10), Some("PushButton")),
+            child((40, 0, 10, 10), None),
+            child((60, 0, 10, 10), Some("Link")),
+        ];
+        filter_roles(&mut children, &["Link".to_string()]);
+        assert_eq!(extents(&children), [(0, 0, 10, 10), (60, 0, 10, 10)]);
+
+        let mut all = vec![child((0, 0, 10, 10), None)];
+        filter_roles(&mut all, &[]);
+        assert_eq!(all.len(), 1);
+    }
+
+    #[test]
+    fn unique_prefix_match_needs_exactly_one_hint() {
+        let hints: HintMap = [
+            ("a".to_string(), child((0, 0, 10, 10), None)),
+            ("sd".to_string(), child((20, 0, 10, 10), None)),
+            ("sf".to_string(), child((40, 0, 10, 10), None)),
+        ]
+        .into();
+        let label = |prefix| unique_prefix_match(&hints, prefix).map(|(label, _)| label.as_str());
+        assert_eq!(label("a"), Some("a"));
+        assert_eq!(label("sd"), Some("sd"));
+        // Ambiguous
+        assert_eq!(label("s"), None);
+        assert_eq!(label(""), None);
+        // No match
+        assert_eq!(label("x"), None);
+        assert_eq!(label("sdd"), None);
+    }
+
+    #[test]
+    fn merge_text_runs_joins_close_text_on_one_line() {
+        let children = vec![
+            child((0, 0, 20, 10), Some("Text")),
+            child((30, 0, 10, 10), Some("Link")),
+            child((24, 0, 20, 10), Some("Text")),
+            child((100, 0, 20, 10), Some("Text")),
+            child((0, 30, 20, 10), Some("Text")),
+        ];
+        let merged = merge_text_runs(children, 5);
+        assert_eq!(
+            extents(&merged),
+            [
+                (30, 0, 10, 10),
+                (0, 0, 44, 10),
+                (100, 0, 20, 10),
+                (0, 30, 20, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_labels_and_their_prefixes_are_skipped() {
+        let children: Vec<Child> = (0..3).map(|i| child((i * 20, 0, 10, 10), None)).collect();
+        let mut labels: Vec<String> = generate_hints(&children, "asdf", &["sa".to_string()])
+            .into_keys()
+            .collect();
+        labels.sort();
+        assert_eq!(labels, ["a", "d", "f"]);
+
+        let children: Vec<Child> = (0..5).map(|i| child((i * 20, 0, 10, 10), None)).collect();
+        let hints = generate_hints(&children, "asdf", &["a".to_string()]);
+        assert_eq!(hints.len(), 5);
+        assert!(
+            hints
+                .keys()
+                .all(|label| label.len() == 2 && !label.starts_with('a'))
+        );
+    }
+
+    #[test]
+    fn sort_rtl_starts_top_right() {
+        let mut children = vec![
+            child((0, 0, 10, 10), None),
+            child((0, 40, 10, 10), None),
+            child((50, 0, 10, 10), None),
+            child((20, 0, 10, 10), None),
+        ];
+        sort_rtl(&mut children);
+        assert_eq!(
+            extents(&children),
+            [
+                (50, 0, 10, 10),
+                (20, 0, 10, 10),
+                (0, 0, 10, 10),
+                (0, 40, 10, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn stable_hints_keep_labels_across_a_scroll() {
+        // Uneven spacing, so only the real scroll lines up several elements
+        let xs = [0, 30, 70, 130];
+        let at = |y| -> Vec<Child> { xs.iter().map(|&x| child((x, y, 10, 10), None)).collect() };
+        let first = generate_hints(&at(100), "asdf", &[]);
+        let label_at = |hints: &HintMap, x: i32, y: i32| {
+            hints
+                .iter()
+                .find(|(_, c)| (c.absolute_x, c.absolute_y) == (x, y))
+                .map(|(label, _)| label.clone())
+        };
+
+        // Collected again unchanged: the same labels
+        let again = stable_hints(&first, &at(100), "asdf", &[]);
+        for &x in &xs {
+            assert_eq!(label_at(&again, x, 100), label_at(&first, x, 100));
+        }
+
+        // Scrolled up 30px: the first element left, a new one came in
+        let mut scrolled = at(70).split_off(1);
+        scrolled.push(child((160, 90, 12, 12), None));
+        let second = stable_hints(&first, &scrolled, "asdf", &[]);
+        assert_eq!(second.len(), 4);
+        for &x in &xs[1..] {
+            assert_eq!(label_at(&second, x, 70), label_at(&first, x, 100));
+        }
+        let fresh = label_at(&second, 160, 90).unwrap();
+        assert!(
+            xs[1..]
+                .iter()
+                .all(|&x| label_at(&first, x, 100).unwrap() != fresh)
+        );
+    }
+
+    #[test]
+    fn window_space_round_trips() {
+        let focus = Some((100, 50, 800, 600));
+        let children = vec![child((150, 80, 10, 10), None), child((90, 40, 20, 5), None)];
+        let hints = generate_hints(&children, "asdf", &[]);
+        let at = |hints: &HintMap, label: &str| extents(std::slice::from_ref(&hints[label]))[0];
+
+        let relative = convert_hints(hints.clone(), focus, CoordSpace::Screen, CoordSpace::Window);
+        for (label, child) in &hints {
+            assert_eq!(
+                at(&relative, label),
+                (
+                    child.absolute_x - 100,
+                    child.absolute_y - 50,
+                    child.width,
+                    child.height
+                )
+            );
+        }
+        let back = convert_hints(relative, focus, CoordSpace::Window, CoordSpace::Screen);
+        for label in hints.keys() {
+            assert_eq!(at(&back, label), at(&hints, label));
+        }
+
+        // Same space, or no window to measure from: unchanged
+        let same = convert_hints(hints.clone(), focus, CoordSpace::Window, CoordSpace::Window);
+        let unfocused = convert_hints(hints.clone(), None, CoordSpace::Screen, CoordSpace::Window);
+        for label in hints.keys() {
+            assert_eq!(at(&same, label), at(&hints, label));
+            assert_eq!(at(&unfocused, label), at(&hints, label));
+        }
+    }
+
+    fn row(count: i32) -> Vec<Child> {
+        (0..count)
+            .map(|i| child((i * 20, 0, 10, 10), None))
+            .collect()
+    }
+
+    #[test]
+    fn fit_label_length_drops_what_does_not_fit() {
+        let mut children = row(20);
+        assert_eq!(fit_label_length(&mut children, "ab", "", 0), "ab");
+        assert_eq!(children.len(), 20);
+        assert_eq!(fit_label_length(&mut children, "ab", "", 5), "ab");
+        assert_eq!(children.len(), 20);
+
+        assert_eq!(fit_label_length(&mut children, "ab", "", 2), "ab");
+        assert_eq!(extents(&children), extents(&row(4)));
+    }
+
+    #[test]
+    fn retain_in_area_keeps_children_centered_inside() {
+        let area = (100, 100, 200, 100);
+        let mut children = vec![
+            child((120, 120, 20, 20), None), // inside
+            child((10, 10, 20, 20), None),   // outside
+            child((90, 140, 40, 20), None),  // straddles the left edge, center inside
+            child((280, 140, 60, 20), None), // straddles the right edge, center outside
+            child((290, 190, 20, 20), None), // center exactly on the far corner
+        ];
+        retain_in_area(&mut children, area);
+        assert_eq!(
+            extents(&children),
+            vec![(120, 120, 20, 20), (90, 140, 40, 20)]
+        );
+    }
+
+    #[test]
+    fn drop_clustered_prefers_roles_then_smaller_elements() {
+        let children = vec![
+            child((0, 0, 40, 40), None),
+            child((100, 0, 10, 10), None),
+            child((16, 16, 10, 10), None),
+            child((15, 15, 10, 10), Some("Link")),
+        ];
+        assert_eq!(drop_clustered(children.clone(), 0).len(), 4);
+        assert_eq!(
+            extents(&drop_clustered(children, 8)),
+            [(100, 0, 10, 10), (15, 15, 10, 10)]
+        );
+    }
+
+    #[test]
+    fn partitions_are_disjoint_and_prefix_free() {
+        let children: Vec<Child> = (0..15)
+            .map(|i| {
+                let role = ["Link", "PushButton", "Text"][i as usize % 3];
+                child((i * 20, 0, 10, 10), Some(role))
+            })
+            .collect();
+        let role_prefixes = HashMap::from([
+            ("Link".to_string(), "f".to_string()),
+            ("PushButton".to_string(), "d".to_string()),
+        ]);
+        let hints = generate_partitioned(&children, "asdf", &["sa".to_string()], &role_prefixes);
+        assert_eq!(hints.len(), 15);
+
+        for (label, child) in &hints {
+            let expected = match child.role.as_deref() {
+                Some("Link") => Some('f'),
+                Some("PushButton") => Some('d'),
+                _ => None,
+            };
+            match expected {
+                Some(prefix) => assert!(label.starts_with(prefix), "{label}"),
+                None => assert!(!label.starts_with(['f', 'd']), "{label}"),
+            }
+            assert!(!label.starts_with("sa") && !"sa".starts_with(label.as_str()));
+        }
+        for a in hints.keys() {
+            for b in hints.keys().filter(|b| *b != a) {
+                assert!(!b.starts_with(a.as_str()), "{a} is a prefix of {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn last_clicked_label_finds_the_saved_element() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache/last_click.json");
+        assert!(LastClick::load_from(&path).is_none());
+
+        let clicked = Child {
+            accessible_path: Some("/org/a11y/atspi/accessible/42".into()),
+            ..child((40, 0, 10, 10), Some("PushButton"))
+        };
+        LastClick { child: clicked }.save_to(&path).unwrap();
+        let last = LastClick::load_from(&path).unwrap().child;
+
+        // Next run: the object moved by a relayout, and another took its place
+        let moved = Child {
+            accessible_path: Some("/org/a11y/atspi/accessible/42".into()),
+            ..child((60, 5, 10, 10), Some("PushButton"))
+        };
+        let replacement = Child {
+            accessible_path: Some("/org/a11y/atspi/accessible/7".into()),
+            ..child((40, 0, 10, 10), Some("PushButton"))
+        };
+        let hints: HintMap = [("a".to_string(), replacement), ("s".to_string(), moved)].into();
+        assert_eq!(last_clicked_label(&hints, &last), Some("s".to_string()));
+
+        // Without paths, role and bounds have to match
+        let last = child((0, 0, 10, 10), Some("Link"));
+        let mut hints: HintMap = [
+            ("a".to_string(), child((0, 0, 10, 10), Some("Text"))),
+            ("s".to_string(), child((0, 0, 10, 10), Some("Link"))),
+        ]
+        .into();
+        assert_eq!(last_clicked_label(&hints, &last), Some("s".to_string()));
+        hints.remove("s");
+        assert_eq!(last_clicked_label(&hints, &last), None);
+    }
+
+    #[test]
+    fn generate_partitioned_labels_with_the_given_alphabet() {
+        let children = row(3);
+        let hints = generate_partitioned(&children, "jk", &[], &HashMap::new());
+        let mut labels: Vec<_> = hints.keys().map(String::as_str).collect();
+        labels.sort();
+        assert_eq!(labels, ["jj", "jk", "kj"]);
+        assert_eq!(
+            extents(&[hints["jj"].clone(), hints["kj"].clone()]),
+            [(0, 0, 10, 10), (40, 0, 10, 10)]
+        );
+    }
+
+    #[test]
+    fn normalize_extents_flips_negative_and_drops_empty_boxes() {
+        assert_eq!(normalize_extents((10, 20, 5, 7)), Some((10, 20, 5, 7)));
+        assert_eq!(normalize_extents((10, 10, -4, 6)), Some((6, 10, 4, 6)));
+        assert_eq!(normalize_extents((10, 20, 5, -5)), Some((10, 15, 5, 5)));
+        assert_eq!(normalize_extents((0, 0, -3, -3)), Some((-3, -3, 3, 3)));
+        // Zero-size
+        assert_eq!(normalize_extents((0, 0, 0, 5)), None);
+        assert_eq!(normalize_extents((0, 0, 5, 0)), None);
+        // Far edge overflows
+        assert_eq!(normalize_extents((i32::MAX - 1, 0, 5, 5)), None);
+        assert_eq!(normalize_extents((i32::MIN, 0, -1, 1)), None);
+        assert_eq!(normalize_extents((0, 0, i32::MIN, 1)), None);
+    }
+
+    #[test]
+    fn generate_partitioned_gives_fewer_children_shorter_labels() {
+        let mut children: Vec<Child> = (0..20)
+            .map(|i| {
+                child(
+                    (i * 20, 0, 10, 10),
+                    Some(if i % 5 == 4 { "Text" } else { "Link" }),
+                )
+            })
+            .collect();
+        let before = generate_partitioned(&children, "asdf", &[], &HashMap::new());
+        assert!(before.keys().all(|label| label.len() == 3));
+
+        filter_roles(&mut children, &["Text".to_string()]);
+        assert_eq!(children.len(), 4);
+        let after = generate_partitioned(&children, "asdf", &[], &HashMap::new());
+        for (label, child) in ["a", "s", "d", "f"].iter().zip(&children) {
+            assert_eq!(
+                extents(std::slice::from_ref(&after[*label])),
+                extents(std::slice::from_ref(child))
+            );
+        }
+    }
+
+    #[test]
+    fn fit_label_length_switches_to_a_larger_fallback() {
+        let mut children = row(20);
+        assert_eq!(
+            fit_label_length(&mut children, "ab", "asdfghjkl", 2),
+            "asdfghjkl"
+        );
+        assert_eq!(children.len(), 20);
+
+        // Too many even for the fallback: label with it and drop the rest
+        let mut children = row(100);
+        assert_eq!(
+            fit_label_length(&mut children, "ab", "asdfghjkl", 2),
+            "asdfghjkl"
+        );
+        assert_eq!(children.len(), 81);
+
+        // A fallback no larger than the alphabet is never used
+        let mut children = row(20);
+        assert_eq!(fit_label_length(&mut children, "asdf", "ab", 2), "asdf");
+        assert_eq!(children.len(), 16);
+    }
+
+    #[test]
+    fn sort_by_distance_puts_the_nearest_first() {
+        let mut children = vec![
+            child((0, 0, 10, 10), None),
+            child((200, 200, 10, 10), None),
+            child((90, 90, 20, 20), None),
+            child((300, 0, 10, 10), None),
+        ];
+        sort_by_distance(&mut children, (100, 100));
+        assert_eq!(
+            extents(&children),
+            [
+                (90, 90, 20, 20),
+                (0, 0, 10, 10),
+                (200, 200, 10, 10),
+                (300, 0, 10, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn click_point_uses_the_offset_configured_for_the_role() {
+        let offsets = HashMap::from([("ScrollBar".to_string(), (0.5, 0.1))]);
+        let scrollbar = child((100, 0, 20, 200), Some("ScrollBar"));
+        assert_eq!(scrollbar.click_point(&offsets), (110, 20));
+        assert_eq!(scrollbar.click_point(&HashMap::new()), scrollbar.center());
+        let button = child((100, 0, 20, 200), Some("PushButton"));
+        assert_eq!(button.click_point(&offsets), (110, 100));
+        let unknown = child((100, 0, 20, 200), None);
+        assert_eq!(unknown.click_point(&offsets), (110, 100));
+    }
+}
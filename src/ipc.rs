@@ -1,8 +1,21 @@
-use crate::consts::UNIX_DOMAIN_SOCKET_FILE;
+use crate::backends::BackendResult;
+use crate::consts::{DAEMON_LOCK_FILE, UNIX_DOMAIN_SOCKET_FILE};
 use anyhow::{Context, Result, anyhow};
+use nix::errno::Errno;
+use nix::fcntl::{FcntlArg, FdFlag, Flock, FlockArg, fcntl};
 use serde::{Deserialize, Serialize};
+use std::fs::File;
 use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// First file descriptor systemd passes to socket-activated services.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Whether `send` asks the daemon to log requests in full; see [`set_verbose`].
+static VERBOSE: AtomicBool = AtomicBool::new(true);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum MouseMode {
@@ -10,16 +23,36 @@ pub enum MouseMode {
     Scroll,
 }
 
+/// A touchpad gesture, emulated with the wheel events apps already react to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GestureKind {
+    /// Two-finger swipe by (dx, dy) logical pixels of finger travel, scrolling
+    /// the content along with the fingers like natural touchpad scrolling
+    Swipe { dx: i32, dy: i32 },
+    /// Pinch to `scale` times the current size (above 1 zooms in), sent as
+    /// Ctrl+wheel, which browsers, editors and viewers take as zoom
+    Pinch { scale: f64 },
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
     Move {
         x: i32,
         y: i32,
         absolute: bool,
+        /// Glide to the target over this many milliseconds instead of jumping
+        duration_ms: Option<u32>,
     },
+    /// Wheel detents as `Pointer::scroll` takes them: positive `y` is up,
+    /// positive `x` is right
     Scroll {
         x: i32,
         y: i32,
+        /// Move the pointer here (absolute) first, so the wheel reaches the element
+        /// under it rather than whatever the cursor happens to be over
+        at: Option<(i32, i32)>,
+        /// Multiplier on (x, y), e.g. a typed repeat count; 0 counts as 1
+        count: u32,
     },
     Click {
         x: i32,
@@ -28,20 +61,139 @@ pub enum Request {
         button_states: Vec<i32>,
         repeat: u32,
         absolute: bool,
+        /// Move to (x, y) before clicking; false clicks at the current pointer
+        /// position and ignores x/y
+        move_first: bool,
+        /// Connector name (e.g. "DP-2") of the output (x, y) is on, so the daemon
+        /// maps it with that output's scale instead of the first monitor's
+        output: Option<String>,
+        /// Let the overlay's input grab settle before clicking. Clients that know
+        /// their overlay is already unmapped send false to skip the wait
+        settle: bool,
     },
     DoMouseAction {
         key: String,
         mode: MouseMode,
     },
+    /// Press and release a keyboard key, named like evdev's `KeyCode` (e.g. "KEY_ENTER").
+    Key {
+        key: String,
+    },
+    /// Gather hintable elements in the daemon. `timeout_ms` of 0 means no limit;
+    /// otherwise the daemon answers with whatever it has at the deadline.
+    Collect {
+        timeout_ms: u64,
+    },
+    /// Left-click element `id` (its index in the children of the daemon's last
+    /// `Collect`) at fractions `(dx_frac, dy_frac)` of its width and height, so
+    /// clients don't do coordinate math themselves. (0.5, 0.5) is the center.
+    ClickElement {
+        id: usize,
+        dx_frac: f64,
+        dy_frac: f64,
+    },
+    /// Re-read the config file. The daemon keeps its old config and answers with
+    /// an error if the file doesn't parse; the pointer backend isn't rebuilt.
+    ReloadConfig,
+    /// Abort the action the daemon is running (a glide, a smooth scroll) between
+    /// steps, drop any requests queued before this one, and release a button a
+    /// half-finished drag left held down.
+    Cancel,
+    /// Run `steps` in order, stopping at the first that fails or at a Cancel.
+    /// Answers with the last step's response.
+    Sequence {
+        steps: Vec<Request>,
+    },
+    /// Perform a touchpad gesture, first moving the pointer to `at` (absolute) so
+    /// it reaches the element there.
+    Gesture {
+        kind: GestureKind,
+        at: Option<(i32, i32)>,
+    },
+    /// Drag with the left button from where the pointer is to `(x, y)`
+    /// (absolute). A drop within `mouse.drag_edge_margin` of an edge of `area`
+    /// (logical x, y, width, height) scrolls towards that edge
+    /// `mouse.drag_edge_scroll_steps` times before the button is released.
+    Drag {
+        x: i32,
+        y: i32,
+        area: Option<(i32, i32, i32, i32)>,
+        /// Wait for the overlay to let go of its input grab before pressing
+        settle: bool,
+    },
+    /// Run `request`, logging it in full when `verbose` and with a one-line
+    /// summary otherwise, so routine actions keep the daemon's journal short.
+    Logged {
+        verbose: bool,
+        request: Box<Request>,
+    },
+}
+
+impl Request {
+    /// Variant name, for logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Move { .. } => "Move",
+            Request::Scroll { .. } => "Scroll",
+            Request::Click { .. } => "Click",
+            Request::DoMouseAction { .. } => "DoMouseAction",
+            Request::Key { .. } => "Key",
+            Request::Collect { .. } => "Collect",
+            Request::ClickElement { .. } => "ClickElement",
+            Request::ReloadConfig => "ReloadConfig",
+            Request::Cancel => "Cancel",
+            Request::Sequence { .. } => "Sequence",
+            Request::Gesture { .. } => "Gesture",
+            Request::Drag { .. } => "Drag",
+            Request::Logged { request, .. } => request.name(),
+        }
+    }
+
+    /// Whether this is a Cancel, possibly wrapped in `Logged`.
+    pub fn is_cancel(&self) -> bool {
+        match self {
+            Request::Logged { request, .. } => request.is_cancel(),
+            other => matches!(other, Request::Cancel),
+        }
+    }
+
+    /// The request to run and whether to log it in full; requests that don't
+    /// say are verbose.
+    pub fn verbosity(self) -> (Request, bool) {
+        match self {
+            Request::Logged { verbose, request } => (*request, verbose),
+            other => (other, true),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
     Ok,
     Error(String),
+    Children(BackendResult),
+    /// Where a Move/Click actually went, in device pixels after scaling and
+    /// clamping (for relative moves, the delta that was emitted).
+    Position {
+        x: i32,
+        y: i32,
+    },
+}
+
+/// Have later `send` calls from this process ask the daemon for full request
+/// logs (the default) or a one-line summary each.
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
 }
 
 pub fn send(request: Request) -> Result<Response> {
+    let request = match VERBOSE.load(Ordering::Relaxed) {
+        true => request,
+        false => Request::Logged {
+            verbose: false,
+            request: Box::new(request),
+        },
+    };
     log::info!("IPC: ========== Sending Request ==========");
     log::info!("IPC: Connecting to socket: {}", UNIX_DOMAIN_SOCKET_FILE);
     let mut stream = UnixStream::connect(UNIX_DOMAIN_SOCKET_FILE)
@@ -74,12 +226,157 @@ pub fn send(request: Request) -> Result<Response> {
     Ok(resp)
 }
 
-pub fn ensure_daemon_running() -> Result<()> {
-    if std::path::Path::new(UNIX_DOMAIN_SOCKET_FILE).exists() {
+/// Fail unless the daemon's socket exists at `socket` (normally
+/// `UNIX_DOMAIN_SOCKET_FILE`).
+pub fn ensure_daemon_running(socket: &Path) -> Result<()> {
+    if socket.exists() {
         return Ok(());
     }
     Err(anyhow!(
         "mouse daemon socket not found at {}. Start `hintsd` first.",
-        UNIX_DOMAIN_SOCKET_FILE
+        socket.display()
     ))
 }
+
+/// Take the daemon's instance lock at `DAEMON_LOCK_FILE`; see [`lock_instance`].
+pub fn lock_daemon_instance() -> Result<Flock<File>> {
+    lock_instance(Path::new(DAEMON_LOCK_FILE))
+}
+
+/// Take the instance lock at `path`, failing when another `hintsd` holds it. Keep
+/// the returned lock alive for the daemon's lifetime; the kernel drops it when
+/// the process exits, however it exits. The lock file records the holder's pid.
+pub fn lock_instance(path: &Path) -> Result<Flock<File>> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => {
+            lock.set_len(0)?;
+            write!(&*lock, "{}", std::process::id())?;
+            Ok(lock)
+        }
+        Err((file, Errno::EWOULDBLOCK)) => {
+            let owner = std::io::read_to_string(file).unwrap_or_default();
+            Err(anyhow!(
+                "another hintsd (pid {}) is already running; not starting a second one",
+                owner.trim()
+            ))
+        }
+        Err((_, errno)) => Err(anyhow!("lock {}: {errno}", path.display())),
+    }
+}
+
+/// Listen on `path`, first removing a socket file a dead daemon left behind.
+/// Only call with the instance lock held, or a live daemon's socket goes too.
+pub fn bind_socket(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        std::fs::remove_file(path).with_context(|| format!("remove {}", path.display()))?;
+    }
+    UnixListener::bind(path).with_context(|| format!("bind {}", path.display()))
+}
+
+/// Listening socket inherited through systemd socket activation, if any.
+///
+/// Only honoured when `LISTEN_PID` names this process, so a stale environment
+/// inherited from a parent is ignored. The first passed fd must be the listener.
+pub fn inherited_listener() -> Result<Option<UnixListener>> {
+    listener_from_env(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        SD_LISTEN_FDS_START,
+    )
+}
+
+/// `inherited_listener` over the given `LISTEN_PID`/`LISTEN_FDS` values, taking
+/// ownership of `first_fd` only when they pass a listener to this process.
+fn listener_from_env(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    first_fd: RawFd,
+) -> Result<Option<UnixListener>> {
+    let for_us = listen_pid
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    if !for_us {
+        return Ok(None);
+    }
+    let fds = listen_fds.and_then(|n| n.parse::<i32>().ok()).unwrap_or(0);
+    if fds < 1 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        log::warn!("IPC: {} fds passed by systemd; using only the first", fds);
+    }
+
+    // systemd hands fds over without CLOEXEC; keep it from leaking into the
+    // helper processes (hyprctl, ydotool) the daemon spawns.
+    fcntl(first_fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+        .with_context(|| format!("fd {first_fd} from LISTEN_FDS is not open"))?;
+    // SAFETY: LISTEN_PID/LISTEN_FDS say `first_fd` was opened for this process
+    // and nothing else in the daemon owns it.
+    let listener = unsafe { UnixListener::from_raw_fd(first_fd) };
+    Ok(Some(listener))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_startup_aborts_and_keeps_the_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let (lock, socket) = (
+            dir.path().join("hints.lock"),
+            dir.path().join("hints.socket"),
+        );
+
+        let _first = lock_instance(&lock).unwrap();
+        let _listener = bind_socket(&socket).unwrap();
+
+        let second = lock_instance(&lock);
+        assert!(second.unwrap_err().to_string().contains("already running"));
+        assert!(socket.exists());
+        assert!(UnixStream::connect(&socket).is_ok());
+    }
+
+    #[test]
+    fn inherited_listener_only_for_this_process() {
+        use std::os::fd::{AsRawFd, IntoRawFd};
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("hints.socket");
+        let bound = UnixListener::bind(&socket).unwrap();
+        let fd = bound.as_raw_fd();
+        let pid = std::process::id().to_string();
+
+        // Not ours, or no fds: the fd is left alone
+        let other = (std::process::id() + 1).to_string();
+        assert!(
+            listener_from_env(Some(&other), Some("1"), fd)
+                .unwrap()
+                .is_none()
+        );
+        assert!(listener_from_env(None, Some("1"), fd).unwrap().is_none());
+        assert!(
+            listener_from_env(Some(&pid), Some("0"), fd)
+                .unwrap()
+                .is_none()
+        );
+        assert!(listener_from_env(Some(&pid), None, fd).unwrap().is_none());
+
+        // As systemd passes it: without CLOEXEC
+        let fd = bound.into_raw_fd();
+        fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty())).unwrap();
+        let listener = listener_from_env(Some(&pid), Some("2"), fd)
+            .unwrap()
+            .unwrap();
+        let flags = fcntl(fd, FcntlArg::F_GETFD).unwrap();
+        assert!(FdFlag::from_bits_truncate(flags).contains(FdFlag::FD_CLOEXEC));
+        let _client = UnixStream::connect(&socket).unwrap();
+        assert!(listener.accept().is_ok());
+    }
+}
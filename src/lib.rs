@@ -1,6 +1,8 @@
 pub mod backends;
 pub mod config;
 pub mod consts;
+pub mod coords;
+pub mod doctor;
 pub mod hints;
 pub mod ipc;
 pub mod mouse;
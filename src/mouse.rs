@@ -1,11 +1,20 @@
-use anyhow::Result;
+use crate::config::{AbsoluteMapping, ClickTool, MouseConfig, MoveTool, ScrollMode};
+use crate::coords;
+use crate::ipc::GestureKind;
+use anyhow::{Result, anyhow};
 use evdev::{
-    AbsInfo, AbsoluteAxisCode, AttributeSet, EventType, InputEvent, KeyCode, RelativeAxisCode,
-    UinputAbsSetup, uinput::VirtualDevice,
+    AbsInfo, AbsoluteAxisCode, AttributeSet, EventType, InputEvent, KeyCode, MiscCode,
+    RelativeAxisCode, UinputAbsSetup, uinput::VirtualDevice,
 };
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "ei")]
+pub mod ei;
+#[cfg(feature = "virtual-pointer")]
+pub mod virtual_pointer;
 
 #[derive(Debug, Clone, Copy)]
 pub enum MouseButton {
@@ -14,18 +23,485 @@ pub enum MouseButton {
     Middle,
 }
 
+/// Logical origin and scale factor of one output, for clicks tagged with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputScale {
+    pub origin: (i32, i32),
+    pub scale: i32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum MouseButtonState {
     Down,
     Up,
 }
 
+/// Set when `hintsd` receives a `Request::Cancel`; multi-step actions check it
+/// between steps and stop early.
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Ask the running action to stop at its next step.
+pub fn request_cancel() {
+    CANCEL.store(true, Ordering::SeqCst);
+}
+
+/// Reset the cancel flag once the daemon is back in a safe state.
+pub fn clear_cancel() {
+    CANCEL.store(false, Ordering::SeqCst);
+}
+
+/// Whether a cancel was requested and hasn't been handled yet.
+pub fn cancel_pending() -> bool {
+    CANCEL.load(Ordering::SeqCst)
+}
+
+/// Error out of a multi-step action if a cancel is pending.
+pub fn check_cancel(done: i32, total: i32) -> Result<()> {
+    if cancel_pending() {
+        return Err(anyhow!("cancelled after {done} of {total} steps"));
+    }
+    Ok(())
+}
+
+/// Button still held down after sending `states` for `button`, given the one
+/// `held` before: a trailing press holds it, a trailing release lets go.
+pub fn held_after(
+    held: Option<MouseButton>,
+    button: MouseButton,
+    states: &[MouseButtonState],
+) -> Option<MouseButton> {
+    match states.last() {
+        Some(MouseButtonState::Down) => Some(button),
+        Some(MouseButtonState::Up) => None,
+        None => held,
+    }
+}
+
+/// A sink for synthetic pointer events. `hintsd` drives whichever implementation
+/// `MouseConfig.backend` selects through this trait.
+pub trait Pointer {
+    fn name(&self) -> &'static str;
+    /// Turn the wheel by `(x, y)` detents in evdev's sense: positive `x` scrolls
+    /// right and positive `y` scrolls up. Backends whose protocol counts down as
+    /// positive flip `y`.
+    fn scroll(&mut self, x: i32, y: i32) -> Result<()>;
+    fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)>;
+    /// Click `button`, first moving to `target` when given; `None` clicks wherever
+    /// the pointer is. Returns the emitted position of that move.
+    fn click(
+        &mut self,
+        target: Option<(i32, i32)>,
+        button: MouseButton,
+        button_states: &[MouseButtonState],
+        repeat: u32,
+        absolute: bool,
+    ) -> Result<Option<(i32, i32)>>;
+    /// Press and release a keyboard key.
+    fn key(&mut self, key: KeyCode) -> Result<()>;
+    /// Scroll the wheel `notches` times (positive zooms in) with Ctrl held.
+    fn zoom(&mut self, _notches: i32) -> Result<()> {
+        Err(anyhow!("the {} backend cannot zoom", self.name()))
+    }
+    /// Current pointer position in logical pixels, if the backend can tell.
+    fn position(&mut self) -> Option<(i32, i32)> {
+        None
+    }
+    /// Map following moves through `output`'s origin and scale instead of the
+    /// default scale; `None` restores the default. Backends that take logical
+    /// coordinates ignore it.
+    fn set_output(&mut self, _output: Option<OutputScale>) {}
+    /// Logical (x, y, width, height) bounding box of all outputs, for backends
+    /// that map absolute moves over the whole layout.
+    fn set_layout(&mut self, _layout: (i32, i32, i32, i32)) {}
+    /// Take up the tuning settings of `cfg` (tools, scroll mode, absolute
+    /// mapping), e.g. after a config reload. Backends without them ignore it.
+    fn configure(&mut self, _cfg: &MouseConfig) {}
+
+    /// Like `r#move`, but spread over `duration` with ease-in-out steps. Relative
+    /// moves are split into partial deltas; absolute moves interpolate from the
+    /// current position, or jump straight there when it is unknown.
+    fn glide(&mut self, x: i32, y: i32, absolute: bool, duration: Duration) -> Result<(i32, i32)> {
+        let steps = (duration.as_millis() / GLIDE_STEP.as_millis()).max(1) as i32;
+        let start = if absolute {
+            match self.position() {
+                Some(pos) => pos,
+                None => return self.r#move(x, y, true),
+            }
+        } else {
+            (0, 0)
+        };
+        let began = Instant::now();
+        let at = |step: i32| {
+            let t = step as f64 / steps as f64;
+            let eased = t * t * (3.0 - 2.0 * t);
+            (
+                coords::round_half_up(start.0 as f64 + (x - start.0) as f64 * eased),
+                coords::round_half_up(start.1 as f64 + (y - start.1) as f64 * eased),
+            )
+        };
+
+        let mut emitted = (0, 0);
+        for step in 1..=steps {
+            check_cancel(step - 1, steps)?;
+            let (px, py) = at(step);
+            if absolute {
+                emitted = self.r#move(px, py, true)?;
+            } else {
+                // Deltas between consecutive rounded points sum exactly to (x, y)
+                let (qx, qy) = at(step - 1);
+                let (dx, dy) = self.r#move(px - qx, py - qy, false)?;
+                emitted = (emitted.0 + dx, emitted.1 + dy);
+            }
+            let due = duration * step as u32 / steps as u32;
+            if let Some(wait) = due.checked_sub(began.elapsed()) {
+                sleep(wait);
+            }
+        }
+        Ok(emitted)
+    }
+}
+
+/// Pointer backends `mouse.backend` can pick in this build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PointerBackend {
+    #[cfg(feature = "ei")]
+    Ei,
+    #[cfg(feature = "virtual-pointer")]
+    VirtualPointer,
+    Uinput,
+}
+
+/// The backend `mouse.backend` names, or uinput (with a warning) when it names
+/// one this build lacks or none at all.
+fn pointer_backend(name: &str) -> PointerBackend {
+    match name {
+        #[cfg(feature = "ei")]
+        "ei" => PointerBackend::Ei,
+        #[cfg(not(feature = "ei"))]
+        "ei" => {
+            log::warn!("hintsd was built without the `ei` feature; falling back to uinput");
+            PointerBackend::Uinput
+        }
+        #[cfg(feature = "virtual-pointer")]
+        "virtual-pointer" => PointerBackend::VirtualPointer,
+        #[cfg(not(feature = "virtual-pointer"))]
+        "virtual-pointer" => {
+            log::warn!(
+                "hintsd was built without the `virtual-pointer` feature; falling back to uinput"
+            );
+            PointerBackend::Uinput
+        }
+        "uinput" | "" => PointerBackend::Uinput,
+        other => {
+            log::warn!("Unknown mouse backend {other:?}; falling back to uinput");
+            PointerBackend::Uinput
+        }
+    }
+}
+
+/// Whether the ydotool click path can work at all, checked before trying it so a
+/// fallback to uinput says why.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum YdotoolStatus {
+    Ready,
+    /// No `ydotool` binary on `PATH`
+    NotInstalled,
+    /// The binary exists but `ydotoold` isn't listening on the socket
+    SocketMissing,
+}
+
+/// Whether an executable called `binary` is on `PATH`.
+pub(crate) fn on_path(binary: &str) -> bool {
+    in_search_path(std::env::var_os("PATH").as_deref(), binary)
+}
+
+/// Whether `binary` is a file in one of the directories of the `PATH`-style
+/// list `path`.
+fn in_search_path(path: Option<&std::ffi::OsStr>, binary: &str) -> bool {
+    path.is_some_and(|path| std::env::split_paths(path).any(|dir| dir.join(binary).is_file()))
+}
+
+/// Socket `ydotool` talks to `ydotoold` through: `$YDOTOOL_SOCKET`, or the
+/// per-user default under `/run/user`.
+pub(crate) fn ydotool_socket() -> String {
+    choose_ydotool_socket(
+        std::env::var("YDOTOOL_SOCKET").ok(),
+        std::fs::read_to_string("/proc/self/loginuid").ok(),
+    )
+}
+
+/// `env_socket` (the value of `$YDOTOOL_SOCKET`) if set, otherwise the default
+/// socket of the user whose `loginuid` is given, or of uid 1000.
+fn choose_ydotool_socket(env_socket: Option<String>, loginuid: Option<String>) -> String {
+    env_socket.unwrap_or_else(|| {
+        let uid = loginuid
+            .and_then(|s| s.trim().parse::<u32>().ok())
+            .unwrap_or(1000);
+        format!("/run/user/{}/.ydotool_socket", uid)
+    })
+}
+
+pub(crate) fn probe_ydotool(socket: &str) -> YdotoolStatus {
+    probe_ydotool_in(std::env::var_os("PATH").as_deref(), socket)
+}
+
+/// `probe_ydotool` with `path` searched for the binary instead of `PATH`.
+fn probe_ydotool_in(path: Option<&std::ffi::OsStr>, socket: &str) -> YdotoolStatus {
+    if !in_search_path(path, "ydotool") {
+        YdotoolStatus::NotInstalled
+    } else if !std::path::Path::new(socket).exists() {
+        YdotoolStatus::SocketMissing
+    } else {
+        YdotoolStatus::Ready
+    }
+}
+
+/// Prefix shared by the names of every uinput device `VirtualMouse` creates.
+const DEVICE_NAME_PREFIX: &str = "hintsx-";
+
+/// Names among `names` that belong to a `VirtualMouse`.
+fn hintsx_device_names<I: IntoIterator<Item = String>>(names: I) -> Vec<String> {
+    names
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| name.starts_with(DEVICE_NAME_PREFIX))
+        .collect()
+}
+
+/// `hintsx-*` input devices currently registered with the kernel.
+fn lingering_devices() -> Vec<String> {
+    let names = std::fs::read_dir("/sys/class/input")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("name")).ok());
+    hintsx_device_names(names)
+}
+
+/// `hintsd` processes other than `own` holding `/dev/uinput` open, i.e. the
+/// owners of lingering devices, found under the procfs mounted at `proc_root`.
+fn stale_device_owners(proc_root: &std::path::Path, own: i32) -> Vec<i32> {
+    let Ok(procs) = std::fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    procs
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|&pid| pid != own)
+        .filter(|pid| {
+            std::fs::read_to_string(proc_root.join(format!("{pid}/comm")))
+                .is_ok_and(|comm| comm.trim() == "hintsd")
+        })
+        .filter(|pid| {
+            std::fs::read_dir(proc_root.join(format!("{pid}/fd")))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .any(|fd| {
+                    std::fs::read_link(fd.path())
+                        .is_ok_and(|target| target == std::path::Path::new("/dev/uinput"))
+                })
+        })
+        .collect()
+}
+
+/// Warn about uinput devices left by an earlier `hintsd`, which would otherwise
+/// show up as a second cursor, and which processes still hold them.
+fn check_lingering_devices() {
+    let devices = lingering_devices();
+    if devices.is_empty() {
+        return;
+    }
+    let owners = stale_device_owners(std::path::Path::new("/proc"), std::process::id() as i32);
+    log::warn!(
+        "found existing virtual devices {devices:?} (held by hintsd pids {owners:?}); \
+         a hung hintsd may cause duplicate cursors"
+    );
+}
+
+/// Finger travel in logical pixels per wheel notch of a swipe, libinput's
+/// default scroll distance per detent.
+const SWIPE_PIXELS_PER_NOTCH: f64 = 15.0;
+
+/// Zoom factor of one Ctrl+wheel notch in most apps.
+const ZOOM_PER_NOTCH: f64 = 1.1;
+
+/// Wheel events a gesture is emulated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureAction {
+    /// Wheel notches (horizontal, vertical) as `Pointer::scroll` takes them
+    Scroll(i32, i32),
+    /// Ctrl+wheel notches, positive zooming in
+    Zoom(i32),
+}
+
+/// How `kind` is emulated. A swipe moves the content with the fingers: down
+/// means wheel up, right means wheel left. A pinch takes as many zoom notches as
+/// reach `scale` most closely.
+pub fn gesture_action(kind: GestureKind) -> Result<GestureAction> {
+    match kind {
+        GestureKind::Swipe { dx, dy } => {
+            let notches = |pixels: i32| (pixels as f64 / SWIPE_PIXELS_PER_NOTCH).round() as i32;
+            Ok(GestureAction::Scroll(-notches(dx), notches(dy)))
+        }
+        GestureKind::Pinch { scale } if scale > 0.0 && scale.is_finite() => Ok(
+            GestureAction::Zoom((scale.ln() / ZOOM_PER_NOTCH.ln()).round() as i32),
+        ),
+        GestureKind::Pinch { scale } => Err(anyhow!("invalid pinch scale {scale}")),
+    }
+}
+
+/// Interval between intermediate emits of `Pointer::glide`.
+const GLIDE_STEP: Duration = Duration::from_millis(16);
+
+/// `REL_WHEEL_HI_RES` units per wheel detent.
+const HI_RES_PER_NOTCH: i32 = 120;
+
+/// HID usage a USB mouse reports in `MSC_SCAN` for `button` (usage page 9).
+fn button_scancode(button: MouseButton) -> i32 {
+    match button {
+        MouseButton::Left => 0x90001,
+        MouseButton::Right => 0x90002,
+        MouseButton::Middle => 0x90003,
+    }
+}
+
+/// Whether `states` are exactly one press and its release, the click
+/// `ydotool click` sends.
+fn whole_click(states: &[MouseButtonState]) -> bool {
+    matches!(states, [MouseButtonState::Down, MouseButtonState::Up])
+}
+
+/// One press (`value` 1) or release (0) of `code` the way hardware reports it:
+/// `MSC_SCAN` with the raw `scan` code first, then the key event. `emit` closes
+/// the frame with `SYN_REPORT`, and the kernel timestamps each frame as it is
+/// written, so frames sent apart get increasing times.
+pub fn key_frame(code: KeyCode, scan: i32, value: i32) -> [InputEvent; 2] {
+    [
+        InputEvent::new(EventType::MISC.0, MiscCode::MSC_SCAN.0, scan),
+        InputEvent::new(EventType::KEY.0, code.0, value),
+    ]
+}
+
+/// Interval between the hi-res deltas of a smooth scroll.
+const SMOOTH_SCROLL_STEP: Duration = Duration::from_millis(8);
+
+/// Most hi-res deltas one smooth scroll sends, keeping it under half a second.
+const MAX_SMOOTH_SCROLL_STEPS: u32 = 60;
+
+/// Hi-res deltas per notch for a smooth scroll of `notches`, fewer than `steps`
+/// when that many would exceed `MAX_SMOOTH_SCROLL_STEPS`. `None` when even one per
+/// notch would, and the scroll should go out as whole notches instead.
+pub fn smooth_steps_per_notch(notches: u32, steps: u32) -> Option<u32> {
+    let notches = notches.max(1);
+    (notches <= MAX_SMOOTH_SCROLL_STEPS).then(|| steps.clamp(1, MAX_SMOOTH_SCROLL_STEPS / notches))
+}
+
+/// Spread `notches` detents over `steps` hi-res wheel deltas per detent that
+/// start large and taper off. The deltas always sum to `notches * 120`.
+pub fn smooth_scroll_deltas(notches: i32, steps: u32) -> Vec<i32> {
+    let total = notches.saturating_mul(HI_RES_PER_NOTCH);
+    let steps = steps.max(1).saturating_mul(notches.unsigned_abs().max(1));
+    let mut emitted = 0;
+    (1..=steps)
+        .map(|step| {
+            let t = step as f64 / steps as f64;
+            let target = (total as f64 * (1.0 - (1.0 - t).powi(2))).round() as i32;
+            let delta = target - emitted;
+            emitted = target;
+            delta
+        })
+        .collect()
+}
+
+/// Build the pointer named by `cfg.backend`, falling back to uinput when the
+/// requested backend is unavailable or unknown.
+pub fn build_pointer(
+    cfg: &MouseConfig,
+    screen_width: i32,
+    screen_height: i32,
+    scale_factor: i32,
+) -> Result<Box<dyn Pointer>> {
+    match pointer_backend(&cfg.backend) {
+        #[cfg(feature = "ei")]
+        PointerBackend::Ei => match ei::EiPointer::connect() {
+            Ok(pointer) => {
+                log::info!("Using libei pointer backend");
+                return Ok(Box::new(pointer));
+            }
+            Err(err) => log::warn!("libei unavailable ({err}); falling back to uinput"),
+        },
+        #[cfg(feature = "virtual-pointer")]
+        PointerBackend::VirtualPointer => {
+            match virtual_pointer::VirtualPointer::connect(screen_width, screen_height) {
+                Ok(pointer) => {
+                    log::info!("Using wlr virtual-pointer backend");
+                    return Ok(Box::new(pointer));
+                }
+                Err(err) => {
+                    log::warn!("virtual-pointer unavailable ({err}); falling back to uinput")
+                }
+            }
+        }
+        PointerBackend::Uinput => {}
+    }
+    check_lingering_devices();
+    let mut mouse = VirtualMouse::new(screen_width, screen_height, scale_factor)?;
+    mouse.configure(cfg);
+    Ok(Box::new(mouse))
+}
+
+/// Carries the sub-pixel part of relative motion between moves so repeated
+/// fractional moves don't drift.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SubpixelAccumulator {
+    x: f64,
+    y: f64,
+}
+
+impl SubpixelAccumulator {
+    /// Add `(dx, dy)` and return the whole pixels to emit now.
+    pub fn take(&mut self, dx: f64, dy: f64) -> (i32, i32) {
+        self.x += dx;
+        self.y += dy;
+        let (whole_x, whole_y) = (self.x.trunc(), self.y.trunc());
+        self.x -= whole_x;
+        self.y -= whole_y;
+        (whole_x as i32, whole_y as i32)
+    }
+}
+
+/// Where `VirtualMouse` writes its evdev frames: a uinput device, or a recorder
+/// in tests. `emit` closes each frame with `SYN_REPORT`.
+trait EventSink: std::fmt::Debug {
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()>;
+}
+
+impl EventSink for VirtualDevice {
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        VirtualDevice::emit(self, events)
+    }
+}
+
 #[derive(Debug)]
 pub struct VirtualMouse {
-    abs_device: VirtualDevice,
-    rel_device: VirtualDevice,
-    write_pause: Duration,
+    abs_device: Box<dyn EventSink>,
+    rel_device: Box<dyn EventSink>,
+    key_device: Box<dyn EventSink>,
     scale_factor: i32,
+    /// Upper bounds of the absolute device's ABS_X/ABS_Y axes
+    abs_max: (i32, i32),
+    rel_remainder: SubpixelAccumulator,
+    /// Output the current request targets, when it isn't the default one
+    output: Option<OutputScale>,
+    /// What absolute moves and clicks try before the evdev devices
+    move_tool: MoveTool,
+    click_tool: ClickTool,
+    scroll_mode: ScrollMode,
+    smooth_scroll_steps: u32,
+    absolute_mapping: AbsoluteMapping,
+    /// Logical bounding box of all outputs, for `AbsoluteMapping::Layout`
+    layout: Option<(i32, i32, i32, i32)>,
 }
 
 impl VirtualMouse {
@@ -48,12 +524,18 @@ impl VirtualMouse {
         let mut abs_keys = rel_keys.clone();
         abs_keys.insert(KeyCode::BTN_TOUCH);
 
+        // Real mice and keyboards announce the scan codes they send before keys
+        let mut misc = AttributeSet::<MiscCode>::new();
+        misc.insert(MiscCode::MSC_SCAN);
+
         // Relative axes for scrolling and relative motion
         let mut rel_axes = AttributeSet::<RelativeAxisCode>::new();
         rel_axes.insert(RelativeAxisCode::REL_X);
         rel_axes.insert(RelativeAxisCode::REL_Y);
         rel_axes.insert(RelativeAxisCode::REL_WHEEL);
         rel_axes.insert(RelativeAxisCode::REL_HWHEEL);
+        rel_axes.insert(RelativeAxisCode::REL_WHEEL_HI_RES);
+        rel_axes.insert(RelativeAxisCode::REL_HWHEEL_HI_RES);
 
         log::info!("Building relative mouse device...");
         let rel_device = VirtualDevice::builder()
@@ -72,6 +554,7 @@ impl VirtualMouse {
                 log::error!("Failed to add relative axes: {}", e);
                 anyhow::anyhow!("Failed to add relative axes: {}", e)
             })?
+            .with_msc(&misc)?
             .build()
             .map_err(|e| {
                 log::error!("Failed to build relative device: {}. Make sure you're in the 'input' group or run as root.", e);
@@ -104,32 +587,177 @@ impl VirtualMouse {
                 e
             })?;
 
+        // Keyboard for activating hinted elements with Enter/Space
+        let mut kbd_keys = AttributeSet::<KeyCode>::new();
+        for code in KeyCode::KEY_ESC.0..=KeyCode::KEY_MICMUTE.0 {
+            kbd_keys.insert(KeyCode::new(code));
+        }
+
+        log::info!("Building virtual keyboard device...");
+        let key_device = VirtualDevice::builder()?
+            .name("hintsx-keyboard")
+            .with_keys(&kbd_keys)?
+            .with_msc(&misc)?
+            .build()
+            .map_err(|e| {
+                log::error!("Failed to build keyboard device: {}. Make sure you're in the 'input' group or run as root.", e);
+                e
+            })?;
+
         log::info!("Virtual mouse devices created successfully");
-        Ok(Self {
+        Ok(Self::with_devices(
+            Box::new(abs_device),
+            Box::new(rel_device),
+            Box::new(key_device),
+            (screen_width, screen_height),
+            scale_factor,
+        ))
+    }
+
+    fn with_devices(
+        abs_device: Box<dyn EventSink>,
+        rel_device: Box<dyn EventSink>,
+        key_device: Box<dyn EventSink>,
+        (screen_width, screen_height): (i32, i32),
+        scale_factor: i32,
+    ) -> Self {
+        Self {
             abs_device,
             rel_device,
-            write_pause: Duration::from_millis(30), // Match Python service timing
+            key_device,
             scale_factor,
-        })
+            abs_max: (screen_width * scale_factor, screen_height * scale_factor),
+            rel_remainder: SubpixelAccumulator::default(),
+            output: None,
+            move_tool: MoveTool::default(),
+            click_tool: ClickTool::default(),
+            scroll_mode: ScrollMode::default(),
+            smooth_scroll_steps: 1,
+            absolute_mapping: AbsoluteMapping::default(),
+            layout: None,
+        }
+    }
+
+    pub fn configure(&mut self, cfg: &MouseConfig) {
+        (self.move_tool, self.click_tool) = cfg.tools();
+        self.scroll_mode = cfg.scroll_mode;
+        self.smooth_scroll_steps = cfg.smooth_scroll_steps;
+        self.absolute_mapping = cfg.absolute_mapping;
+        log::info!(
+            "uinput backend moves via {:?}, clicks via {:?}, scrolls {:?}",
+            self.move_tool,
+            self.click_tool,
+            self.scroll_mode
+        );
     }
 
     pub fn scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        if self.scroll_mode == ScrollMode::Smooth {
+            return self.smooth_scroll(x, y);
+        }
+        self.notch_scroll(x, y)
+    }
+
+    /// Scroll by whole detents in a single event.
+    fn notch_scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        // The device advertises hi-res wheels, so libinput reads those; send both
         self.rel_device.emit(&[
             InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_HWHEEL.0, x),
             InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_WHEEL.0, y),
+            InputEvent::new(
+                EventType::RELATIVE.0,
+                RelativeAxisCode::REL_HWHEEL_HI_RES.0,
+                x.saturating_mul(HI_RES_PER_NOTCH),
+            ),
+            InputEvent::new(
+                EventType::RELATIVE.0,
+                RelativeAxisCode::REL_WHEEL_HI_RES.0,
+                y.saturating_mul(HI_RES_PER_NOTCH),
+            ),
             InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
         ])?;
         Ok(())
     }
 
-    pub fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<()> {
+    /// Scroll as a run of fractional hi-res deltas, with a legacy detent event
+    /// whenever a whole notch has accumulated for clients that only read those.
+    /// Scrolls too long to animate in time go out as whole notches.
+    fn smooth_scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        let notches = x.unsigned_abs().max(y.unsigned_abs()).max(1);
+        let Some(per_notch_steps) = smooth_steps_per_notch(notches, self.smooth_scroll_steps)
+        else {
+            return self.notch_scroll(x, y);
+        };
+        let steps = per_notch_steps * notches;
+        let per_notch = |notches: i32| steps / notches.unsigned_abs().max(1);
+        let xs = smooth_scroll_deltas(x, per_notch(x));
+        let ys = smooth_scroll_deltas(y, per_notch(y));
+        let axes = [
+            (
+                RelativeAxisCode::REL_HWHEEL_HI_RES,
+                RelativeAxisCode::REL_HWHEEL,
+                xs,
+            ),
+            (
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                RelativeAxisCode::REL_WHEEL,
+                ys,
+            ),
+        ];
+        let mut accumulated = [0; 2];
+        for step in 0..steps as usize {
+            check_cancel(step as i32, steps as i32)?;
+            let mut events = Vec::new();
+            for (axis, (hi_res, detent, deltas)) in axes.iter().enumerate() {
+                let Some(&delta) = deltas.get(step).filter(|d| **d != 0) else {
+                    continue;
+                };
+                events.push(InputEvent::new(EventType::RELATIVE.0, hi_res.0, delta));
+                accumulated[axis] += delta;
+                let notches = accumulated[axis] / HI_RES_PER_NOTCH;
+                if notches != 0 {
+                    accumulated[axis] -= notches * HI_RES_PER_NOTCH;
+                    events.push(InputEvent::new(EventType::RELATIVE.0, detent.0, notches));
+                }
+            }
+            if events.is_empty() {
+                continue;
+            }
+            events.push(InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0));
+            self.rel_device.emit(&events)?;
+            sleep(SMOOTH_SCROLL_STEP);
+        }
+        Ok(())
+    }
+
+    /// Move the pointer and return the device-pixel position (absolute) or
+    /// delta (relative) actually emitted after scaling and clamping.
+    pub fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)> {
         log::info!("========== MOVE START ==========");
         log::info!("Input coordinates: x={}, y={}, absolute={}", x, y, absolute);
-        log::info!("Scale factor: {}", self.scale_factor);
+        log::info!(
+            "Scale factor: {}, output: {:?}",
+            self.scale_factor,
+            self.output
+        );
 
-        let x_scaled = x * self.scale_factor;
-        let y_scaled = y * self.scale_factor;
+        let base = self.scale_factor as f64;
+        let (x_scaled, y_scaled) = match self.output {
+            Some(OutputScale { origin, scale }) => (
+                coords::scale_on_output(x, origin.0, base, scale as f64),
+                coords::scale_on_output(y, origin.1, base, scale as f64),
+            ),
+            None => (coords::scale(x, base), coords::scale(y, base)),
+        };
         log::info!("Scaled coordinates: x={}, y={}", x_scaled, y_scaled);
+        // The tablet device may span the whole layout rather than monitor 0
+        let (x_axis, y_axis) = match (self.absolute_mapping, self.layout) {
+            (AbsoluteMapping::Layout, Some((lx, ly, lw, lh))) => (
+                coords::layout_axis(x, lx, lw, self.abs_max.0),
+                coords::layout_axis(y, ly, lh, self.abs_max.1),
+            ),
+            _ => (x_scaled, y_scaled),
+        };
 
         if absolute {
             log::info!("Using ABSOLUTE positioning mode");
@@ -139,105 +767,168 @@ impl VirtualMouse {
             // We need to convert from screen pixels to ydotool coordinates
             // But we don't know screen resolution here, so let's try hyprctl first
 
-            // Use hyprctl for movement (it uses screen coordinates directly)
-            log::info!(
-                "Attempting hyprctl dispatch movecursor {} {}",
-                x_scaled,
-                y_scaled
-            );
-            let output = Command::new("hyprctl")
-                .args(&[
-                    "dispatch",
-                    "movecursor",
-                    &x_scaled.to_string(),
-                    &y_scaled.to_string(),
-                ])
-                .output();
+            let emitted = if self.move_tool == MoveTool::Uinput {
+                log::info!("move_tool is uinput; skipping hyprctl");
+                self.move_absolute_uinput(x_axis, y_axis)?
+            } else {
+                // Use hyprctl for movement (it uses screen coordinates directly)
+                log::info!(
+                    "Attempting hyprctl dispatch movecursor {} {}",
+                    x_scaled,
+                    y_scaled
+                );
+                let output = Command::new("hyprctl")
+                    .args([
+                        "dispatch",
+                        "movecursor",
+                        &x_scaled.to_string(),
+                        &y_scaled.to_string(),
+                    ])
+                    .output();
 
-            match output {
-                Ok(result) if result.status.success() => {
-                    log::info!("✓ hyprctl command executed successfully");
-                    log::info!("  stdout: {}", String::from_utf8_lossy(&result.stdout));
-                    log::info!("  stderr: {}", String::from_utf8_lossy(&result.stderr));
-                    log::info!("Sleeping 50ms for hyprctl to process...");
-                    sleep(Duration::from_millis(50));
-                    log::info!("Sleep complete");
-                }
-                Ok(result) => {
-                    log::warn!("✗ hyprctl returned error code: {:?}", result.status.code());
-                    log::warn!("  stdout: {}", String::from_utf8_lossy(&result.stdout));
-                    log::warn!("  stderr: {}", String::from_utf8_lossy(&result.stderr));
-                    log::info!("Falling back to uinput...");
-
-                    self.abs_device.emit(&[
-                        InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, x_scaled),
-                        InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, y_scaled),
-                        InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
-                    ])?;
-                    sleep(Duration::from_millis(50));
-                }
-                Err(e) => {
-                    log::warn!("✗ Failed to execute hyprctl: {}", e);
-                    log::info!("Falling back to uinput...");
-
-                    self.abs_device.emit(&[
-                        InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, x_scaled),
-                        InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, y_scaled),
-                        InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
-                    ])?;
-                    sleep(Duration::from_millis(50));
+                match output {
+                    Ok(result) if result.status.success() => {
+                        log::info!("✓ hyprctl command executed successfully");
+                        log::info!("  stdout: {}", String::from_utf8_lossy(&result.stdout));
+                        log::info!("  stderr: {}", String::from_utf8_lossy(&result.stderr));
+                        log::info!("Sleeping 50ms for hyprctl to process...");
+                        sleep(Duration::from_millis(50));
+                        log::info!("Sleep complete");
+                        (x_scaled, y_scaled)
+                    }
+                    Ok(result) => {
+                        log::warn!("✗ hyprctl returned error code: {:?}", result.status.code());
+                        log::warn!("  stdout: {}", String::from_utf8_lossy(&result.stdout));
+                        log::warn!("  stderr: {}", String::from_utf8_lossy(&result.stderr));
+                        log::info!("Falling back to uinput...");
+
+                        self.move_absolute_uinput(x_axis, y_axis)?
+                    }
+                    Err(e) => {
+                        log::warn!("✗ Failed to execute hyprctl: {}", e);
+                        log::info!("Falling back to uinput...");
+
+                        self.move_absolute_uinput(x_axis, y_axis)?
+                    }
                 }
-            }
+            };
+            log::info!("========== MOVE COMPLETE ==========");
+            Ok(emitted)
         } else {
             log::info!("Using RELATIVE positioning mode");
-            log::info!(
-                "Emitting REL_X={}, REL_Y={} via rel_device",
-                x_scaled,
-                y_scaled
+            let factor = self.output.map_or(self.scale_factor, |o| o.scale) as f64;
+            let emitted = self.move_relative(x as f64 * factor, y as f64 * factor)?;
+            log::info!("========== MOVE COMPLETE ==========");
+            Ok(emitted)
+        }
+    }
+
+    /// Absolute move through the uinput tablet device. Coordinates are clamped to
+    /// the device's axis range; the clamped position is returned.
+    fn move_absolute_uinput(&mut self, x: i32, y: i32) -> Result<(i32, i32)> {
+        let clamped = coords::clamp_to_axes((x, y), self.abs_max);
+        if clamped != (x, y) {
+            log::warn!(
+                "Target ({}, {}) outside device range, clamped to {:?}",
+                x,
+                y,
+                clamped
             );
+        }
+        let (x, y) = clamped;
+        log::info!("Emitting ABS_X={}, ABS_Y={} via abs_device", x, y);
+        self.abs_device.emit(&[
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, x),
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, y),
+            InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
+        ])?;
+        sleep(Duration::from_millis(50));
+        Ok((x, y))
+    }
+
+    /// Relative move by a possibly fractional amount of device pixels. The
+    /// fraction that can't be emitted is carried into the next call.
+    pub fn move_relative(&mut self, dx: f64, dy: f64) -> Result<(i32, i32)> {
+        let (rel_x, rel_y) = self.rel_remainder.take(dx, dy);
+        log::info!(
+            "Emitting REL_X={}, REL_Y={} via rel_device (carrying {:?})",
+            rel_x,
+            rel_y,
+            self.rel_remainder
+        );
+        if rel_x != 0 || rel_y != 0 {
             self.rel_device.emit(&[
-                InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, x_scaled),
-                InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_Y.0, y_scaled),
+                InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, rel_x),
+                InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_Y.0, rel_y),
                 InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
             ])?;
-            log::info!("Relative move events emitted, sleeping 30ms...");
-            sleep(Duration::from_millis(30));
-            log::info!("Sleep complete");
         }
-        log::info!("========== MOVE COMPLETE ==========");
+        log::info!("Relative move events emitted, sleeping 30ms...");
+        sleep(Duration::from_millis(30));
+        log::info!("Sleep complete");
+        Ok((rel_x, rel_y))
+    }
+
+    pub fn key(&mut self, key: KeyCode) -> Result<()> {
+        log::info!("Emitting key press/release: {:?}", key);
+        for value in [1, 0] {
+            // No scan code table for a virtual keyboard; report the keycode itself
+            self.key_device.emit(&key_frame(key, key.0 as i32, value))?;
+            sleep(Duration::from_millis(20));
+        }
         Ok(())
     }
 
+    /// Wheel `notches` with Ctrl held down on the virtual keyboard.
+    pub fn zoom(&mut self, notches: i32) -> Result<()> {
+        let ctrl = KeyCode::KEY_LEFTCTRL;
+        log::info!("Zooming {notches} notches with Ctrl+wheel");
+        self.key_device.emit(&key_frame(ctrl, ctrl.0 as i32, 1))?;
+        sleep(Duration::from_millis(20));
+        let scrolled = self.scroll(0, notches);
+        // Release Ctrl even when the scroll failed or was cancelled
+        self.key_device.emit(&key_frame(ctrl, ctrl.0 as i32, 0))?;
+        scrolled
+    }
+
     pub fn click(
         &mut self,
-        x: i32,
-        y: i32,
+        target: Option<(i32, i32)>,
         button: MouseButton,
         button_states: &[MouseButtonState],
         repeat: u32,
         absolute: bool,
-    ) -> Result<()> {
+    ) -> Result<Option<(i32, i32)>> {
         log::info!("╔════════════════════════════════════════════════════════════════════╗");
         log::info!("║                      CLICK OPERATION START                         ║");
         log::info!("╚════════════════════════════════════════════════════════════════════╝");
         log::info!("Click parameters:");
-        log::info!("  Target position: ({}, {})", x, y);
+        log::info!("  Target position: {:?}", target);
         log::info!("  Button: {:?}", button);
         log::info!("  Button states: {:?}", button_states);
         log::info!("  Repeat count: {}", repeat);
         log::info!("  Absolute positioning: {}", absolute);
 
-        // FIRST: Move mouse to target position
-        log::info!("");
-        log::info!("STEP 1: Moving mouse to target position...");
-        self.r#move(x, y, absolute)?;
-        log::info!("STEP 1: Move completed successfully");
+        // FIRST: Move mouse to target position (unless clicking where it is)
+        let emitted = match target {
+            Some((x, y)) => {
+                log::info!("");
+                log::info!("STEP 1: Moving mouse to target position...");
+                let emitted = self.r#move(x, y, absolute)?;
+                log::info!("STEP 1: Move completed successfully");
 
-        // Add extra delay to ensure move is fully processed before clicking
-        log::info!("");
-        log::info!("STEP 2: Waiting 100ms for move to settle...");
-        sleep(Duration::from_millis(100));
-        log::info!("STEP 2: Wait complete");
+                // Add extra delay to ensure move is fully processed before clicking
+                log::info!("");
+                log::info!("STEP 2: Waiting 100ms for move to settle...");
+                sleep(Duration::from_millis(100));
+                log::info!("STEP 2: Wait complete");
+                Some(emitted)
+            }
+            None => {
+                log::info!("STEP 1-2: No target; clicking at the current position");
+                None
+            }
+        };
 
         let btn_code = match button {
             MouseButton::Left => KeyCode::BTN_LEFT,
@@ -259,20 +950,37 @@ impl VirtualMouse {
         log::info!("  Command: ydotool click {}", ydotool_button);
         log::info!("  Repeat count: {}", repeat);
 
-        // Determine the correct ydotool socket path
-        // Try to get from environment, or construct from UID
-        let ydotool_socket = std::env::var("YDOTOOL_SOCKET").unwrap_or_else(|_| {
-            // Get UID from /proc/self/loginuid or default to 1000
-            let uid = std::fs::read_to_string("/proc/self/loginuid")
-                .ok()
-                .and_then(|s| s.trim().parse::<u32>().ok())
-                .unwrap_or(1000);
-            format!("/run/user/{}/.ydotool_socket", uid)
-        });
+        let ydotool_socket = ydotool_socket();
         log::info!("  Using YDOTOOL_SOCKET: {}", ydotool_socket);
 
         let mut ydotool_worked = false;
-        for iteration in 0..repeat {
+        let ydotool = if self.click_tool == ClickTool::Uinput {
+            log::info!("  click_tool is uinput; skipping ydotool");
+            None
+        } else if !whole_click(button_states) {
+            // ydotool is only asked for whole clicks; a bare press or release (a
+            // drag, or the release a Cancel sends) must not turn into a click
+            log::info!("  Not a whole click; skipping ydotool");
+            None
+        } else {
+            Some(probe_ydotool(&ydotool_socket))
+        };
+        match &ydotool {
+            Some(YdotoolStatus::Ready) | None => {}
+            Some(YdotoolStatus::NotInstalled) => {
+                log::warn!("  ✗ ydotool is not installed (not on PATH); using uinput");
+            }
+            Some(YdotoolStatus::SocketMissing) => log::warn!(
+                "  ✗ ydotool socket {} does not exist; start ydotoold (see start_ydotoold.sh). Using uinput",
+                ydotool_socket
+            ),
+        }
+        let attempts = if ydotool == Some(YdotoolStatus::Ready) {
+            repeat
+        } else {
+            0
+        };
+        for iteration in 0..attempts {
             log::info!("  Attempt {}/{}", iteration + 1, repeat);
             let ydotool_cmd = format!("ydotool click -D 25 {}", ydotool_button);
             log::info!(
@@ -282,7 +990,7 @@ impl VirtualMouse {
             );
 
             let output = Command::new("sh")
-                .args(&[
+                .args([
                     "-c",
                     &format!("YDOTOOL_SOCKET={} {}", ydotool_socket, ydotool_cmd),
                 ])
@@ -353,10 +1061,8 @@ impl VirtualMouse {
                     );
                     log::info!("      Emitting: KeyCode={:?}, value={}", btn_code, value);
 
-                    self.rel_device.emit(&[
-                        InputEvent::new(EventType::KEY.0, btn_code.0, value),
-                        InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0),
-                    ])?;
+                    self.rel_device
+                        .emit(&key_frame(btn_code, button_scancode(button), value))?;
                     log::info!("      Event emitted successfully");
 
                     log::info!("      Sleeping 50ms...");
@@ -377,6 +1083,318 @@ impl VirtualMouse {
         sleep(Duration::from_millis(200));
         log::info!("All done!");
 
-        Ok(())
+        Ok(emitted)
+    }
+}
+
+impl Pointer for VirtualMouse {
+    fn name(&self) -> &'static str {
+        "uinput"
+    }
+
+    fn scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        VirtualMouse::scroll(self, x, y)
+    }
+
+    fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)> {
+        VirtualMouse::r#move(self, x, y, absolute)
+    }
+
+    fn click(
+        &mut self,
+        target: Option<(i32, i32)>,
+        button: MouseButton,
+        button_states: &[MouseButtonState],
+        repeat: u32,
+        absolute: bool,
+    ) -> Result<Option<(i32, i32)>> {
+        VirtualMouse::click(self, target, button, button_states, repeat, absolute)
+    }
+    fn key(&mut self, key: KeyCode) -> Result<()> {
+        VirtualMouse::key(self, key)
+    }
+
+    fn zoom(&mut self, notches: i32) -> Result<()> {
+        VirtualMouse::zoom(self, notches)
+    }
+
+    fn position(&mut self) -> Option<(i32, i32)> {
+        if self.move_tool == MoveTool::Uinput {
+            return None;
+        }
+        // uinput can't read the pointer back; ask Hyprland where it is ("x, y")
+        let output = Command::new("hyprctl").arg("cursorpos").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let (x, y) = text.trim().split_once(',')?;
+        Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+    }
+
+    fn set_output(&mut self, output: Option<OutputScale>) {
+        self.output = output;
+    }
+
+    fn set_layout(&mut self, layout: (i32, i32, i32, i32)) {
+        self.layout = Some(layout);
+    }
+
+    fn configure(&mut self, cfg: &MouseConfig) {
+        VirtualMouse::configure(self, cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_backend_falls_back_to_uinput() {
+        assert_eq!(pointer_backend(""), PointerBackend::Uinput);
+        assert_eq!(pointer_backend("uinput"), PointerBackend::Uinput);
+        assert_eq!(pointer_backend("xdotool"), PointerBackend::Uinput);
+        #[cfg(feature = "ei")]
+        assert_eq!(pointer_backend("ei"), PointerBackend::Ei);
+        #[cfg(not(feature = "ei"))]
+        assert_eq!(pointer_backend("ei"), PointerBackend::Uinput);
+        #[cfg(feature = "virtual-pointer")]
+        assert_eq!(
+            pointer_backend("virtual-pointer"),
+            PointerBackend::VirtualPointer
+        );
+        #[cfg(not(feature = "virtual-pointer"))]
+        assert_eq!(pointer_backend("virtual-pointer"), PointerBackend::Uinput);
+    }
+
+    /// (type, code, value) of each event in a frame
+    type Frame = Vec<(EventType, u16, i32)>;
+
+    /// Event sink that records the frames it is sent.
+    #[derive(Clone, Debug, Default)]
+    struct Recorder(std::rc::Rc<std::cell::RefCell<Vec<Frame>>>);
+
+    impl EventSink for Recorder {
+        fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+            let frame = events
+                .iter()
+                .map(|e| (e.event_type(), e.code(), e.value()))
+                .collect();
+            self.0.borrow_mut().push(frame);
+            Ok(())
+        }
+    }
+
+    /// A `VirtualMouse` on recorders, with its relative device's recorder.
+    fn recording_mouse() -> (VirtualMouse, Recorder) {
+        let rel = Recorder::default();
+        let mouse = VirtualMouse::with_devices(
+            Box::new(Recorder::default()),
+            Box::new(rel.clone()),
+            Box::new(Recorder::default()),
+            (1920, 1080),
+            1,
+        );
+        (mouse, rel)
+    }
+
+    #[test]
+    fn bare_press_and_release_bypass_ydotool() {
+        let (mut mouse, rel) = recording_mouse();
+        // The default click tool, which only sends whole clicks
+        assert_eq!(mouse.click_tool, ClickTool::Ydotool);
+        let frame = |value| {
+            key_frame(
+                KeyCode::BTN_RIGHT,
+                button_scancode(MouseButton::Right),
+                value,
+            )
+            .map(|e| (e.event_type(), e.code(), e.value()))
+            .to_vec()
+        };
+
+        // What a Cancel sends to let go of a drag: a release and nothing else
+        let up = [MouseButtonState::Up];
+        assert_eq!(
+            mouse.click(None, MouseButton::Right, &up, 1, true).unwrap(),
+            None
+        );
+        assert_eq!(*rel.0.borrow(), [frame(0)]);
+
+        let down = [MouseButtonState::Down];
+        mouse
+            .click(None, MouseButton::Right, &down, 1, true)
+            .unwrap();
+        assert_eq!(*rel.0.borrow(), [frame(0), frame(1)]);
+
+        assert!(whole_click(&[MouseButtonState::Down, MouseButtonState::Up]));
+        assert!(!whole_click(&up));
+        assert!(!whole_click(&[
+            MouseButtonState::Up,
+            MouseButtonState::Down
+        ]));
+    }
+
+    #[test]
+    fn subpixel_accumulator_carries_fractions() {
+        let mut acc = SubpixelAccumulator::default();
+        for _ in 0..3 {
+            assert_eq!(acc.take(0.25, -0.25), (0, 0));
+        }
+        assert_eq!(acc.take(0.25, -0.25), (1, -1));
+        assert_eq!(acc.take(2.5, 0.0), (2, 0));
+        assert_eq!(acc.take(0.5, 0.0), (1, 0));
+    }
+
+    #[test]
+    fn key_frame_sends_the_scan_code_before_the_key() {
+        let triples = |frame: [InputEvent; 2]| frame.map(|e| (e.event_type(), e.code(), e.value()));
+        assert_eq!(
+            triples(key_frame(KeyCode::BTN_LEFT, 0x90001, 1)),
+            [
+                (EventType::MISC, MiscCode::MSC_SCAN.0, 0x90001),
+                (EventType::KEY, KeyCode::BTN_LEFT.0, 1),
+            ]
+        );
+        assert_eq!(
+            triples(key_frame(KeyCode::KEY_ENTER, 28, 0))[1],
+            (EventType::KEY, KeyCode::KEY_ENTER.0, 0)
+        );
+        // `VirtualDevice::emit` appends the one SYN_REPORT; one inside the frame
+        // would split the scan code from its key
+        assert!(
+            key_frame(KeyCode::KEY_ENTER, 28, 1)
+                .iter()
+                .all(|e| e.event_type() != EventType::SYNCHRONIZATION)
+        );
+    }
+
+    #[test]
+    fn ydotool_status_needs_the_binary_then_the_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::create_dir(&bin).unwrap();
+        let path = std::env::join_paths([dir.path().join("empty"), bin.clone()]).unwrap();
+        let socket = dir.path().join(".ydotool_socket");
+        let socket = socket.to_str().unwrap();
+
+        assert_eq!(probe_ydotool_in(None, socket), YdotoolStatus::NotInstalled);
+        assert_eq!(
+            probe_ydotool_in(Some(&path), socket),
+            YdotoolStatus::NotInstalled
+        );
+        std::fs::write(bin.join("ydotool"), "").unwrap();
+        assert!(in_search_path(Some(&path), "ydotool"));
+        assert_eq!(
+            probe_ydotool_in(Some(&path), socket),
+            YdotoolStatus::SocketMissing
+        );
+        std::os::unix::net::UnixListener::bind(socket).unwrap();
+        assert_eq!(probe_ydotool_in(Some(&path), socket), YdotoolStatus::Ready);
+    }
+
+    #[test]
+    fn ydotool_socket_prefers_the_environment() {
+        assert_eq!(
+            choose_ydotool_socket(Some("/tmp/ydo".into()), Some("1001\n".into())),
+            "/tmp/ydo"
+        );
+        assert_eq!(
+            choose_ydotool_socket(None, Some("1001\n".into())),
+            "/run/user/1001/.ydotool_socket"
+        );
+        // No readable loginuid
+        assert_eq!(
+            choose_ydotool_socket(None, None),
+            "/run/user/1000/.ydotool_socket"
+        );
+    }
+
+    #[test]
+    fn hintsx_device_names_picks_our_devices() {
+        let names = [
+            "AT Translated Set 2 keyboard\n",
+            "hintsx-mouse\n",
+            "ydotoold virtual device\n",
+            "hintsx-abs\n",
+        ]
+        .map(String::from);
+        assert_eq!(hintsx_device_names(names), ["hintsx-mouse", "hintsx-abs"]);
+    }
+
+    #[test]
+    fn stale_device_owners_are_other_hintsd_holding_uinput() {
+        let proc_root = tempfile::tempdir().unwrap();
+        let fake = |pid: i32, comm: &str, fds: &[&str]| {
+            let dir = proc_root.path().join(pid.to_string());
+            std::fs::create_dir_all(dir.join("fd")).unwrap();
+            std::fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+            for (fd, target) in fds.iter().enumerate() {
+                std::os::unix::fs::symlink(target, dir.join("fd").join(fd.to_string())).unwrap();
+            }
+        };
+        fake(100, "hintsd", &["/dev/null", "/dev/uinput"]);
+        fake(101, "hintsd", &["/dev/null"]);
+        fake(102, "ydotoold", &["/dev/uinput"]);
+        fake(103, "hintsd", &["/dev/uinput"]);
+        std::fs::create_dir_all(proc_root.path().join("self")).unwrap();
+
+        assert_eq!(stale_device_owners(proc_root.path(), 103), [100]);
+        assert!(stale_device_owners(&proc_root.path().join("missing"), 103).is_empty());
+    }
+
+    #[test]
+    fn smooth_scroll_deltas_taper_and_sum_to_whole_notches() {
+        for (notches, steps) in [(1, 12), (-3, 5), (2, 1), (0, 4)] {
+            let deltas = smooth_scroll_deltas(notches, steps);
+            assert_eq!(deltas.iter().sum::<i32>(), notches * HI_RES_PER_NOTCH);
+        }
+        let deltas = smooth_scroll_deltas(1, 12);
+        assert_eq!(deltas.len(), 12);
+        assert!(deltas.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn smooth_scroll_stays_within_its_step_budget() {
+        // One notch keeps the configured smoothness
+        assert_eq!(smooth_steps_per_notch(1, 12), Some(12));
+        assert_eq!(smooth_steps_per_notch(0, 0), Some(1));
+        // A default page scroll (10 steps of 5 notches) thins out to fit
+        let page = 10 * 5;
+        let per_notch = smooth_steps_per_notch(page, 12).unwrap();
+        assert_eq!(per_notch, 1);
+        assert!(per_notch * page <= MAX_SMOOTH_SCROLL_STEPS);
+        assert_eq!(smooth_scroll_deltas(page as i32, per_notch).len(), 50);
+        // Three pages are sent as plain notches
+        assert_eq!(smooth_steps_per_notch(3 * page, 12), None);
+    }
+
+    #[test]
+    fn held_after_follows_the_last_state() {
+        use MouseButtonState::{Down, Up};
+        let left = MouseButton::Left;
+        assert!(matches!(
+            held_after(None, left, &[Down]),
+            Some(MouseButton::Left)
+        ));
+        assert!(held_after(Some(left), left, &[Down, Up]).is_none());
+        assert!(matches!(
+            held_after(Some(MouseButton::Right), left, &[]),
+            Some(MouseButton::Right)
+        ));
+    }
+
+    #[test]
+    fn gesture_action_maps_swipes_and_pinches() {
+        let swipe = GestureKind::Swipe { dx: 30, dy: -45 };
+        assert_eq!(
+            gesture_action(swipe).unwrap(),
+            GestureAction::Scroll(-2, -3)
+        );
+        let pinch = |scale| gesture_action(GestureKind::Pinch { scale });
+        assert_eq!(pinch(1.21).unwrap(), GestureAction::Zoom(2));
+        assert_eq!(pinch(1.0 / 1.331).unwrap(), GestureAction::Zoom(-3));
+        assert!(pinch(0.0).is_err());
+        assert!(pinch(f64::NAN).is_err());
     }
 }
@@ -0,0 +1,394 @@
+//! Pointer emulation through libei (`ei` protocol), for compositors that expose an
+//! EIS socket via `LIBEI_SOCKET` (or a portal-provided fd). Unlike uinput this
+//! needs neither `input` group membership nor an external daemon.
+use super::{MouseButton, MouseButtonState, Pointer};
+use anyhow::{Result, anyhow};
+use evdev::KeyCode;
+use nix::poll::{PollFd, PollFlags, poll};
+use reis::PendingRequestResult;
+use reis::ei;
+use reis::event::{Device, DeviceCapability, EiEvent, EiEventConverter};
+use std::os::fd::AsFd;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct EiPointer {
+    sink: Box<dyn EiSink>,
+}
+
+impl EiPointer {
+    /// Connect to the EIS server named by the environment and wait until it hands
+    /// us a resumed pointer device.
+    pub fn connect() -> Result<Self> {
+        Ok(Self {
+            sink: Box::new(EiConnection::connect()?),
+        })
+    }
+}
+
+/// The ei requests `EiPointer` sends: to a live EIS connection, or to a recorder
+/// in tests.
+trait EiSink {
+    /// Pointer motion to `(x, y)`, or by it when not `absolute`
+    fn motion(&mut self, x: f32, y: f32, absolute: bool) -> Result<()>;
+    fn button(&mut self, code: u32, pressed: bool) -> Result<()>;
+    /// Wheel motion in 120ths of a detent
+    fn scroll_discrete(&mut self, x: i32, y: i32) -> Result<()>;
+    fn key(&mut self, code: u32, pressed: bool) -> Result<()>;
+    /// Close the events sent since the last frame on the pointer device, or with
+    /// `keyboard` on the keyboard device
+    fn frame(&mut self, keyboard: bool) -> Result<()>;
+}
+
+struct EiConnection {
+    context: ei::Context,
+    converter: EiEventConverter,
+    device: Option<Device>,
+    keyboard: Option<Device>,
+    last_serial: u32,
+    sequence: u32,
+}
+
+impl EiConnection {
+    fn connect() -> Result<Self> {
+        let context = ei::Context::connect_to_env()?
+            .ok_or_else(|| anyhow!("LIBEI_SOCKET is not set; no EIS server to talk to"))?;
+        let handshake = reis::handshake::ei_handshake_blocking(
+            &context,
+            "hintsd",
+            ei::handshake::ContextType::Sender,
+        )
+        .map_err(|e| anyhow!("ei handshake failed: {e:?}"))?;
+        let converter = EiEventConverter::new(&context, handshake);
+
+        let mut connection = Self {
+            context,
+            converter,
+            device: None,
+            keyboard: None,
+            last_serial: 0,
+            sequence: 0,
+        };
+
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        while connection.device.is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(anyhow!("EIS server did not offer a pointer device"));
+            }
+            connection.dispatch(remaining)?;
+        }
+        Ok(connection)
+    }
+
+    /// Read and convert pending protocol events, waiting at most `timeout` for the
+    /// socket to become readable.
+    fn dispatch(&mut self, timeout: Duration) -> Result<()> {
+        let millis = timeout.as_millis().min(u16::MAX as u128) as u16;
+        let mut fds = [PollFd::new(self.context.as_fd(), PollFlags::POLLIN)];
+        if poll(&mut fds, millis)? > 0 {
+            self.context.read()?;
+        }
+
+        while let Some(pending) = self.context.pending_event() {
+            match pending {
+                PendingRequestResult::Request(event) => self
+                    .converter
+                    .handle_event(event)
+                    .map_err(|e| anyhow!("ei protocol error: {e:?}"))?,
+                PendingRequestResult::ParseError(e) => {
+                    return Err(anyhow!("ei parse error: {e:?}"));
+                }
+                PendingRequestResult::InvalidObject(_) => {}
+            }
+        }
+
+        while let Some(event) = self.converter.next_event() {
+            match event {
+                EiEvent::SeatAdded(evt) => {
+                    evt.seat.bind_capabilities(&[
+                        DeviceCapability::Pointer,
+                        DeviceCapability::PointerAbsolute,
+                        DeviceCapability::Button,
+                        DeviceCapability::Scroll,
+                        DeviceCapability::Keyboard,
+                    ]);
+                }
+                EiEvent::DeviceResumed(evt) => {
+                    let is_pointer = evt.device.has_capability(DeviceCapability::Button);
+                    let is_keyboard = evt.device.has_capability(DeviceCapability::Keyboard);
+                    if is_pointer || is_keyboard {
+                        self.last_serial = evt.serial;
+                        self.sequence += 1;
+                        evt.device
+                            .device()
+                            .start_emulating(self.last_serial, self.sequence);
+                        log::info!("ei: emulating on device {:?}", evt.device.name());
+                        if is_keyboard {
+                            self.keyboard = Some(evt.device.clone());
+                        }
+                        if is_pointer {
+                            self.device = Some(evt.device);
+                        }
+                    }
+                }
+                EiEvent::DevicePaused(evt) => {
+                    if self.device.as_ref() == Some(&evt.device) {
+                        log::warn!("ei: pointer device paused by compositor");
+                        self.device = None;
+                    }
+                    if self.keyboard.as_ref() == Some(&evt.device) {
+                        log::warn!("ei: keyboard device paused by compositor");
+                        self.keyboard = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.context.flush()?;
+        Ok(())
+    }
+
+    fn device(&mut self) -> Result<Device> {
+        self.dispatch(Duration::ZERO)?;
+        self.device
+            .clone()
+            .ok_or_else(|| anyhow!("no resumed ei pointer device"))
+    }
+
+    fn keyboard(&mut self) -> Result<Device> {
+        self.dispatch(Duration::ZERO)?;
+        self.keyboard
+            .clone()
+            .ok_or_else(|| anyhow!("no resumed ei keyboard device"))
+    }
+}
+
+impl EiSink for EiConnection {
+    fn motion(&mut self, x: f32, y: f32, absolute: bool) -> Result<()> {
+        let device = self.device()?;
+        if absolute {
+            let pointer = device
+                .interface::<ei::PointerAbsolute>()
+                .ok_or_else(|| anyhow!("ei device has no absolute pointer capability"))?;
+            pointer.motion_absolute(x, y);
+        } else {
+            let pointer = device
+                .interface::<ei::Pointer>()
+                .ok_or_else(|| anyhow!("ei device has no relative pointer capability"))?;
+            pointer.motion_relative(x, y);
+        }
+        Ok(())
+    }
+
+    fn button(&mut self, code: u32, pressed: bool) -> Result<()> {
+        let device = self.device()?;
+        let buttons = device
+            .interface::<ei::Button>()
+            .ok_or_else(|| anyhow!("ei device has no button capability"))?;
+        let state = if pressed {
+            ei::button::ButtonState::Press
+        } else {
+            ei::button::ButtonState::Released
+        };
+        buttons.button(code, state);
+        Ok(())
+    }
+
+    fn scroll_discrete(&mut self, x: i32, y: i32) -> Result<()> {
+        let device = self.device()?;
+        let scroll = device
+            .interface::<ei::Scroll>()
+            .ok_or_else(|| anyhow!("ei device has no scroll capability"))?;
+        scroll.scroll_discrete(x, y);
+        Ok(())
+    }
+
+    fn key(&mut self, code: u32, pressed: bool) -> Result<()> {
+        let device = self.keyboard()?;
+        let keyboard = device
+            .interface::<ei::Keyboard>()
+            .ok_or_else(|| anyhow!("ei device has no keyboard capability"))?;
+        let state = if pressed {
+            ei::keyboard::KeyState::Press
+        } else {
+            ei::keyboard::KeyState::Released
+        };
+        keyboard.key(code, state);
+        Ok(())
+    }
+
+    fn frame(&mut self, keyboard: bool) -> Result<()> {
+        let device = if keyboard {
+            self.keyboard()?
+        } else {
+            self.device()?
+        };
+        let now_us = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+        device.device().frame(self.last_serial, now_us);
+        self.context.flush()?;
+        Ok(())
+    }
+}
+
+impl Pointer for EiPointer {
+    fn name(&self) -> &'static str {
+        "ei"
+    }
+
+    fn scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        // ei discrete scroll uses 120ths of a wheel detent, like hi-res wheel
+        // events, but counts down as positive where evdev counts up.
+        self.sink.scroll_discrete(x * 120, -y * 120)?;
+        self.sink.frame(false)
+    }
+
+    fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)> {
+        self.sink.motion(x as f32, y as f32, absolute)?;
+        self.sink.frame(false)?;
+        sleep(Duration::from_millis(30));
+        Ok((x, y))
+    }
+
+    fn click(
+        &mut self,
+        target: Option<(i32, i32)>,
+        button: MouseButton,
+        button_states: &[MouseButtonState],
+        repeat: u32,
+        absolute: bool,
+    ) -> Result<Option<(i32, i32)>> {
+        let emitted = match target {
+            Some((x, y)) => {
+                let emitted = self.r#move(x, y, absolute)?;
+                sleep(Duration::from_millis(100));
+                Some(emitted)
+            }
+            None => None,
+        };
+
+        let code = match button {
+            MouseButton::Left => KeyCode::BTN_LEFT,
+            MouseButton::Right => KeyCode::BTN_RIGHT,
+            MouseButton::Middle => KeyCode::BTN_MIDDLE,
+        };
+
+        for _ in 0..repeat {
+            for state in button_states {
+                let pressed = matches!(state, MouseButtonState::Down);
+                self.sink.button(code.0 as u32, pressed)?;
+                self.sink.frame(false)?;
+                sleep(Duration::from_millis(50));
+            }
+        }
+        Ok(emitted)
+    }
+    fn key(&mut self, key: KeyCode) -> Result<()> {
+        for pressed in [true, false] {
+            self.sink.key(key.0 as u32, pressed)?;
+            self.sink.frame(true)?;
+            sleep(Duration::from_millis(20));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Sink that records the requests it is sent.
+    #[derive(Clone, Default)]
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl EiSink for Recorder {
+        fn motion(&mut self, x: f32, y: f32, absolute: bool) -> Result<()> {
+            let kind = if absolute { "absolute" } else { "relative" };
+            self.0.borrow_mut().push(format!("motion {kind} {x} {y}"));
+            Ok(())
+        }
+
+        fn button(&mut self, code: u32, pressed: bool) -> Result<()> {
+            let state = if pressed { "press" } else { "release" };
+            self.0.borrow_mut().push(format!("button {code} {state}"));
+            Ok(())
+        }
+
+        fn scroll_discrete(&mut self, x: i32, y: i32) -> Result<()> {
+            self.0.borrow_mut().push(format!("scroll {x} {y}"));
+            Ok(())
+        }
+
+        fn key(&mut self, code: u32, pressed: bool) -> Result<()> {
+            let state = if pressed { "press" } else { "release" };
+            self.0.borrow_mut().push(format!("key {code} {state}"));
+            Ok(())
+        }
+
+        fn frame(&mut self, keyboard: bool) -> Result<()> {
+            let device = if keyboard { "keyboard" } else { "pointer" };
+            self.0.borrow_mut().push(format!("frame {device}"));
+            Ok(())
+        }
+    }
+
+    fn recording_pointer() -> (EiPointer, Recorder) {
+        let recorder = Recorder::default();
+        let pointer = EiPointer {
+            sink: Box::new(recorder.clone()),
+        };
+        (pointer, recorder)
+    }
+
+    #[test]
+    fn click_moves_then_presses_and_releases_in_separate_frames() {
+        let (mut pointer, recorder) = recording_pointer();
+        let states = [MouseButtonState::Down, MouseButtonState::Up];
+        let emitted = pointer
+            .click(Some((10, 20)), MouseButton::Left, &states, 1, true)
+            .unwrap();
+        assert_eq!(emitted, Some((10, 20)));
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "motion absolute 10 20",
+                "frame pointer",
+                "button 272 press",
+                "frame pointer",
+                "button 272 release",
+                "frame pointer",
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_move_is_one_motion_frame() {
+        let (mut pointer, recorder) = recording_pointer();
+        assert_eq!(pointer.r#move(-5, 3, false).unwrap(), (-5, 3));
+        assert_eq!(
+            *recorder.0.borrow(),
+            ["motion relative -5 3", "frame pointer"]
+        );
+    }
+
+    #[test]
+    fn scroll_up_is_negative_in_ei() {
+        let (mut pointer, recorder) = recording_pointer();
+        pointer.scroll(0, 2).unwrap();
+        pointer.scroll(1, -3).unwrap();
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "scroll 0 -240",
+                "frame pointer",
+                "scroll 120 360",
+                "frame pointer"
+            ]
+        );
+    }
+}
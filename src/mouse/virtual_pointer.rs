@@ -0,0 +1,331 @@
+//! Pointer emulation through `wlr-virtual-pointer-unstable-v1`, which wlroots
+//! compositors (Sway, Hyprland, river, ...) offer to ordinary clients. Needs no
+//! uinput access, but can't type keys.
+use super::{MouseButton, MouseButtonState, Pointer};
+use anyhow::{Result, anyhow};
+use evdev::KeyCode;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::wl_pointer::{Axis, AxisSource, ButtonState};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle, delegate_noop};
+use wayland_protocols_wlr::virtual_pointer::v1::client::{
+    zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1,
+    zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1,
+};
+
+/// Scroll distance per wheel detent, matching libinput's default.
+const AXIS_STEP: f64 = 15.0;
+
+struct State;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ZwlrVirtualPointerManagerV1);
+delegate_noop!(State: ZwlrVirtualPointerV1);
+
+pub struct VirtualPointer {
+    sink: Box<dyn VirtualPointerSink>,
+    /// Logical (x, y, width, height) box absolute coordinates are mapped onto;
+    /// without an output bound to the pointer, wlroots stretches absolute motion
+    /// over the whole output layout
+    layout: (i32, i32, i32, i32),
+}
+
+impl VirtualPointer {
+    /// Connect to `$WAYLAND_DISPLAY` and create a virtual pointer on the default
+    /// seat. Absolute moves cover a `screen_width` x `screen_height` layout until
+    /// `set_layout` says otherwise.
+    pub fn connect(screen_width: i32, screen_height: i32) -> Result<Self> {
+        Ok(Self {
+            sink: Box::new(WlrConnection::connect()?),
+            layout: (0, 0, screen_width, screen_height),
+        })
+    }
+}
+
+/// The requests `VirtualPointer` sends: to the compositor, or to a recorder in
+/// tests.
+trait VirtualPointerSink {
+    /// Pointer motion by `(dx, dy)` logical pixels
+    fn motion(&mut self, dx: f64, dy: f64) -> Result<()>;
+    /// Pointer motion to `(x, y)` of an area `extent` wide and high
+    fn motion_absolute(&mut self, x: u32, y: u32, extent: (u32, u32)) -> Result<()>;
+    fn button(&mut self, code: u32, pressed: bool) -> Result<()>;
+    /// Wheel detents on each axis, in Wayland's sense: positive `y` is down
+    fn wheel(&mut self, x: i32, y: i32) -> Result<()>;
+    /// Close the events sent since the last frame
+    fn frame(&mut self) -> Result<()>;
+}
+
+struct WlrConnection {
+    conn: Connection,
+    queue: EventQueue<State>,
+    pointer: ZwlrVirtualPointerV1,
+    started: Instant,
+}
+
+impl WlrConnection {
+    fn connect() -> Result<Self> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+        let manager: ZwlrVirtualPointerManagerV1 = globals
+            .bind(&qh, 1..=2, ())
+            .map_err(|_| anyhow!("compositor lacks zwlr_virtual_pointer_manager_v1"))?;
+        let seat: Option<WlSeat> = globals.bind(&qh, 1..=1, ()).ok();
+        let pointer = manager.create_virtual_pointer(seat.as_ref(), &qh, ());
+        queue.roundtrip(&mut State)?;
+
+        Ok(Self {
+            conn,
+            queue,
+            pointer,
+            started: Instant::now(),
+        })
+    }
+
+    /// Protocol timestamp in milliseconds; only differences matter to clients.
+    fn time(&self) -> u32 {
+        self.started.elapsed().as_millis() as u32
+    }
+}
+
+impl VirtualPointerSink for WlrConnection {
+    fn motion(&mut self, dx: f64, dy: f64) -> Result<()> {
+        self.pointer.motion(self.time(), dx, dy);
+        Ok(())
+    }
+
+    fn motion_absolute(&mut self, x: u32, y: u32, (width, height): (u32, u32)) -> Result<()> {
+        self.pointer
+            .motion_absolute(self.time(), x, y, width, height);
+        Ok(())
+    }
+
+    fn button(&mut self, code: u32, pressed: bool) -> Result<()> {
+        let state = if pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        };
+        self.pointer.button(self.time(), code, state);
+        Ok(())
+    }
+
+    fn wheel(&mut self, x: i32, y: i32) -> Result<()> {
+        let time = self.time();
+        self.pointer.axis_source(AxisSource::Wheel);
+        for (axis, steps) in [(Axis::HorizontalScroll, x), (Axis::VerticalScroll, y)] {
+            if steps != 0 {
+                self.pointer
+                    .axis_discrete(time, axis, steps as f64 * AXIS_STEP, steps);
+            }
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self) -> Result<()> {
+        self.pointer.frame();
+        self.conn.flush()?;
+        self.queue.dispatch_pending(&mut State)?;
+        Ok(())
+    }
+}
+
+/// Logical point `(x, y)` clamped onto `layout`, and its offset into the layout
+/// as absolute motion over the layout's extent.
+fn absolute_motion(
+    (x, y): (i32, i32),
+    (lx, ly, width, height): (i32, i32, i32, i32),
+) -> ((i32, i32), (u32, u32), (u32, u32)) {
+    let (width, height) = (width.max(1), height.max(1));
+    let x = x.clamp(lx, lx + width);
+    let y = y.clamp(ly, ly + height);
+    (
+        (x, y),
+        ((x - lx) as u32, (y - ly) as u32),
+        (width as u32, height as u32),
+    )
+}
+
+impl Pointer for VirtualPointer {
+    fn name(&self) -> &'static str {
+        "virtual-pointer"
+    }
+
+    fn scroll(&mut self, x: i32, y: i32) -> Result<()> {
+        // Wayland axes count down as positive, evdev's wheel counts up
+        self.sink.wheel(x, -y)?;
+        self.sink.frame()
+    }
+
+    fn r#move(&mut self, x: i32, y: i32, absolute: bool) -> Result<(i32, i32)> {
+        let emitted = if absolute {
+            let (emitted, (ax, ay), extent) = absolute_motion((x, y), self.layout);
+            self.sink.motion_absolute(ax, ay, extent)?;
+            emitted
+        } else {
+            self.sink.motion(x as f64, y as f64)?;
+            (x, y)
+        };
+        self.sink.frame()?;
+        sleep(Duration::from_millis(30));
+        Ok(emitted)
+    }
+
+    fn click(
+        &mut self,
+        target: Option<(i32, i32)>,
+        button: MouseButton,
+        button_states: &[MouseButtonState],
+        repeat: u32,
+        absolute: bool,
+    ) -> Result<Option<(i32, i32)>> {
+        let emitted = match target {
+            Some((x, y)) => {
+                let emitted = self.r#move(x, y, absolute)?;
+                sleep(Duration::from_millis(100));
+                Some(emitted)
+            }
+            None => None,
+        };
+
+        let code = match button {
+            MouseButton::Left => KeyCode::BTN_LEFT,
+            MouseButton::Right => KeyCode::BTN_RIGHT,
+            MouseButton::Middle => KeyCode::BTN_MIDDLE,
+        };
+        for _ in 0..repeat {
+            for state in button_states {
+                let pressed = matches!(state, MouseButtonState::Down);
+                self.sink.button(code.0 as u32, pressed)?;
+                self.sink.frame()?;
+                sleep(Duration::from_millis(50));
+            }
+        }
+        Ok(emitted)
+    }
+
+    fn key(&mut self, _key: KeyCode) -> Result<()> {
+        Err(anyhow!(
+            "the virtual-pointer backend cannot send key events"
+        ))
+    }
+
+    fn set_layout(&mut self, layout: (i32, i32, i32, i32)) {
+        self.layout = layout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Sink that records the requests it is sent.
+    #[derive(Clone, Default)]
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl VirtualPointerSink for Recorder {
+        fn motion(&mut self, dx: f64, dy: f64) -> Result<()> {
+            self.0.borrow_mut().push(format!("motion {dx} {dy}"));
+            Ok(())
+        }
+
+        fn motion_absolute(&mut self, x: u32, y: u32, (w, h): (u32, u32)) -> Result<()> {
+            self.0
+                .borrow_mut()
+                .push(format!("motion_absolute {x} {y} of {w}x{h}"));
+            Ok(())
+        }
+
+        fn button(&mut self, code: u32, pressed: bool) -> Result<()> {
+            let state = if pressed { "press" } else { "release" };
+            self.0.borrow_mut().push(format!("button {code} {state}"));
+            Ok(())
+        }
+
+        fn wheel(&mut self, x: i32, y: i32) -> Result<()> {
+            self.0.borrow_mut().push(format!("wheel {x} {y}"));
+            Ok(())
+        }
+
+        fn frame(&mut self) -> Result<()> {
+            self.0.borrow_mut().push("frame".into());
+            Ok(())
+        }
+    }
+
+    fn recording_pointer(layout: (i32, i32, i32, i32)) -> (VirtualPointer, Recorder) {
+        let recorder = Recorder::default();
+        let pointer = VirtualPointer {
+            sink: Box::new(recorder.clone()),
+            layout,
+        };
+        (pointer, recorder)
+    }
+
+    #[test]
+    fn click_moves_then_presses_and_releases_in_separate_frames() {
+        let (mut pointer, recorder) = recording_pointer((0, 0, 1920, 1080));
+        let states = [MouseButtonState::Down, MouseButtonState::Up];
+        let emitted = pointer
+            .click(Some((10, 20)), MouseButton::Right, &states, 1, true)
+            .unwrap();
+        assert_eq!(emitted, Some((10, 20)));
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "motion_absolute 10 20 of 1920x1080",
+                "frame",
+                "button 273 press",
+                "frame",
+                "button 273 release",
+                "frame",
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_moves_span_the_whole_layout() {
+        // A 1280x1024 output left of a 1920x1080 primary
+        let (mut pointer, recorder) = recording_pointer((0, 0, 1920, 1080));
+        pointer.set_layout((-1280, 0, 3200, 1080));
+        assert_eq!(pointer.r#move(-100, 500, true).unwrap(), (-100, 500));
+        assert_eq!(pointer.r#move(2500, -5, true).unwrap(), (1920, 0));
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "motion_absolute 1180 500 of 3200x1080",
+                "frame",
+                "motion_absolute 3200 0 of 3200x1080",
+                "frame",
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_up_is_a_negative_vertical_axis() {
+        let (mut pointer, recorder) = recording_pointer((0, 0, 1920, 1080));
+        pointer.scroll(0, 2).unwrap();
+        pointer.scroll(1, -3).unwrap();
+        assert_eq!(
+            *recorder.0.borrow(),
+            ["wheel 0 -2", "frame", "wheel 1 3", "frame"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Optional D-Bus service (`overlay.dbus_service`) publishing the overlay's hints
+//! so accessibility and automation tools can list them and activate one by label.
+use crate::hints::HintMap;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, mpsc};
+
+pub const BUS_NAME: &str = "xyz.hintsx.Hints";
+pub const OBJECT_PATH: &str = "/xyz/hintsx/Hints";
+
+/// A hint as published: label and screen rect (x, y, width, height).
+pub type PublishedHint = (String, i32, i32, i32, i32);
+
+/// `hints` as published over D-Bus, sorted by label.
+pub fn published_hints(hints: &HintMap) -> Vec<PublishedHint> {
+    let mut published: Vec<PublishedHint> = hints
+        .iter()
+        .map(|(label, child)| {
+            (
+                label.clone(),
+                child.absolute_x,
+                child.absolute_y,
+                child.width,
+                child.height,
+            )
+        })
+        .collect();
+    published.sort();
+    published
+}
+
+/// Where activating `label` clicks, with the same per-role offsets as typing it.
+pub fn activation_point(
+    hints: &HintMap,
+    label: &str,
+    click_offsets: &HashMap<String, (f64, f64)>,
+) -> Option<(i32, i32)> {
+    hints
+        .get(label)
+        .map(|child| child.click_point(click_offsets))
+}
+
+struct HintsService {
+    hints: Arc<Mutex<HintMap>>,
+    click_offsets: HashMap<String, (f64, f64)>,
+    activations: mpsc::Sender<String>,
+}
+
+#[zbus::interface(name = "xyz.hintsx.Hints")]
+impl HintsService {
+    /// Current hints as (label, x, y, width, height) in screen coordinates.
+    fn hints(&self) -> Vec<PublishedHint> {
+        published_hints(&self.hints.lock().unwrap())
+    }
+
+    /// Click the hint labeled `label` and close the overlay; returns the point clicked.
+    fn activate(&self, label: &str) -> zbus::fdo::Result<(i32, i32)> {
+        let point = activation_point(&self.hints.lock().unwrap(), label, &self.click_offsets)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no hint labeled {label:?}")))?;
+        self.activations
+            .send(label.to_string())
+            .map_err(|_| zbus::fdo::Error::Failed("the overlay has closed".into()))?;
+        Ok(point)
+    }
+}
+
+/// Serve `hints` on the session bus from a background thread. Labels activated
+/// remotely arrive on the returned receiver for the overlay to click.
+pub fn serve(
+    hints: Arc<Mutex<HintMap>>,
+    click_offsets: HashMap<String, (f64, f64)>,
+) -> Result<mpsc::Receiver<String>> {
+    let (activations, activated) = mpsc::channel();
+    let service = HintsService {
+        hints,
+        click_offsets,
+        activations,
+    };
+    let connection = futures::executor::block_on(async {
+        zbus::connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, service)?
+            .build()
+            .await
+    })?;
+    std::thread::spawn(move || {
+        // Keep the connection (and with it the service) up while the overlay runs
+        let _connection = connection;
+        loop {
+            std::thread::park();
+        }
+    });
+    Ok(activated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hints::Child;
+
+    fn hints() -> HintMap {
+        let child = |(x, y, w, h), role: &str| Child {
+            absolute_x: x,
+            absolute_y: y,
+            width: w,
+            height: h,
+            role: Some(role.into()),
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        [
+            ("sd".to_string(), child((100, 200, 40, 20), "ScrollBar")),
+            ("a".to_string(), child((0, 0, 11, 11), "PushButton")),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn published_hints_are_sorted_screen_rects() {
+        assert_eq!(
+            published_hints(&hints()),
+            [
+                ("a".to_string(), 0, 0, 11, 11),
+                ("sd".to_string(), 100, 200, 40, 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn activation_point_uses_the_role_offsets() {
+        let offsets = HashMap::from([("ScrollBar".to_string(), (0.5, 0.1))]);
+        assert_eq!(activation_point(&hints(), "a", &offsets), Some((5, 5)));
+        assert_eq!(activation_point(&hints(), "sd", &offsets), Some((120, 202)));
+        assert_eq!(
+            activation_point(&hints(), "sd", &HashMap::new()),
+            Some((120, 210))
+        );
+        assert_eq!(activation_point(&hints(), "zz", &offsets), None);
+    }
+}
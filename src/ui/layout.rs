@@ -0,0 +1,118 @@
+//! Keyboard-layout awareness for hint labels: read `alphabet` as physical key
+//! positions on a US layout and translate them to what the active XKB layout
+//! types on those keys, so "asdf" stays the home row on Dvorak or Colemak.
+use evdev::KeyCode;
+use gtk4::gdk;
+use gtk4::prelude::*;
+
+/// X11/XKB keycodes are evdev codes offset by 8.
+const XKB_KEYCODE_OFFSET: u32 = 8;
+
+/// evdev key at the position of `ch` on a US QWERTY layout.
+fn us_position(ch: char) -> Option<KeyCode> {
+    let name = match ch.to_ascii_lowercase() {
+        c @ ('a'..='z' | '0'..='9') => format!("KEY_{}", c.to_ascii_uppercase()),
+        ';' => "KEY_SEMICOLON".into(),
+        '\'' => "KEY_APOSTROPHE".into(),
+        ',' => "KEY_COMMA".into(),
+        '.' => "KEY_DOT".into(),
+        '/' => "KEY_SLASH".into(),
+        '[' => "KEY_LEFTBRACE".into(),
+        ']' => "KEY_RIGHTBRACE".into(),
+        '-' => "KEY_MINUS".into(),
+        '=' => "KEY_EQUAL".into(),
+        _ => return None,
+    };
+    name.parse().ok()
+}
+
+/// Character the active layout produces for `key` without modifiers.
+fn layout_char(display: &gdk::Display, key: KeyCode) -> Option<char> {
+    display
+        .map_keycode(key.0 as u32 + XKB_KEYCODE_OFFSET)?
+        .into_iter()
+        .find(|(entry, _)| entry.group() == 0 && entry.level() == 0)
+        .and_then(|(_, keyval)| keyval.to_unicode())
+        .filter(|c| !c.is_control())
+}
+
+/// Translate `alphabet` (US key positions) through the active layout. Keys that
+/// can't be mapped, or that would repeat an earlier character, keep the original
+/// character; any still-duplicate characters are dropped.
+pub fn physical_alphabet(alphabet: &str) -> String {
+    let Some(display) = gdk::Display::default() else {
+        log::warn!("no display to read the keyboard layout from; using alphabet as-is");
+        return alphabet.to_string();
+    };
+
+    let mapped = map_alphabet(alphabet, |key| layout_char(&display, key));
+    log::info!("physical alphabet {alphabet:?} maps to {mapped:?} on the active layout");
+    mapped
+}
+
+/// `physical_alphabet` with `layout` giving the character typed on each key.
+fn map_alphabet(alphabet: &str, layout: impl Fn(KeyCode) -> Option<char>) -> String {
+    let mut mapped = String::new();
+    for ch in alphabet.chars() {
+        let candidate = us_position(ch)
+            .and_then(&layout)
+            .filter(|c| !mapped.contains(*c))
+            .unwrap_or(ch);
+        if !mapped.contains(candidate) {
+            mapped.push(candidate);
+        }
+    }
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a French AZERTY layout types on some of its keys.
+    fn azerty(key: KeyCode) -> Option<char> {
+        let ch = match key {
+            KeyCode::KEY_Q => 'a',
+            KeyCode::KEY_A => 'q',
+            KeyCode::KEY_W => 'z',
+            KeyCode::KEY_Z => 'w',
+            KeyCode::KEY_SEMICOLON => 'm',
+            KeyCode::KEY_M => ',',
+            KeyCode::KEY_1 => '&',
+            KeyCode::KEY_S => 's',
+            KeyCode::KEY_D => 'd',
+            KeyCode::KEY_F => 'f',
+            _ => return None,
+        };
+        Some(ch)
+    }
+
+    #[test]
+    fn us_positions() {
+        assert_eq!(us_position('a'), Some(KeyCode::KEY_A));
+        assert_eq!(us_position('A'), Some(KeyCode::KEY_A));
+        assert_eq!(us_position(';'), Some(KeyCode::KEY_SEMICOLON));
+        assert_eq!(us_position('7'), Some(KeyCode::KEY_7));
+        assert_eq!(us_position('é'), None);
+    }
+
+    #[test]
+    fn alphabet_keeps_its_key_positions_on_azerty() {
+        assert_eq!(map_alphabet("asdf;", azerty), "qsdfm");
+        assert_eq!(map_alphabet("qwzm", azerty), "azw,");
+        // Unmapped keys keep the typed character
+        assert_eq!(map_alphabet("ag1", azerty), "qg&");
+        assert_eq!(map_alphabet("é", azerty), "é");
+    }
+
+    #[test]
+    fn duplicates_fall_back_or_are_dropped() {
+        // Every key types 'x', so only the first gets it and the rest keep theirs
+        let same = |_| Some('x');
+        assert_eq!(map_alphabet("abc", same), "xbc");
+        assert_eq!(map_alphabet("aa", azerty), "qa");
+        // 'b' types 'a', so 'a' has nothing left to fall back to and is dropped
+        let b_is_a = |key| (key == KeyCode::KEY_B || key == KeyCode::KEY_A).then_some('a');
+        assert_eq!(map_alphabet("ba", b_is_a), "a");
+    }
+}
@@ -1 +1,5 @@
+pub mod dbus;
+pub mod layout;
+pub mod notice;
 pub mod overlay;
+pub mod x11;
@@ -0,0 +1,60 @@
+use crate::config::Config;
+use crate::window_system::WindowSystem;
+use gtk4::gio::prelude::ApplicationExtManual;
+use gtk4::prelude::*;
+use gtk4::{Application, ApplicationWindow, Label};
+use std::time::Duration;
+
+#[cfg(feature = "layer-shell")]
+use crate::window_system::WindowSystemType;
+#[cfg(feature = "layer-shell")]
+use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+
+/// Flash a small label near the top of the screen for `cfg.overlay.empty_notice_ms`,
+/// so the user can tell `hintsx` ran even though there is nothing to hint.
+pub fn launch_notice(cfg: &Config, ws: &WindowSystem, message: &str) {
+    let app = Application::builder()
+        .application_id("xyz.hintsx.notice")
+        .build();
+
+    let cfg = cfg.clone();
+    let ws = ws.clone();
+    let message = message.to_string();
+    app.connect_activate(move |app| {
+        let label = Label::new(Some(&message));
+        label.set_margin_top(cfg.hints.hint_padding_y * 2);
+        label.set_margin_bottom(cfg.hints.hint_padding_y * 2);
+        label.set_margin_start(cfg.hints.hint_padding_x * 4);
+        label.set_margin_end(cfg.hints.hint_padding_x * 4);
+
+        let window = ApplicationWindow::builder()
+            .application(app)
+            .title("HintsX")
+            .decorated(false)
+            .resizable(false)
+            .child(&label)
+            .build();
+
+        #[cfg(feature = "layer-shell")]
+        if ws.window_system_type == WindowSystemType::Wayland && cfg.overlay.use_layer_shell {
+            window.init_layer_shell();
+            window.set_namespace(Some(&cfg.overlay.layer_shell_namespace));
+            window.set_layer(Layer::Overlay);
+            window.set_keyboard_mode(KeyboardMode::None);
+            window.set_anchor(Edge::Top, true);
+            window.set_margin(Edge::Top, cfg.hints.hint_height * 2);
+        }
+        #[cfg(not(feature = "layer-shell"))]
+        let _ = &ws;
+
+        window.present();
+
+        let app = app.clone();
+        gtk4::glib::timeout_add_local_once(
+            Duration::from_millis(cfg.overlay.empty_notice_ms),
+            move || app.quit(),
+        );
+    });
+
+    app.run();
+}
@@ -1,31 +1,51 @@
+#[cfg(feature = "atspi-backend")]
+use crate::config::CaretPlacement;
 use crate::config::Config;
-use crate::hints::HintMap;
-use crate::ipc::{Request, send};
+#[cfg(feature = "layer-shell")]
+use crate::config::SpanningWindow;
+use crate::hints::{
+    Child, CoordSpace, DrawnHints, HintMap, LastClick, convert_children, convert_hints,
+    generate_partitioned, last_clicked_label, unique_prefix_match,
+};
+use crate::ipc::{Request, Response, send};
 use crate::window_system::{WindowSystem, WindowSystemType};
 use gtk4::gio::ListModel;
 use gtk4::gio::prelude::ApplicationExtManual;
 use gtk4::glib::{ControlFlow, Propagation, translate::IntoGlib};
 use gtk4::prelude::*;
 use gtk4::{
-    Application, ApplicationWindow, CssProvider, EventControllerKey,
-    STYLE_PROVIDER_PRIORITY_APPLICATION, StyleContext, gdk,
+    Application, ApplicationWindow, CssProvider, DrawingArea, EventControllerKey,
+    STYLE_PROVIDER_PRIORITY_APPLICATION, gdk,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex, mpsc};
 
 #[cfg(feature = "layer-shell")]
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 
+/// Show `hints` and act on the chosen one. `space` says whether the hints are in
+/// screen coordinates or relative to `focus_extents`; the overlay converts them
+/// to screen coordinates once so drawing and clicking agree. `children` are the
+/// hinted elements in labeling order, kept to relabel them cheaply.
 pub fn launch_overlay(
     config: Config,
     ws: WindowSystem,
     focus_extents: Option<(i32, i32, i32, i32)>,
     hints: HintMap,
+    children: Vec<Child>,
+    space: CoordSpace,
     debug_overlay: bool,
 ) {
     let app = Application::builder().application_id("xyz.hintsx").build();
 
-    let hints_rc = Rc::new(hints);
+    let hints_rc = Rc::new(RefCell::new(convert_hints(
+        hints,
+        focus_extents,
+        space,
+        CoordSpace::Screen,
+    )));
+    let children = convert_children(children, focus_extents, space, CoordSpace::Screen);
     let ws_clone = ws.clone();
     app.connect_activate(move |app| {
         build_ui(
@@ -34,6 +54,7 @@ pub fn launch_overlay(
             &ws_clone,
             focus_extents,
             hints_rc.clone(),
+            children.clone(),
             debug_overlay,
         );
     });
@@ -46,12 +67,13 @@ fn build_ui(
     cfg: &Config,
     ws: &WindowSystem,
     focus_extents: Option<(i32, i32, i32, i32)>,
-    hints: Rc<HintMap>,
+    hints: Rc<RefCell<HintMap>>,
+    children: Vec<Child>,
     debug_overlay: bool,
 ) {
     // Ensure the window itself is transparent and not painted by the theme.
     let provider = CssProvider::new();
-    let _ = provider.load_from_data(
+    provider.load_from_data(
         "
         /* Force our overlay window and everything inside it to be transparent. */
         window.overlay-window,
@@ -74,7 +96,7 @@ fn build_ui(
         ",
     );
     if let Some(display) = gdk::Display::default() {
-        StyleContext::add_provider_for_display(
+        gtk4::style_context_add_provider_for_display(
             &display,
             &provider,
             STYLE_PROVIDER_PRIORITY_APPLICATION,
@@ -87,19 +109,23 @@ fn build_ui(
         }
     }
 
+    // Without usable extents, size to the hints instead of anchoring a tiny
+    // overlay at a bogus origin.
+    let focus_extents = crate::coords::sized_extents(focus_extents);
     let use_focus_anchor = focus_extents.is_some();
     let (origin_x, origin_y, width, height) = if use_focus_anchor {
         let (fx, fy, fw, fh) = focus_extents.unwrap();
         (fx, fy, fw, fh)
     } else {
-        let (max_x, max_y) = hints.values().fold((0i32, 0i32), |acc, child| {
-            (
-                acc.0.max(child.absolute_x + child.width),
-                acc.1.max(child.absolute_y + child.height),
-            )
-        });
-        (0, 0, max_x, max_y)
+        hint_bounds(&hints.borrow())
     };
+    // Without a window to anchor to, the overlay only needs placing when hints lie
+    // left of or above the screen origin (monitors left of/above the primary)
+    #[cfg_attr(
+        not(any(feature = "layer-shell", feature = "x11")),
+        allow(unused_variables)
+    )]
+    let positioned = use_focus_anchor || (origin_x, origin_y) != (0, 0);
 
     let window = ApplicationWindow::builder()
         .application(app)
@@ -109,12 +135,11 @@ fn build_ui(
         .build();
 
     // Force RGBA visual for transparency
-    if let Some(display) = gdk::Display::default() {
-        if display.is_rgba() {
-            if debug_overlay {
-                println!("DEBUG: Display supports RGBA");
-            }
-        }
+    if let Some(display) = gdk::Display::default()
+        && display.is_rgba()
+        && debug_overlay
+    {
+        println!("DEBUG: Display supports RGBA");
     }
 
     window.set_can_focus(true);
@@ -129,6 +154,13 @@ fn build_ui(
         println!("DEBUG: window css classes = {:?}", classes);
     }
 
+    // Area the main surface covers in global coordinates, and the other monitors
+    // (with the part of the window on them) that get a surface of their own
+    #[cfg_attr(not(feature = "layer-shell"), allow(unused_mut))]
+    let mut surface_rect = (origin_x, origin_y, width, height);
+    #[cfg_attr(not(feature = "layer-shell"), allow(unused_mut))]
+    let mut extra_surfaces: Vec<(gdk::Monitor, gdk::Rectangle)> = Vec::new();
+
     #[cfg(feature = "layer-shell")]
     if ws.window_system_type == WindowSystemType::Wayland && cfg.overlay.use_layer_shell {
         window.init_layer_shell();
@@ -140,11 +172,37 @@ fn build_ui(
         // Set exclusive zone from config (-1 for transparency)
         window.set_exclusive_zone(cfg.overlay.layer_shell_exclusive_zone);
 
-        if use_focus_anchor {
-            if let Some((monitor, geo)) = monitor_for_point(origin_x, origin_y) {
+        if positioned {
+            let monitors = display_monitors();
+            let geos: Vec<gdk::Rectangle> = monitors.iter().map(|m| m.geometry()).collect();
+            let overlaps = monitor_overlaps((origin_x, origin_y, width, height), &geos);
+            let spanning =
+                overlaps.len() > 1 && cfg.overlay.spanning_window != SpanningWindow::Origin;
+            let anchor = match overlaps.iter().max_by_key(|(_, r)| r.width() * r.height()) {
+                Some(&(idx, part)) if spanning => {
+                    if debug_overlay {
+                        println!(
+                            "DEBUG: window spans {} monitors; {:?} mode",
+                            overlaps.len(),
+                            cfg.overlay.spanning_window
+                        );
+                    }
+                    surface_rect = (part.x(), part.y(), part.width(), part.height());
+                    if cfg.overlay.spanning_window == SpanningWindow::Split {
+                        extra_surfaces = overlaps
+                            .iter()
+                            .filter(|(i, _)| *i != idx)
+                            .map(|&(i, r)| (monitors[i].clone(), r))
+                            .collect();
+                    }
+                    Some((monitors[idx].clone(), geos[idx]))
+                }
+                _ => monitor_for_point(origin_x, origin_y),
+            };
+            if let Some((monitor, geo)) = anchor {
                 window.set_monitor(Some(&monitor));
-                let margin_top = origin_y - geo.y() + cfg.overlay_y_offset;
-                let margin_left = origin_x - geo.x() + cfg.overlay_x_offset;
+                let margin_top = surface_rect.1 - geo.y() + cfg.overlay_y_offset;
+                let margin_left = surface_rect.0 - geo.x() + cfg.overlay_x_offset;
                 window.set_margin(Edge::Top, margin_top);
                 window.set_margin(Edge::Left, margin_left);
                 if debug_overlay {
@@ -162,24 +220,34 @@ fn build_ui(
         }
     }
 
-    let window_width = width.max(100);
-    let window_height = height.max(100);
+    let window_width = surface_rect.2.max(100);
+    let window_height = surface_rect.3.max(100);
     window.set_default_size(window_width, window_height);
 
     // Use DrawingArea for transparent rendering
-    use gtk4::DrawingArea;
     let drawing_area = DrawingArea::new();
     drawing_area.add_css_class("overlay-area");
     drawing_area.set_hexpand(true);
     drawing_area.set_vexpand(true);
 
     // Set drawing area to have transparent background
-    drawing_area.set_opacity(1.0); // Ensure widget itself is visible
+    // Global dim knob; GTK multiplies it into everything the area draws
+    drawing_area.set_opacity(layer_opacity(&cfg.overlay));
     window.set_child(Some(&drawing_area));
 
     // Set window background to transparent
     window.set_opacity(1.0); // Keep window visible but let background be transparent
 
+    // Exclusive keyboard grabs make some compositors hide the pointer over the
+    // overlay; give both surfaces an explicit cursor so hover stays visible.
+    if let Some(cursor_name) = overlay_cursor(&cfg.overlay) {
+        window.set_cursor_from_name(Some(cursor_name));
+        drawing_area.set_cursor_from_name(Some(cursor_name));
+        if debug_overlay {
+            println!("DEBUG: overlay cursor forced to {:?}", cursor_name);
+        }
+    }
+
     // Ensure the surface itself is non-opaque so alpha is respected.
     window.connect_realize(move |w| {
         if let Some(surface) = w.surface() {
@@ -193,101 +261,428 @@ fn build_ui(
         }
     });
 
+    // Typed hint prefix and repeat count, shared between the key handler and the draw callback
+    let input = Rc::new(RefCell::new(String::new()));
+    let repeat_count = Rc::new(RefCell::new(0u32));
+    // Center of the scrollable element chosen as scroll target, if any
+    let scroll_anchor: Rc<RefCell<Option<(i32, i32)>>> = Rc::new(RefCell::new(None));
+    // Labels picked with `mouse.multi_select_modifier`, in the order chosen
+    let selected: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+
     // Clone data for drawing callback
     let hints_for_draw = hints.clone();
+    let input_for_draw = input.clone();
+    let repeat_for_draw = repeat_count.clone();
+    let anchor_for_draw = scroll_anchor.clone();
+    let selected_for_draw = selected.clone();
+    let shape_input = cfg.overlay.x11_shape_input && ws.window_system_type == WindowSystemType::X11;
     let cfg_for_draw = cfg.clone();
-    let offset_x = if use_focus_anchor { origin_x } else { 0 };
-    let offset_y = if use_focus_anchor { origin_y } else { 0 };
-
-    drawing_area.set_draw_func(move |_area, cr, w, h| {
-        // Clear entire surface to transparent if configured
-        if cfg_for_draw.overlay.clear_background {
-            cr.set_source_rgba(
-                cfg_for_draw.overlay.background_color.0,
-                cfg_for_draw.overlay.background_color.1,
-                cfg_for_draw.overlay.background_color.2,
-                cfg_for_draw.overlay.background_color.3,
-            );
-            cr.set_operator(gtk4::cairo::Operator::Clear);
-            cr.paint().ok();
-        }
+    let last_clicked = cfg
+        .hints
+        .mark_last_clicked
+        .then(LastClick::load)
+        .flatten()
+        .map(|last| last.child);
+    let monitor_scale = monitor_for_point(origin_x, origin_y).map_or(1, |(m, _)| m.scale_factor());
+    let font_size = scaled_font_size(&cfg.hints, monitor_scale);
+    if debug_overlay {
+        println!(
+            "DEBUG: hint font size {} (monitor scale {})",
+            font_size, monitor_scale
+        );
+    }
+    let (offset_x, offset_y) = (surface_rect.0, surface_rect.1);
+    // Only an OpenCV collection can mistake the boxes for elements
+    let record_drawn = cfg.backends.enable.iter().any(|name| name == "opencv");
+    let drawn_for_draw = Rc::new(RefCell::new(Vec::new()));
 
-        // Now switch to normal compositing
-        cr.set_operator(gtk4::cairo::Operator::Over);
+    // Global alpha the hints are drawn with; a tick callback raises it to 1 while
+    // fading in
+    let animate = cfg.overlay.animation_ms > 0 && !reduce_motion(cfg);
+    let appear = Rc::new(Cell::new(if animate { 0.0 } else { 1.0 }));
+    let appear_for_draw = appear.clone();
 
-        if cfg_for_draw.overlay.debug_overlay_enabled || debug_overlay {
-            // Draw a debug overlay with configured color
-            cr.set_source_rgba(
-                cfg_for_draw.overlay.debug_overlay_color.0,
-                cfg_for_draw.overlay.debug_overlay_color.1,
-                cfg_for_draw.overlay.debug_overlay_color.2,
-                cfg_for_draw.overlay.debug_overlay_color.3,
-            );
-            cr.rectangle(0.0, 0.0, w as f64, h as f64);
-            let _ = cr.fill();
-        }
+    // Debug tint and draw logging; starts from the config/env and flips with
+    // `overlay.debug_toggle_key`
+    let debug_draw = Rc::new(Cell::new(
+        cfg.overlay.debug_overlay_enabled || debug_overlay,
+    ));
+    let debug_for_draw = debug_draw.clone();
 
-        // Draw hints
-        for (label_text, child) in hints_for_draw.iter() {
-            let center_x =
-                child.absolute_x - offset_x + cfg_for_draw.overlay_x_offset + child.width / 2
-                    - cfg_for_draw.hints.hint_width_padding;
-            let center_y =
-                child.absolute_y - offset_y + cfg_for_draw.overlay_y_offset + child.height / 2
-                    - cfg_for_draw.hints.hint_height / 2;
-
-            let text = if cfg_for_draw.hints.hint_uppercase {
-                label_text.to_uppercase()
-            } else {
-                label_text.to_string()
-            };
+    // Shared by the main surface and, for a window spanning monitors, the extra
+    // per-monitor surfaces; `offset` is the surface's top-left in global coordinates
+    let draw_hints = Rc::new(
+        move |area: &DrawingArea,
+              cr: &gtk4::cairo::Context,
+              w: i32,
+              h: i32,
+              (offset_x, offset_y): (i32, i32)| {
+            // Clear entire surface to transparent if configured
+            if cfg_for_draw.overlay.clear_background {
+                cr.set_source_rgba(
+                    cfg_for_draw.overlay.background_color.0,
+                    cfg_for_draw.overlay.background_color.1,
+                    cfg_for_draw.overlay.background_color.2,
+                    cfg_for_draw.overlay.background_color.3,
+                );
+                cr.set_operator(gtk4::cairo::Operator::Clear);
+                cr.paint().ok();
+            }
 
-            // Set font
-            cr.select_font_face(
-                &cfg_for_draw.hints.hint_font_face,
-                gtk4::cairo::FontSlant::Normal,
-                gtk4::cairo::FontWeight::Bold,
-            );
-            cr.set_font_size(cfg_for_draw.hints.hint_font_size as f64);
-
-            let extents = cr.text_extents(&text).unwrap();
-            let hint_width = extents.width() + (cfg_for_draw.hints.hint_width_padding * 2) as f64;
-            let hint_height = cfg_for_draw.hints.hint_height as f64;
-
-            // Draw background
-            cr.set_source_rgba(
-                cfg_for_draw.hints.hint_background_color.0,
-                cfg_for_draw.hints.hint_background_color.1,
-                cfg_for_draw.hints.hint_background_color.2,
-                cfg_for_draw.hints.hint_background_color.3,
-            );
-            let _ = cr.rectangle(center_x as f64, center_y as f64, hint_width, hint_height);
-            let _ = cr.fill();
-
-            // Draw text
-            cr.set_source_rgba(
-                cfg_for_draw.hints.hint_font_color.0,
-                cfg_for_draw.hints.hint_font_color.1,
-                cfg_for_draw.hints.hint_font_color.2,
-                cfg_for_draw.hints.hint_font_color.3,
-            );
-            let text_x = center_x as f64 + cfg_for_draw.hints.hint_width_padding as f64;
-            let text_y = center_y as f64 + hint_height / 2.0 + extents.height() / 2.0;
-            cr.move_to(text_x, text_y);
-            let _ = cr.show_text(&text);
+            // Now switch to normal compositing
+            cr.set_operator(gtk4::cairo::Operator::Over);
+
+            // While fading in, draw into a group and composite it with the alpha
+            let alpha = appear_for_draw.get();
+            if alpha < 1.0 {
+                cr.push_group();
+            }
+
+            if debug_for_draw.get() {
+                println!(
+                    "DEBUG: draw {}x{} at offset ({}, {}), alpha {:.2}, typed {:?}",
+                    w,
+                    h,
+                    offset_x,
+                    offset_y,
+                    alpha,
+                    input_for_draw.borrow()
+                );
+                // Draw a debug overlay with configured color
+                cr.set_source_rgba(
+                    cfg_for_draw.overlay.debug_overlay_color.0,
+                    cfg_for_draw.overlay.debug_overlay_color.1,
+                    cfg_for_draw.overlay.debug_overlay_color.2,
+                    cfg_for_draw.overlay.debug_overlay_color.3,
+                );
+                cr.rectangle(0.0, 0.0, w as f64, h as f64);
+                let _ = cr.fill();
+            }
+
+            // Draw hints that still match what has been typed
+            let typed = input_for_draw.borrow();
+            let hints = hints_for_draw.borrow();
+            let last_clicked = last_clicked
+                .as_ref()
+                .and_then(|last| last_clicked_label(&hints, last));
+            let mut hint_rects = Vec::new();
+            for (label_text, child) in hints.iter() {
+                if !label_text.starts_with(typed.as_str()) {
+                    continue;
+                }
+                let text = if cfg_for_draw.hints.hint_uppercase {
+                    label_text.to_uppercase()
+                } else {
+                    label_text.to_string()
+                };
+
+                // Set font
+                cr.select_font_face(
+                    &cfg_for_draw.hints.hint_font_face,
+                    gtk4::cairo::FontSlant::Normal,
+                    gtk4::cairo::FontWeight::Bold,
+                );
+                cr.set_font_size(font_size);
+
+                let extents = cr.text_extents(&text).unwrap();
+                let (hint_width, hint_height) =
+                    hint_box_size(&cfg_for_draw.hints, extents.width(), extents.height());
+
+                // Center the box on the element
+                let (center_x, center_y) = child.center_in((offset_x, offset_y));
+                let box_x = (center_x + cfg_for_draw.overlay_x_offset) as f64 - hint_width / 2.0;
+                let box_y = (center_y + cfg_for_draw.overlay_y_offset) as f64 - hint_height / 2.0;
+
+                // Draw background, set apart for selected hints and the element
+                // clicked last time
+                let background = if selected_for_draw.borrow().contains(label_text) {
+                    cfg_for_draw.hints.selected_background_color
+                } else if last_clicked.as_ref() == Some(label_text) {
+                    cfg_for_draw.hints.last_clicked_background_color
+                } else if cfg_for_draw.backends.show_all {
+                    source_background(&cfg_for_draw.hints, child)
+                } else {
+                    cfg_for_draw.hints.hint_background_color
+                };
+                cr.set_source_rgba(background.0, background.1, background.2, background.3);
+                cr.rectangle(box_x, box_y, hint_width, hint_height);
+                let _ = cr.fill();
+                hint_rects.push((box_x, box_y, hint_width, hint_height));
+
+                // Draw text: the typed prefix in the pressed color, the rest normally.
+                // Upper-casing can change the length (ß -> SS), so count it as shown
+                let typed_shown = if cfg_for_draw.hints.hint_uppercase {
+                    typed.to_uppercase()
+                } else {
+                    typed.to_string()
+                };
+                let split = text
+                    .char_indices()
+                    .nth(typed_shown.chars().count())
+                    .map_or(text.len(), |(idx, _)| idx);
+                let (pressed, rest) = text.split_at(split);
+                let text_x = box_x + cfg_for_draw.hints.hint_padding_x as f64;
+                let text_y = box_y + hint_height / 2.0 + extents.height() / 2.0;
+                stroke_text_outline(cr, &text, text_x, text_y, &cfg_for_draw.hints);
+                cr.move_to(text_x, text_y);
+                cr.set_source_rgba(
+                    cfg_for_draw.hints.hint_pressed_font_color.0,
+                    cfg_for_draw.hints.hint_pressed_font_color.1,
+                    cfg_for_draw.hints.hint_pressed_font_color.2,
+                    cfg_for_draw.hints.hint_pressed_font_color.3,
+                );
+                let _ = cr.show_text(pressed);
+                cr.set_source_rgba(
+                    cfg_for_draw.hints.hint_font_color.0,
+                    cfg_for_draw.hints.hint_font_color.1,
+                    cfg_for_draw.hints.hint_font_color.2,
+                    cfg_for_draw.hints.hint_font_color.3,
+                );
+                let _ = cr.show_text(rest);
+
+                if cfg_for_draw.hints.show_names
+                    && let Some(name) = child.name.as_deref()
+                {
+                    let name = truncate_name(name, cfg_for_draw.hints.name_max_chars);
+                    cr.select_font_face(
+                        &cfg_for_draw.hints.hint_font_face,
+                        gtk4::cairo::FontSlant::Normal,
+                        gtk4::cairo::FontWeight::Normal,
+                    );
+                    cr.set_font_size(font_size * NAME_FONT_RATIO);
+                    let name_extents = cr.text_extents(&name).unwrap();
+                    let (name_width, name_height) = hint_box_size(
+                        &cfg_for_draw.hints,
+                        name_extents.width(),
+                        name_extents.height(),
+                    );
+                    // Centered under the label box
+                    let name_x = box_x + hint_width / 2.0 - name_width / 2.0;
+                    let name_y = box_y + hint_height;
+                    cr.set_source_rgba(
+                        cfg_for_draw.hints.hint_background_color.0,
+                        cfg_for_draw.hints.hint_background_color.1,
+                        cfg_for_draw.hints.hint_background_color.2,
+                        cfg_for_draw.hints.hint_background_color.3,
+                    );
+                    cr.rectangle(name_x, name_y, name_width, name_height);
+                    let _ = cr.fill();
+                    let text_x = name_x + cfg_for_draw.hints.hint_padding_x as f64;
+                    let text_y = name_y + name_height / 2.0 + name_extents.height() / 2.0;
+                    stroke_text_outline(cr, &name, text_x, text_y, &cfg_for_draw.hints);
+                    cr.move_to(text_x, text_y);
+                    cr.set_source_rgba(
+                        cfg_for_draw.hints.hint_font_color.0,
+                        cfg_for_draw.hints.hint_font_color.1,
+                        cfg_for_draw.hints.hint_font_color.2,
+                        cfg_for_draw.hints.hint_font_color.3,
+                    );
+                    let _ = cr.show_text(&name);
+                }
+            }
+
+            // Record where the boxes are on screen for another run's OpenCV capture;
+            // every surface draws the same boxes, so only changes are written
+            if record_drawn {
+                let rects: Vec<_> = hint_rects
+                    .iter()
+                    .map(|&(x, y, w, h)| {
+                        (
+                            (x + offset_x as f64).floor() as i32,
+                            (y + offset_y as f64).floor() as i32,
+                            w.ceil() as i32,
+                            h.ceil() as i32,
+                        )
+                    })
+                    .collect();
+                if *drawn_for_draw.borrow() != rects {
+                    let drawn = DrawnHints {
+                        pid: std::process::id(),
+                        rects,
+                    };
+                    if let Err(err) = drawn.save() {
+                        log::warn!("failed to save drawn hint boxes: {err}");
+                    }
+                    *drawn_for_draw.borrow_mut() = drawn.rects;
+                }
+            }
+
+            // Only the visible hint boxes take pointer input; a scroll anchor has already
+            // made the whole surface click-through
+            if shape_input
+                && anchor_for_draw.borrow().is_none()
+                && let Some(surface) = area.native().and_then(|native| native.surface())
+            {
+                surface.set_input_region(&hint_input_region(&hint_rects));
+            }
+
+            // Show the pending repeat count in the top-left corner
+            let repeat = *repeat_for_draw.borrow();
+            if repeat > 0 {
+                let text = format!("{repeat}x");
+                cr.select_font_face(
+                    &cfg_for_draw.hints.hint_font_face,
+                    gtk4::cairo::FontSlant::Normal,
+                    gtk4::cairo::FontWeight::Bold,
+                );
+                cr.set_font_size(font_size);
+                let extents = cr.text_extents(&text).unwrap();
+                let padding = cfg_for_draw.hints.hint_padding_x as f64;
+                let (box_width, box_height) =
+                    hint_box_size(&cfg_for_draw.hints, extents.width(), extents.height());
+                cr.set_source_rgba(
+                    cfg_for_draw.hints.hint_background_color.0,
+                    cfg_for_draw.hints.hint_background_color.1,
+                    cfg_for_draw.hints.hint_background_color.2,
+                    cfg_for_draw.hints.hint_background_color.3,
+                );
+                cr.rectangle(0.0, 0.0, box_width, box_height);
+                let _ = cr.fill();
+                cr.set_source_rgba(
+                    cfg_for_draw.hints.hint_pressed_font_color.0,
+                    cfg_for_draw.hints.hint_pressed_font_color.1,
+                    cfg_for_draw.hints.hint_pressed_font_color.2,
+                    cfg_for_draw.hints.hint_pressed_font_color.3,
+                );
+                let text_y = box_height / 2.0 + extents.height() / 2.0;
+                stroke_text_outline(cr, &text, padding, text_y, &cfg_for_draw.hints);
+                cr.move_to(padding, text_y);
+                let _ = cr.show_text(&text);
+            }
+
+            if alpha < 1.0 {
+                let _ = cr.pop_group_to_source();
+                let _ = cr.paint_with_alpha(alpha);
+            }
+        },
+    );
+
+    let extras: Vec<(ApplicationWindow, DrawingArea)> = extra_surfaces
+        .iter()
+        .map(|(monitor, part)| monitor_surface(app, cfg, monitor, *part, draw_hints.clone()))
+        .collect();
+    let extra_areas: Vec<DrawingArea> = extras.iter().map(|(_, area)| area.clone()).collect();
+    drawing_area.set_draw_func(move |area, cr, w, h| {
+        draw_hints(area, cr, w, h, (offset_x, offset_y));
+        // The extra surfaces show the same typed prefix, so redraw them along
+        for extra in &extra_areas {
+            extra.queue_draw();
         }
     });
+    if !extras.is_empty() {
+        let extra_windows: Vec<ApplicationWindow> = extras.into_iter().map(|(w, _)| w).collect();
+        let to_hide = extra_windows.clone();
+        window.connect_hide(move |_| {
+            for extra in &to_hide {
+                extra.set_visible(false);
+            }
+        });
+        window.connect_destroy(move |_| {
+            for extra in &extra_windows {
+                extra.close();
+            }
+        });
+    }
+
+    if animate {
+        let duration_ms = cfg.overlay.animation_ms;
+        let started = Cell::new(None);
+        drawing_area.add_tick_callback(move |area, clock| {
+            // Frame times are in microseconds
+            let now = clock.frame_time();
+            let start = started.get().unwrap_or(now);
+            started.set(Some(start));
+            let alpha = appear_alpha((now - start) as f64 / 1000.0, duration_ms);
+            appear.set(alpha);
+            area.queue_draw();
+            if alpha >= 1.0 {
+                ControlFlow::Break
+            } else {
+                ControlFlow::Continue
+            }
+        });
+    }
 
-    let input = Rc::new(RefCell::new(String::new()));
-    let repeat_count = Rc::new(RefCell::new(0u32));
     let hints_for_key = hints.clone();
     let cfg_mouse = cfg.mouse.clone();
+    let activate_on_unique = cfg.hints.activate_on_unique;
+    let mark_last_clicked = cfg.hints.mark_last_clicked;
+    let debug_toggle_key = cfg.overlay.debug_toggle_key;
+    // The main alphabet first, then the extra ones `alphabet_cycle_key` switches to
+    let alphabets: Vec<(String, String)> =
+        std::iter::once(("default".to_string(), cfg.alphabet.clone()))
+            .chain(
+                cfg.hints
+                    .alphabets
+                    .iter()
+                    .map(|named| (named.name.clone(), named.alphabet.clone())),
+            )
+            .collect();
+    let alphabet_idx = Cell::new(0usize);
+    // What the current hints label, for relabeling without collecting again
+    let children = RefCell::new(children);
+    let alphabet_cycle_key = cfg.hints.alphabet_cycle_key;
+    let reserved_labels = cfg.hints.reserved_labels.clone();
+    let role_prefixes = cfg.hints.role_prefixes.clone();
+    let key_aliases = cfg.hints.key_aliases.clone();
+    // First stage of `hints.scoped`: the hints are containers to narrow down to
+    #[cfg(feature = "atspi-backend")]
+    let scoping = Cell::new(cfg.hints.scoped);
+    #[cfg(feature = "atspi-backend")]
+    let (cfg_for_scope, ws_for_scope) = (cfg.clone(), ws.clone());
+    // Time of the last key press, for `overlay.auto_dismiss_ms`
+    let last_key = Rc::new(Cell::new(std::time::Instant::now()));
+    if cfg.overlay.auto_dismiss_ms > 0 {
+        let timeout = std::time::Duration::from_millis(cfg.overlay.auto_dismiss_ms);
+        let last_key = last_key.clone();
+        let window_weak = window.downgrade();
+        gtk4::glib::timeout_add_local(IDLE_CHECK_INTERVAL, move || {
+            let Some(window) = window_weak.upgrade() else {
+                return ControlFlow::Break;
+            };
+            if !idle_expired(last_key.get().elapsed(), timeout) {
+                return ControlFlow::Continue;
+            }
+            log::info!("OVERLAY: No key pressed for {timeout:?}; closing");
+            window.close();
+            ControlFlow::Break
+        });
+    }
+
+    // Publish the hints over D-Bus and click the ones activated there
+    let published = cfg
+        .overlay
+        .dbus_service
+        .then(|| Arc::new(Mutex::new(hints.borrow().clone())));
+    if let Some(published) = &published {
+        match super::dbus::serve(published.clone(), cfg.mouse.click_offsets.clone()) {
+            Ok(activated) => poll_remote_activations(
+                activated,
+                hints.clone(),
+                cfg.mouse.click_offsets.clone(),
+                window.downgrade(),
+                app.clone(),
+            ),
+            Err(err) => log::warn!("OVERLAY: D-Bus hint service unavailable: {err}"),
+        }
+    }
+
+    // When the overlay was mapped, for `overlay.input_grace_ms`
+    let mapped_at: Rc<Cell<Option<std::time::Instant>>> = Rc::new(Cell::new(None));
+    {
+        let mapped_at = mapped_at.clone();
+        window.connect_map(move |_| mapped_at.set(Some(std::time::Instant::now())));
+    }
+    let input_grace = std::time::Duration::from_millis(cfg.overlay.input_grace_ms);
+
     let key_controller = EventControllerKey::new();
     let window_weak = window.downgrade();
+    let area_weak = drawing_area.downgrade();
     let app_handle = app.clone();
 
     key_controller.connect_key_pressed(move |_ctrl, keyval, _keycode, state| {
         let keyval_raw = keyval.into_glib();
+        last_key.set(std::time::Instant::now());
 
         // Check for exit key
         if keyval_raw == cfg_mouse.exit_key {
@@ -297,23 +692,171 @@ fn build_ui(
             return Propagation::Stop;
         }
 
+        // Keys from the launch binding can still be down when the overlay appears
+        if in_input_grace(mapped_at.get().map(|at| at.elapsed()), input_grace) {
+            log::info!(
+                "OVERLAY: Ignoring key {keyval_raw:#x} pressed right after the overlay appeared"
+            );
+            return Propagation::Stop;
+        }
+
+        // Click every multi-selected hint, one after another
+        if keyval_raw == cfg_mouse.multi_select_confirm_key && !selected.borrow().is_empty() {
+            let points = selection_clicks(
+                &selected.borrow(),
+                &hints_for_key.borrow(),
+                &cfg_mouse.click_offsets,
+            );
+            log::info!(
+                "OVERLAY: Clicking {} selected hints: {:?}",
+                points.len(),
+                points
+            );
+            if let Some(w) = window_weak.upgrade() {
+                w.hide();
+            }
+            let app_ref = app_handle.clone();
+            let guard = app_ref.hold();
+            gtk4::glib::timeout_add_local_once(std::time::Duration::from_millis(25), move || {
+                let request = selection_sequence(&points, |x, y| {
+                    monitor_for_point(x, y)
+                        .and_then(|(monitor, _)| monitor.connector())
+                        .map(|name| name.to_string())
+                });
+                let result = send(request);
+                log::info!("OVERLAY: Sequence request result: {:?}", result);
+                drop(guard);
+                app_ref.quit();
+            });
+            return Propagation::Stop;
+        }
+
+        if let Some(on) = debug_toggled(debug_toggle_key, keyval_raw, debug_draw.get()) {
+            debug_draw.set(on);
+            println!(
+                "DEBUG: debug overlay {}",
+                if debug_draw.get() { "on" } else { "off" }
+            );
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
+            return Propagation::Stop;
+        }
+
+        // Relabel everything with the next alphabet ("number mode" and the like)
+        if alphabets.len() > 1 && keyval_raw == alphabet_cycle_key {
+            let relabeled = next_alphabet_hints(
+                &children.borrow(),
+                &alphabets,
+                &alphabet_idx,
+                &reserved_labels,
+                &role_prefixes,
+            );
+            *hints_for_key.borrow_mut() = relabeled;
+            let (name, alphabet) = &alphabets[alphabet_idx.get()];
+            // Selected labels now name other elements
+            selected.borrow_mut().clear();
+            if let Some(published) = &published {
+                published
+                    .lock()
+                    .unwrap()
+                    .clone_from(&hints_for_key.borrow());
+            }
+            log::info!("OVERLAY: Switched to alphabet {name:?} ({alphabet:?})");
+            input.borrow_mut().clear();
+            *repeat_count.borrow_mut() = 0;
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
+            return Propagation::Stop;
+        }
+        let alphabet_has_upper = alphabets[alphabet_idx.get()]
+            .1
+            .chars()
+            .any(|c| c.is_uppercase());
+
+        // Cancel clears the typed prefix and repeat count but keeps hinting
+        if keyval_raw == cfg_mouse.cancel_key {
+            input.borrow_mut().clear();
+            *repeat_count.borrow_mut() = 0;
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
+            return Propagation::Stop;
+        }
+
+        // Backspace drops the last hint char, or the last repeat digit once the prefix is empty
+        if keyval_raw == cfg_mouse.backspace_key {
+            erase_last_input(&mut input.borrow_mut(), &mut repeat_count.borrow_mut());
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
+            return Propagation::Stop;
+        }
+
+        // Page scrolling, vim Ctrl-d/Ctrl-u style; a repeat prefix scrolls that many pages
+        if keyval_raw == cfg_mouse.page_down_key || keyval_raw == cfg_mouse.page_up_key {
+            let pages = (*repeat_count.borrow()).max(1);
+            *repeat_count.borrow_mut() = 0;
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
+            let _ = send(Request::Scroll {
+                x: 0,
+                y: page_scroll_y(&cfg_mouse, keyval_raw),
+                at: *scroll_anchor.borrow(),
+                count: pages.saturating_mul(cfg_mouse.page_scroll_steps),
+            });
+            return Propagation::Stop;
+        }
+
         // Handle vim movement keys for scrolling/moving
-        if let Some(ch) = keyval.to_unicode() {
-            let ch_lower = ch.to_ascii_lowercase();
-            let is_uppercase = ch.is_ascii_uppercase();
+        if let Some(ch) = keyval_char(keyval_raw, keyval.to_unicode(), &key_aliases) {
+            let ch_lower = fold_case(ch);
+            let is_uppercase = ch.is_uppercase();
+            // A button suffix typed after a complete label fires it with that button
+            let label_complete = {
+                let hints = hints_for_key.borrow();
+                let typed = input.borrow();
+                !typed.is_empty()
+                    && (hints.contains_key(&*typed)
+                        || activate_on_unique && unique_prefix_match(&hints, &typed).is_some())
+            };
+            let suffix = suffix_button(&cfg_mouse.button_suffixes, label_complete, ch);
 
             // Check for movement/scroll keys, but prefer hint input if this letter could start a hint.
-            if ch_lower == 'h' || ch_lower == 'j' || ch_lower == 'k' || ch_lower == 'l' {
+            let diagonal = diagonal_direction(&cfg_mouse, ch_lower);
+            if suffix.is_none()
+                && (ch_lower == 'h'
+                    || ch_lower == 'j'
+                    || ch_lower == 'k'
+                    || ch_lower == 'l'
+                    || diagonal.is_some())
+            {
                 let prospective = format!("{}{}", input.borrow(), ch_lower);
-                let hint_would_match = hints_for_key.keys().any(|h| h.starts_with(&prospective));
+                let hint_would_match = hints_for_key
+                    .borrow()
+                    .keys()
+                    .any(|h| h.starts_with(&prospective));
 
                 if !hint_would_match {
+                    // A repeat prefix ("5j") multiplies the step
+                    let steps = (*repeat_count.borrow()).max(1) as i32;
+                    *repeat_count.borrow_mut() = 0;
+                    if let Some(area) = area_weak.upgrade() {
+                        area.queue_draw();
+                    }
                     let (dx, dy) = match ch_lower {
                         'h' => (-cfg_mouse.move_pixel_sensitivity, 0),
                         'l' => (cfg_mouse.move_pixel_sensitivity, 0),
                         'k' => (0, -cfg_mouse.move_pixel_sensitivity),
                         'j' => (0, cfg_mouse.move_pixel_sensitivity),
-                        _ => (0, 0),
+                        _ => diagonal.map_or((0, 0), |(sx, sy)| {
+                            (
+                                sx * cfg_mouse.move_pixel_sensitivity,
+                                sy * cfg_mouse.move_pixel_sensitivity,
+                            )
+                        }),
                     };
 
                     if state.contains(gdk::ModifierType::SHIFT_MASK) {
@@ -322,57 +865,191 @@ fn build_ui(
                                 / cfg_mouse.move_pixel_sensitivity,
                             y: dy * cfg_mouse.scroll_pixel_sensitivity
                                 / cfg_mouse.move_pixel_sensitivity,
+                            at: *scroll_anchor.borrow(),
+                            count: steps as u32,
                         });
                     } else {
                         let _ = send(Request::Move {
-                            x: dx,
-                            y: dy,
+                            x: steps * dx,
+                            y: steps * dy,
                             absolute: false,
+                            duration_ms: None,
                         });
                     }
                     return Propagation::Stop;
                 }
             }
 
-            // Check for numeric prefix (repeat count)
-            if ch_lower.is_ascii_digit() {
+            // Check for numeric prefix (repeat count), unless digits are hint labels
+            let prospective = format!("{}{}", input.borrow(), ch_lower);
+            if suffix.is_none()
+                && ch_lower.is_ascii_digit()
+                && !hints_for_key
+                    .borrow()
+                    .keys()
+                    .any(|h| h.starts_with(&prospective))
+            {
                 let digit = ch_lower.to_digit(10).unwrap_or(0);
                 let current_repeat = *repeat_count.borrow();
-                *repeat_count.borrow_mut() = current_repeat * 10 + digit;
+                *repeat_count.borrow_mut() =
+                    push_repeat_digit(current_repeat, digit, cfg_mouse.max_repeat);
+                if let Some(area) = area_weak.upgrade() {
+                    area.queue_draw();
+                }
                 return Propagation::Stop;
             }
 
             // Regular hint character
-            input.borrow_mut().push(ch_lower);
+            // Keep the typed case only when Shift doesn't mean right-click and the
+            // alphabet itself has uppercase labels
+            let typed = if !cfg_mouse.shift_is_right_click && alphabet_has_upper {
+                ch
+            } else {
+                ch_lower
+            };
+            if suffix.is_none() {
+                input.borrow_mut().push(typed);
+            }
             let current = input.borrow().clone();
 
             // If no hint starts with the current buffer, reset
-            if !hints_for_key.keys().any(|h| h.starts_with(&current)) {
+            if !hints_for_key
+                .borrow()
+                .keys()
+                .any(|h| h.starts_with(&current))
+            {
                 input.borrow_mut().clear();
                 repeat_count.borrow_mut().clone_from(&0);
+                if let Some(area) = area_weak.upgrade() {
+                    area.queue_draw();
+                }
                 return Propagation::Stop;
             }
+            if let Some(area) = area_weak.upgrade() {
+                area.queue_draw();
+            }
 
             // Check if we have a complete hint
-            if let Some(child) = hints_for_key.get(&current) {
+            let hints_now = hints_for_key.borrow();
+            let matched = hints_now.get_key_value(&current).or_else(|| {
+                if activate_on_unique {
+                    unique_prefix_match(&hints_now, &current)
+                } else {
+                    None
+                }
+            });
+            // With button suffixes, a complete label waits for its suffix or Return
+            if matched.is_some() && suffix.is_none() && !cfg_mouse.button_suffixes.is_empty() {
+                return Propagation::Stop;
+            }
+            #[cfg(feature = "atspi-backend")]
+            if let Some(path) = matched.and_then(|(_, child)| scope_root(scoping.get(), child)) {
+                log::info!("OVERLAY: Container picked; hinting inside {path}");
+                drop(hints_now);
+                let alphabet = &alphabets[alphabet_idx.get()].1;
+                match scoped_hints(&cfg_for_scope, &ws_for_scope, &path, alphabet) {
+                    Ok((scoped_children, scoped)) => {
+                        *children.borrow_mut() = scoped_children;
+                        *hints_for_key.borrow_mut() = scoped;
+                        selected.borrow_mut().clear();
+                        if let Some(published) = &published {
+                            published
+                                .lock()
+                                .unwrap()
+                                .clone_from(&hints_for_key.borrow());
+                        }
+                        scoping.set(false);
+                    }
+                    Err(err) => log::warn!("OVERLAY: Hinting inside the container failed: {err}"),
+                }
+                input.borrow_mut().clear();
+                if let Some(area) = area_weak.upgrade() {
+                    area.queue_draw();
+                }
+                return Propagation::Stop;
+            }
+            if let Some((label, child)) = matched {
                 log::info!("╔══════════════════════════════════════════════════════════════╗");
                 log::info!("║              OVERLAY: Hint Match Found!                      ║");
                 log::info!("╚══════════════════════════════════════════════════════════════╝");
-                log::info!("OVERLAY: Matched hint label: '{}'", current);
+                log::info!(
+                    "OVERLAY: Matched hint label: '{}' (typed '{}')",
+                    label,
+                    current
+                );
                 log::info!("OVERLAY: Child element details:");
                 log::info!("  absolute_x: {}", child.absolute_x);
                 log::info!("  absolute_y: {}", child.absolute_y);
                 log::info!("  width: {}", child.width);
                 log::info!("  height: {}", child.height);
 
-                let click_x = child.absolute_x + child.width / 2;
-                let click_y = child.absolute_y + child.height / 2;
+                // The tab-close modifier retargets a tab hint to its close button
+                let tab_close_modifier =
+                    gdk::ModifierType::from_bits_truncate(cfg_mouse.tab_close_modifier);
+                let tab_close = (!tab_close_modifier.is_empty()
+                    && state.contains(tab_close_modifier))
+                .then(|| tab_close_point(child, cfg_mouse.tab_close_offset))
+                .flatten();
+                let (click_x, click_y) =
+                    tab_close.unwrap_or_else(|| child.click_point(&cfg_mouse.click_offsets));
+                // Once spent on the tab, the modifier doesn't pick an action too
+                let (state, is_uppercase) = if tab_close.is_some() {
+                    log::info!("OVERLAY: Clicking the tab's close button");
+                    (
+                        state.difference(tab_close_modifier),
+                        is_uppercase && !tab_close_modifier.contains(gdk::ModifierType::SHIFT_MASK),
+                    )
+                } else {
+                    (state, is_uppercase)
+                };
                 log::info!(
-                    "OVERLAY: Calculated click position (center): ({}, {})",
+                    "OVERLAY: Calculated click position: ({}, {})",
                     click_x,
                     click_y
                 );
 
+                // With the multi-select modifier the hint joins the selection instead
+                let multi_modifier =
+                    gdk::ModifierType::from_bits_truncate(cfg_mouse.multi_select_modifier);
+                if !multi_modifier.is_empty() && state.contains(multi_modifier) {
+                    toggle_selection(&mut selected.borrow_mut(), label);
+                    log::info!("OVERLAY: Selection is now {:?}", selected.borrow());
+                    input.borrow_mut().clear();
+                    *repeat_count.borrow_mut() = 0;
+                    if let Some(area) = area_weak.upgrade() {
+                        area.queue_draw();
+                    }
+                    return Propagation::Stop;
+                }
+
+                // A plain hint on a scrollable element makes it the scroll target
+                if let Some((click_x, click_y)) =
+                    scroll_anchor_for(&cfg_mouse, child, state, is_uppercase, (click_x, click_y))
+                {
+                    log::info!(
+                        "OVERLAY: Action determined: SCROLL ANCHOR at ({}, {})",
+                        click_x,
+                        click_y
+                    );
+                    *scroll_anchor.borrow_mut() = Some((click_x, click_y));
+                    input.borrow_mut().clear();
+                    *repeat_count.borrow_mut() = 0;
+                    // Let wheel events pass through the overlay to the element below
+                    if let Some(surface) = window_weak.upgrade().and_then(|w| w.surface()) {
+                        surface.set_input_region(&gtk4::cairo::Region::create());
+                    }
+                    let _ = send(Request::Move {
+                        x: click_x,
+                        y: click_y,
+                        absolute: true,
+                        duration_ms: None,
+                    });
+                    if let Some(area) = area_weak.upgrade() {
+                        area.queue_draw();
+                    }
+                    return Propagation::Stop;
+                }
+
                 // Determine action based on modifiers
                 let mut button = 0u16; // Left click
                 let mut action_type = "click";
@@ -392,18 +1069,72 @@ fn build_ui(
                     state.contains(gdk::ModifierType::CONTROL_MASK)
                 );
 
-                // Check modifiers
-                // Right click: uppercase letter (Shift was pressed) OR explicit Shift modifier
-                if is_uppercase || state.contains(gdk::ModifierType::SHIFT_MASK) {
+                let modifier_action = modifier_action(&cfg_mouse, state, is_uppercase);
+                if modifier_action == ModifierAction::Warp {
+                    // Warp: move the pointer and keep hinting so actions can be chained
+                    log::info!("OVERLAY: Action determined: WARP (overlay stays open)");
+                    let result = send(Request::Move {
+                        x: click_x,
+                        y: click_y,
+                        absolute: true,
+                        duration_ms: None,
+                    });
+                    log_emitted_position((click_x, click_y), &result);
+                    input.borrow_mut().clear();
+                    *repeat_count.borrow_mut() = 0;
+                    if let Some(area) = area_weak.upgrade() {
+                        area.queue_draw();
+                    }
+                    return Propagation::Stop;
+                }
+
+                #[cfg(feature = "atspi-backend")]
+                if let Some(path) = scroll_to_target(child, state, cfg_mouse.scroll_to_modifier) {
+                    log::info!("OVERLAY: Action determined: SCROLL TO {path}");
+                    if let Some(w) = window_weak.upgrade() {
+                        w.hide();
+                    }
+                    let app_ref = app_handle.clone();
+                    let mut app_guard = Some(app_ref.hold());
+                    gtk4::glib::timeout_add_local(
+                        std::time::Duration::from_millis(25),
+                        move || {
+                            if let Err(err) =
+                                crate::backends::atspi_backend::scroll_into_view(&path)
+                            {
+                                log::warn!("OVERLAY: Scrolling into view failed: {err}");
+                            }
+                            if let Some(guard) = app_guard.take() {
+                                drop(guard);
+                            }
+                            app_ref.quit();
+                            ControlFlow::Break
+                        },
+                    );
+                    return Propagation::Stop;
+                }
+
+                let mut key_request = key_request(&cfg_mouse, state, suffix);
+                if let Some(suffix_button) = suffix {
+                    button = suffix_button;
+                    log::info!("OVERLAY: Action determined: CLICK by suffix (button={button})");
+                } else if key_request.is_some() {
+                    // Keyboard activation: click to focus, then send the configured key
+                    action_type = "key";
+                    log::info!(
+                        "OVERLAY: Action determined: KEY {} after focusing click",
+                        cfg_mouse.activate_key
+                    );
+                } else if modifier_action == ModifierAction::RightClick {
                     // Right click
                     button = 2;
                     log::info!("OVERLAY: Action determined: RIGHT CLICK (button=2)");
-                } else if state.contains(gdk::ModifierType::ALT_MASK) {
+                } else if modifier_action == ModifierAction::Drag {
                     // Drag/grab - send mouse down, move, then up
                     action_type = "drag";
                     log::info!("OVERLAY: Action determined: DRAG");
-                } else if state.contains(gdk::ModifierType::CONTROL_MASK) {
-                    // Hover - just move the mouse there
+                } else if modifier_action == ModifierAction::Hover {
+                    // Hover (or a warp that closes the overlay) - just move the mouse there
                     log::info!("OVERLAY: Action determined: HOVER (move only)");
                     log::info!("OVERLAY: Closing overlay window FIRST");
                     if let Some(w) = window_weak.upgrade() {
@@ -414,6 +1145,7 @@ fn build_ui(
                     let app_ref = app_handle.clone();
                     let mut app_guard = Some(app_ref.hold());
                     let (tx, ty) = (click_x, click_y);
+                    let hover_glide_ms = Some(cfg_mouse.hover_glide_ms).filter(|&ms| ms > 0);
                     gtk4::glib::timeout_add_local(
                         std::time::Duration::from_millis(25),
                         move || {
@@ -422,8 +1154,10 @@ fn build_ui(
                                 x: tx,
                                 y: ty,
                                 absolute: true,
+                                duration_ms: hover_glide_ms,
                             });
                             log::info!("OVERLAY: Move request result: {:?}", result);
+                            log_emitted_position((tx, ty), &result);
                             if let Some(guard) = app_guard.take() {
                                 drop(guard);
                             }
@@ -444,6 +1178,20 @@ fn build_ui(
                 };
                 log::info!("OVERLAY: Repeat count: {}", repeat);
 
+                if mark_last_clicked && action_type != "drag" {
+                    let last = LastClick {
+                        child: child.clone(),
+                    };
+                    if let Err(err) = last.save() {
+                        log::warn!("OVERLAY: Failed to save the last click: {err}");
+                    }
+                }
+
+                // A mapped overlay may still hold the input grab for a moment after
+                // hiding, so the daemon has to let it settle; checked before hide()
+                // unmaps the window
+                let settle = window_weak.upgrade().is_some_and(|w| w.is_mapped());
+
                 // Close overlay FIRST, then send requests after the window fully unmaps.
                 log::info!("OVERLAY: Closing overlay window FIRST");
                 if let Some(w) = window_weak.upgrade() {
@@ -453,40 +1201,32 @@ fn build_ui(
                 let app_ref = app_handle.clone();
                 let mut app_guard = Some(app_ref.hold());
                 let is_drag = action_type == "drag";
+                // The daemon scrolls a drop near the overlay's edge towards it
+                let drag_area = (origin_x, origin_y, width, height);
                 let (tx, ty, btn, rep) = (click_x, click_y, button, repeat);
+                // Let the daemon scale the target with the output it is on
+                let target_output = monitor_for_point(click_x, click_y)
+                    .and_then(|(monitor, _)| monitor.connector())
+                    .map(|name| name.to_string());
+                #[cfg(feature = "atspi-backend")]
+                let caret_path = if action_type == "click" && button == 0 {
+                    caret_target(child, cfg_mouse.caret_placement)
+                } else {
+                    None
+                };
+                #[cfg(feature = "atspi-backend")]
+                let caret_placement = cfg_mouse.caret_placement;
                 gtk4::glib::timeout_add_local(std::time::Duration::from_millis(25), move || {
                     if is_drag {
-                        log::info!("OVERLAY: Executing DRAG sequence asynchronously:");
-                        log::info!("  1. Mouse down at current position");
-                        log::info!("  2. Move to ({}, {})", tx, ty);
-                        log::info!("  3. Mouse up at target");
-
-                        let result1 = send(Request::Click {
-                            x: 0,
-                            y: 0,
-                            button: 0,
-                            button_states: vec![1], // Mouse down
-                            repeat: 1,
-                            absolute: false,
-                        });
-                        log::info!("OVERLAY: Mouse DOWN result: {:?}", result1);
-
-                        let result2 = send(Request::Move {
-                            x: tx,
-                            y: ty,
-                            absolute: true,
-                        });
-                        log::info!("OVERLAY: MOVE result: {:?}", result2);
-
-                        let result3 = send(Request::Click {
+                        log::info!("OVERLAY: Dragging to ({}, {})", tx, ty);
+                        let result = send(Request::Drag {
                             x: tx,
                             y: ty,
-                            button: 0,
-                            button_states: vec![0], // Mouse up
-                            repeat: 1,
-                            absolute: true,
+                            area: Some(drag_area),
+                            settle,
                         });
-                        log::info!("OVERLAY: Mouse UP result: {:?}", result3);
+                        log::info!("OVERLAY: Drag result: {:?}", result);
+                        log_emitted_position((tx, ty), &result);
                     } else {
                         // Regular click (left or right)
                         log::info!("OVERLAY: Executing CLICK asynchronously:");
@@ -503,8 +1243,32 @@ fn build_ui(
                             button_states: vec![1, 0],
                             repeat: rep,
                             absolute: true,
+                            move_first: true,
+                            output: target_output.clone(),
+                            settle,
                         });
                         log::info!("OVERLAY: Click request result: {:?}", result);
+                        log_emitted_position((tx, ty), &result);
+
+                        #[cfg(feature = "atspi-backend")]
+                        if let Some(path) = &caret_path {
+                            log::info!(
+                                "OVERLAY: Placing caret ({:?}) in {}",
+                                caret_placement,
+                                path
+                            );
+                            if let Err(err) =
+                                crate::backends::atspi_backend::set_caret(path, caret_placement)
+                            {
+                                log::warn!("OVERLAY: Setting caret failed: {err}");
+                            }
+                        }
+
+                        if let Some(request) = key_request.take() {
+                            log::info!("OVERLAY: Sending {:?}", request);
+                            let result = send(request);
+                            log::info!("OVERLAY: Key request result: {:?}", result);
+                        }
                     }
                     if let Some(guard) = app_guard.take() {
                         drop(guard);
@@ -522,54 +1286,1336 @@ fn build_ui(
     });
     window.add_controller(key_controller);
 
-    let ws_clone = ws.clone();
-    let cfg_clone = cfg.clone();
-    window.connect_realize(move |window| {
-        #[cfg(feature = "x11")]
-        {
-            if ws_clone.window_system_type == WindowSystemType::X11 && use_focus_anchor {
-                if let Some(surface) = window.surface() {
-                    if let Ok(x11_surface) = surface.downcast::<gdk4_x11::X11Surface>() {
-                        let xid = x11_surface.xid();
-                        let target_x = origin_x + cfg_clone.overlay_x_offset;
-                        let target_y = origin_y + cfg_clone.overlay_y_offset;
-
-                        // Spawn a thread to move the window to avoid blocking and allow WM to map it
-                        std::thread::spawn(move || {
-                            std::thread::sleep(std::time::Duration::from_millis(100));
-                            let _ = std::process::Command::new("xdotool")
-                                .arg("windowmove")
-                                .arg(xid.to_string())
-                                .arg(target_x.to_string())
-                                .arg(target_y.to_string())
-                                .output();
-                        });
-                    }
-                }
+    // GTK4 can't place windows; on X11 move the overlay over the focused window
+    // once it is mapped, so the window manager's own placement doesn't win
+    #[cfg(feature = "x11")]
+    if ws.window_system_type == WindowSystemType::X11 && positioned {
+        let offset = (cfg.overlay_x_offset, cfg.overlay_y_offset);
+        window.connect_map(move |window| {
+            let Some(x11_surface) = window
+                .surface()
+                .and_then(|s| s.downcast::<gdk4_x11::X11Surface>().ok())
+            else {
+                return;
+            };
+            let request =
+                super::x11::MoveRequest::new(x11_surface.xid(), (origin_x, origin_y), offset);
+            if debug_overlay {
+                println!("DEBUG: X11 move request {:?}", request);
+            }
+            if let Err(err) = super::x11::move_surface(&x11_surface, request) {
+                log::warn!("failed to move the X11 overlay: {err}");
             }
+        });
+    }
+
+    // Present the window for better transparency support
+    window.present();
+}
+
+/// AT-SPI path of the container `child` while hints are still in the container
+/// stage of `hints.scoped`; `None` once its elements are hinted.
+#[cfg(feature = "atspi-backend")]
+fn scope_root(scoping: bool, child: &Child) -> Option<String> {
+    child.accessible_path.clone().filter(|_| scoping)
+}
+
+/// The elements inside the container at `path`, filtered like the top-level
+/// ones, and their hints.
+#[cfg(feature = "atspi-backend")]
+fn scoped_hints(
+    cfg: &Config,
+    ws: &WindowSystem,
+    path: &str,
+    alphabet: &str,
+) -> anyhow::Result<(Vec<Child>, HintMap)> {
+    use crate::backends::atspi_backend::AtspiBackend;
+    let deadline = cfg
+        .backends
+        .collect_timeout()
+        .map(|timeout| std::time::Instant::now() + timeout);
+    let children = AtspiBackend::new(cfg.clone(), ws.clone())
+        .get_subtree(path, deadline)?
+        .children;
+    hint_scope(cfg, children, alphabet)
+        .ok_or_else(|| anyhow::anyhow!("nothing to hint inside {path}"))
+}
+
+/// `children` of a container filtered like the top-level ones and labelled from
+/// `alphabet`; `None` if none are left.
+#[cfg(feature = "atspi-backend")]
+fn hint_scope(
+    cfg: &Config,
+    mut children: Vec<Child>,
+    alphabet: &str,
+) -> Option<(Vec<Child>, HintMap)> {
+    use crate::hints::{drop_clustered, filter_roles};
+    filter_roles(&mut children, &cfg.hints.only_roles);
+    let children = drop_clustered(children, cfg.hints.min_distance);
+    if children.is_empty() {
+        return None;
+    }
+    let hints = generate_partitioned(
+        &children,
+        alphabet,
+        &cfg.hints.reserved_labels,
+        &cfg.hints.role_prefixes,
+    );
+    Some((children, hints))
+}
+
+/// AT-SPI path of `child` when `modifier` (a `gdk::ModifierType` mask, 0 for
+/// disabled) is held, routing the hint to a scroll-into-view instead of a click.
+/// Elements without a path (OpenCV hints) fall through to the other actions.
+#[cfg(feature = "atspi-backend")]
+fn scroll_to_target(child: &Child, state: gdk::ModifierType, modifier: u32) -> Option<String> {
+    let modifier = gdk::ModifierType::from_bits_truncate(modifier);
+    if modifier.is_empty() || !state.contains(modifier) {
+        return None;
+    }
+    child.accessible_path.clone()
+}
+
+/// AT-SPI path of `child` when a plain click on it should also place the caret.
+#[cfg(feature = "atspi-backend")]
+fn caret_target(child: &Child, placement: CaretPlacement) -> Option<String> {
+    use crate::backends::atspi_backend::TEXT_ROLES;
+    let is_text = child
+        .role
+        .as_deref()
+        .is_some_and(|r| TEXT_ROLES.contains(&r));
+    if placement == CaretPlacement::None || !is_text {
+        return None;
+    }
+    child.accessible_path.clone()
+}
+
+/// Point on the tab `child` (role "PageTab") at fractions `offset` of its box,
+/// where the close button sits; `None` for anything that isn't a tab.
+fn tab_close_point(child: &Child, offset: (f64, f64)) -> Option<(i32, i32)> {
+    (child.role.as_deref() == Some("PageTab")).then(|| child.point_at(offset.0, offset.1))
+}
+
+/// Add `label` to the multi-selection, or take it out again if already chosen.
+fn toggle_selection(selected: &mut Vec<String>, label: &str) {
+    match selected.iter().position(|l| l == label) {
+        Some(idx) => {
+            selected.remove(idx);
         }
+        None => selected.push(label.to_string()),
+    }
+}
+
+/// Click points of the `selected` labels in the order they were chosen, one
+/// step each; labels no longer on screen are skipped.
+fn selection_clicks(
+    selected: &[String],
+    hints: &HintMap,
+    click_offsets: &std::collections::HashMap<String, (f64, f64)>,
+) -> Vec<(i32, i32)> {
+    selected
+        .iter()
+        .filter_map(|label| hints.get(label))
+        .map(|child| child.click_point(click_offsets))
+        .collect()
+}
+
+/// Where later scrolls go when a hint on `child` is typed with `state` held:
+/// its `click` point, for a plain hint on an element with one of the
+/// `scroll_roles`.
+fn scroll_anchor_for(
+    cfg: &crate::config::MouseConfig,
+    child: &Child,
+    state: gdk::ModifierType,
+    uppercase: bool,
+    click: (i32, i32),
+) -> Option<(i32, i32)> {
+    let no_modifiers = !uppercase
+        && !state.intersects(
+            gdk::ModifierType::SHIFT_MASK
+                | gdk::ModifierType::CONTROL_MASK
+                | gdk::ModifierType::ALT_MASK
+                | gdk::ModifierType::SUPER_MASK,
+        );
+    let scrollable = child
+        .role
+        .as_ref()
+        .is_some_and(|r| cfg.scroll_roles.contains(r));
+    (no_modifiers && scrollable).then_some(click)
+}
+
+/// One left click per point, in order, as a single Sequence; only the first
+/// click waits for the overlay to go away. `output` names the monitor a point
+/// is on.
+fn selection_sequence(
+    points: &[(i32, i32)],
+    output: impl Fn(i32, i32) -> Option<String>,
+) -> Request {
+    let steps = points
+        .iter()
+        .enumerate()
+        .map(|(idx, &(x, y))| Request::Click {
+            x,
+            y,
+            button: 0,
+            button_states: vec![1, 0],
+            repeat: 1,
+            absolute: true,
+            move_first: true,
+            output: output(x, y),
+            settle: idx == 0,
+        })
+        .collect();
+    Request::Sequence { steps }
+}
+
+/// Click hints activated over D-Bus: hide the overlay, then send the left click
+/// typing the label would, and quit.
+fn poll_remote_activations(
+    activated: mpsc::Receiver<String>,
+    hints: Rc<RefCell<HintMap>>,
+    click_offsets: std::collections::HashMap<String, (f64, f64)>,
+    window_weak: gtk4::glib::WeakRef<ApplicationWindow>,
+    app: Application,
+) {
+    gtk4::glib::timeout_add_local(IDLE_CHECK_INTERVAL, move || {
+        let label = match activated.try_recv() {
+            Ok(label) => label,
+            Err(mpsc::TryRecvError::Empty) => return ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => return ControlFlow::Break,
+        };
+        let Some((x, y)) = super::dbus::activation_point(&hints.borrow(), &label, &click_offsets)
+        else {
+            return ControlFlow::Continue;
+        };
+        log::info!("OVERLAY: Hint {label:?} activated over D-Bus; clicking ({x}, {y})");
+        if let Some(w) = window_weak.upgrade() {
+            w.hide();
+        }
+        let app_ref = app.clone();
+        let guard = app_ref.hold();
+        gtk4::glib::timeout_add_local_once(std::time::Duration::from_millis(25), move || {
+            let output = monitor_for_point(x, y)
+                .and_then(|(monitor, _)| monitor.connector())
+                .map(|name| name.to_string());
+            let result = send(Request::Click {
+                x,
+                y,
+                button: 0,
+                button_states: vec![1, 0],
+                repeat: 1,
+                absolute: true,
+                move_first: true,
+                output,
+                settle: true,
+            });
+            log_emitted_position((x, y), &result);
+            drop(guard);
+            app_ref.quit();
+        });
+        ControlFlow::Break
     });
+}
 
-    // Present the window for better transparency support
+/// Where the glyph path of text filled at `(x, y)` is stroked from, and the
+/// stroke's line width; `None` at outline width 0.
+fn text_outline(style: &crate::config::HintsStyle, x: f64, y: f64) -> Option<((f64, f64), f64)> {
+    // The stroke is centered on the path and the fill covers its inner half.
+    (style.text_outline_width > 0.0).then_some(((x, y), style.text_outline_width * 2.0))
+}
+
+/// Stroke the glyph outlines of `text` in the configured outline color, so the
+/// fill drawn on top stays readable whatever is underneath. No-op at width 0.
+fn stroke_text_outline(
+    cr: &gtk4::cairo::Context,
+    text: &str,
+    x: f64,
+    y: f64,
+    style: &crate::config::HintsStyle,
+) {
+    let Some(((x, y), line_width)) = text_outline(style, x, y) else {
+        return;
+    };
+    cr.save().ok();
+    cr.move_to(x, y);
+    cr.text_path(text);
+    cr.set_source_rgba(
+        style.text_outline_color.0,
+        style.text_outline_color.1,
+        style.text_outline_color.2,
+        style.text_outline_color.3,
+    );
+    cr.set_line_width(line_width);
+    cr.set_line_join(gtk4::cairo::LineJoin::Round);
+    let _ = cr.stroke();
+    cr.restore().ok();
+}
+
+/// Report where the daemon says the pointer actually went, so scaling or clamping
+/// mismatches show up next to the requested target.
+fn log_emitted_position(target: (i32, i32), result: &anyhow::Result<Response>) {
+    if let Ok(Response::Position { x, y }) = result {
+        log::info!(
+            "OVERLAY: Requested ({}, {}), daemon emitted ({}, {}) device px",
+            target.0,
+            target.1,
+            x,
+            y
+        );
+    }
+}
+
+/// What the held modifiers do with a typed hint; suffixes and the scroll-to and
+/// key modifiers are checked separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModifierAction {
+    /// Move the pointer and keep the overlay open for another hint
+    Warp,
+    RightClick,
+    Drag,
+    /// Move the pointer and close the overlay
+    Hover,
+    Click,
+}
+
+/// The action for a hint typed with `state` held; `uppercase` is whether the
+/// label was typed shifted.
+fn modifier_action(
+    cfg: &crate::config::MouseConfig,
+    state: gdk::ModifierType,
+    uppercase: bool,
+) -> ModifierAction {
+    let held = |mask: u32| {
+        let modifier = gdk::ModifierType::from_bits_truncate(mask);
+        !modifier.is_empty() && state.contains(modifier)
+    };
+    let is_warp = held(cfg.warp_modifier);
+    let is_right_click = if cfg.shift_is_right_click {
+        // Uppercase letter (Shift was pressed) OR explicit Shift modifier
+        uppercase || state.contains(gdk::ModifierType::SHIFT_MASK)
+    } else {
+        held(cfg.right_click_modifier)
+    };
+    if is_warp && cfg.warp_stay_open {
+        ModifierAction::Warp
+    } else if is_right_click {
+        ModifierAction::RightClick
+    } else if state.contains(gdk::ModifierType::ALT_MASK) && !is_warp {
+        ModifierAction::Drag
+    } else if is_warp || state.contains(gdk::ModifierType::CONTROL_MASK) {
+        ModifierAction::Hover
+    } else {
+        ModifierAction::Click
+    }
+}
+
+/// The key a hint typed with `state` held sends after its focusing click:
+/// `activate_key` while `key_modifier` is held, unless a button suffix already
+/// picked the action.
+fn key_request(
+    cfg: &crate::config::MouseConfig,
+    state: gdk::ModifierType,
+    suffix: Option<u16>,
+) -> Option<Request> {
+    let modifier = gdk::ModifierType::from_bits_truncate(cfg.key_modifier);
+    (suffix.is_none() && !modifier.is_empty() && state.contains(modifier)).then(|| Request::Key {
+        key: cfg.activate_key.clone(),
+    })
+}
+
+/// Wheel detents one page step scrolls for the page-down/up key `keyval`;
+/// negative, i.e. down, for page down.
+fn page_scroll_y(cfg: &crate::config::MouseConfig, keyval: u32) -> i32 {
+    if keyval == cfg.page_down_key {
+        -cfg.scroll_pixel_sensitivity
+    } else {
+        cfg.scroll_pixel_sensitivity
+    }
+}
+
+/// Advance `idx` to the next of `alphabets` and label the cached `children`
+/// with it; only the labels change, nothing is collected again.
+fn next_alphabet_hints(
+    children: &[Child],
+    alphabets: &[(String, String)],
+    idx: &Cell<usize>,
+    reserved: &[String],
+    role_prefixes: &std::collections::HashMap<String, String>,
+) -> HintMap {
+    idx.set((idx.get() + 1) % alphabets.len());
+    generate_partitioned(children, &alphabets[idx.get()].1, reserved, role_prefixes)
+}
+
+/// Unit direction `(x, y)` for `ch` if it is one of the configured diagonal move keys.
+fn diagonal_direction(cfg: &crate::config::MouseConfig, ch: char) -> Option<(i32, i32)> {
+    [
+        (&cfg.move_up_left, (-1, -1)),
+        (&cfg.move_up_right, (1, -1)),
+        (&cfg.move_down_left, (-1, 1)),
+        (&cfg.move_down_right, (1, 1)),
+    ]
+    .into_iter()
+    .find(|(key, _)| key.chars().eq(std::iter::once(ch)))
+    .map(|(_, dir)| dir)
+}
+
+/// Hint font size in pixels, multiplied by the monitor scale when
+/// `font_scale_with_dpi` is set.
+fn scaled_font_size(style: &crate::config::HintsStyle, monitor_scale: i32) -> f64 {
+    let size = style.hint_font_size as f64;
+    if style.font_scale_with_dpi {
+        size * monitor_scale.max(1) as f64
+    } else {
+        size
+    }
+}
+
+/// Input region covering the hint boxes `rects` (x, y, width, height), rounded
+/// outwards to whole pixels.
+fn hint_input_region(rects: &[(f64, f64, f64, f64)]) -> gtk4::cairo::Region {
+    let rects: Vec<gtk4::cairo::RectangleInt> = rects
+        .iter()
+        .map(|&(x, y, w, h)| {
+            let (left, top) = (x.floor() as i32, y.floor() as i32);
+            let (right, bottom) = ((x + w).ceil() as i32, (y + h).ceil() as i32);
+            gtk4::cairo::RectangleInt::new(left, top, right - left, bottom - top)
+        })
+        .collect();
+    gtk4::cairo::Region::create_rectangles(&rects)
+}
+
+/// `ch` in lower case, for matching typed keys against labels. Characters whose
+/// lower case takes more than one char (e.g. 'İ') are kept as typed.
+fn fold_case(ch: char) -> char {
+    let mut lower = ch.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(folded), None) => folded,
+        _ => ch,
+    }
+}
+
+/// Button to click with when `ch` is typed after a complete label: its entry in
+/// `suffixes`, or left for Return. None while the label is incomplete or
+/// suffixes are off.
+fn suffix_button(
+    suffixes: &std::collections::HashMap<char, u16>,
+    label_complete: bool,
+    ch: char,
+) -> Option<u16> {
+    if suffixes.is_empty() || !label_complete {
+        return None;
+    }
+    match ch {
+        '\r' => Some(0),
+        ch => suffixes.get(&ch).copied(),
+    }
+}
+
+/// Character a key press types: the configured alias for `keyval`, or what GTK
+/// made of it (`unicode`).
+fn keyval_char(
+    keyval: u32,
+    unicode: Option<char>,
+    aliases: &std::collections::HashMap<u32, char>,
+) -> Option<char> {
+    aliases.get(&keyval).copied().or(unicode)
+}
+
+/// Background color for `child`'s hint: its backend's entry in
+/// `source_background_colors`, or the plain `hint_background_color`.
+fn source_background(
+    style: &crate::config::HintsStyle,
+    child: &crate::hints::Child,
+) -> (f64, f64, f64, f64) {
+    child
+        .source
+        .as_ref()
+        .and_then(|source| style.source_background_colors.get(source))
+        .copied()
+        .unwrap_or(style.hint_background_color)
+}
+
+/// Area `(x, y, width, height)` covering every hint and the screen origin, so
+/// hints at negative coordinates widen it to the left/top instead of being cut off.
+fn hint_bounds(hints: &HintMap) -> (i32, i32, i32, i32) {
+    let (min_x, min_y, max_x, max_y) = hints.values().fold(
+        (0i32, 0i32, 0i32, 0i32),
+        |(min_x, min_y, max_x, max_y), child| {
+            (
+                min_x.min(child.absolute_x),
+                min_y.min(child.absolute_y),
+                max_x.max(child.absolute_x + child.width),
+                max_y.max(child.absolute_y + child.height),
+            )
+        },
+    );
+    (min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+/// How often the auto-dismiss timer checks for an idle overlay.
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Whether a key pressed `since_map` after the overlay was mapped falls within the
+/// `grace` period and should be ignored. Before the map is seen nothing is ignored.
+fn in_input_grace(since_map: Option<std::time::Duration>, grace: std::time::Duration) -> bool {
+    since_map.is_some_and(|elapsed| elapsed < grace)
+}
+
+/// Whether the overlay has gone `idle` without a key press for at least
+/// `timeout`; a key press starts `idle` over from zero.
+fn idle_expired(idle: std::time::Duration, timeout: std::time::Duration) -> bool {
+    idle >= timeout
+}
+
+/// Whether animations are off, by config or by the GTK setting.
+fn reduce_motion(cfg: &Config) -> bool {
+    cfg.overlay.reduce_motion
+        || gtk4::Settings::default().is_some_and(|s| !s.is_gtk_enable_animations())
+}
+
+/// Hint alpha `elapsed_ms` into a fade-in lasting `duration_ms`: 0 at the start,
+/// 1 from the end on, eased in between.
+fn appear_alpha(elapsed_ms: f64, duration_ms: u64) -> f64 {
+    if duration_ms == 0 {
+        return 1.0;
+    }
+    let t = (elapsed_ms / duration_ms as f64).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Size of element names relative to the label font.
+const NAME_FONT_RATIO: f64 = 0.8;
+
+/// `name` on one line, cut to at most `max_chars` characters (the last one an
+/// ellipsis when cut).
+fn truncate_name(name: &str, max_chars: usize) -> String {
+    let line = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= max_chars {
+        return line;
+    }
+    let mut cut: String = line.chars().take(max_chars.saturating_sub(1)).collect();
+    cut.truncate(cut.trim_end().len());
+    cut.push('…');
+    cut
+}
+
+/// Size of the box drawn behind a label whose text measures `text_width` by
+/// `text_height`: the text plus padding on each side, never shorter than `hint_height`.
+fn hint_box_size(
+    style: &crate::config::HintsStyle,
+    text_width: f64,
+    text_height: f64,
+) -> (f64, f64) {
+    let width = text_width + (style.hint_padding_x * 2) as f64;
+    let height = (text_height + (style.hint_padding_y * 2) as f64).max(style.hint_height as f64);
+    (width, height)
+}
+
+/// Append a typed digit to the repeat count, clamped to `max`.
+fn push_repeat_digit(current: u32, digit: u32, max: u32) -> u32 {
+    current.saturating_mul(10).saturating_add(digit).min(max)
+}
+
+/// Cursor set on the overlay while `keep_cursor_visible` is on; a blank
+/// `cursor_name` falls back to "default" rather than hiding the pointer.
+fn overlay_cursor(overlay: &crate::config::OverlayConfig) -> Option<&str> {
+    overlay
+        .keep_cursor_visible
+        .then(|| match overlay.cursor_name.trim() {
+            "" => "default",
+            name => name,
+        })
+}
+
+/// Opacity the hint layer is drawn with; GTK multiplies it into the alpha of
+/// every color drawn on the layer. Out-of-range values are clamped, NaN is opaque.
+fn layer_opacity(overlay: &crate::config::OverlayConfig) -> f64 {
+    if overlay.opacity.is_nan() {
+        1.0
+    } else {
+        overlay.opacity.clamp(0.0, 1.0)
+    }
+}
+
+/// The debug overlay's new state when `keyval` is the (non-zero) toggle `key`.
+fn debug_toggled(key: u32, keyval: u32, on: bool) -> Option<bool> {
+    (key != 0 && keyval == key).then_some(!on)
+}
+
+/// Drop the last typed hint char, or the last repeat digit once the prefix is
+/// empty.
+fn erase_last_input(input: &mut String, repeat_count: &mut u32) {
+    if input.pop().is_none() {
+        *repeat_count /= 10;
+    }
+}
+
+#[cfg(feature = "layer-shell")]
+fn display_monitors() -> Vec<gdk::Monitor> {
+    let Some(display) = gdk::Display::default() else {
+        return Vec::new();
+    };
+    let monitors: ListModel = display.monitors();
+    (0..monitors.n_items())
+        .filter_map(|idx| monitors.item(idx)?.downcast::<gdk::Monitor>().ok())
+        .collect()
+}
+
+/// Part of `extents` (x, y, width, height) on each of `monitors`, with the
+/// monitor's index. More than one entry means the window crosses a monitor boundary.
+#[cfg(feature = "layer-shell")]
+fn monitor_overlaps(
+    extents: (i32, i32, i32, i32),
+    monitors: &[gdk::Rectangle],
+) -> Vec<(usize, gdk::Rectangle)> {
+    monitors
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, geo)| {
+            let geo = (geo.x(), geo.y(), geo.width(), geo.height());
+            let (x, y, w, h) = crate::coords::intersect(extents, geo)?;
+            Some((idx, gdk::Rectangle::new(x, y, w, h)))
+        })
+        .collect()
+}
+
+/// Draw-only layer-shell surface covering `part` (global coordinates) of a window
+/// that continues onto `monitor`. It is click-through and never takes the
+/// keyboard; the main overlay window handles input.
+fn monitor_surface(
+    app: &Application,
+    cfg: &Config,
+    monitor: &gdk::Monitor,
+    part: gdk::Rectangle,
+    draw_hints: Rc<impl Fn(&DrawingArea, &gtk4::cairo::Context, i32, i32, (i32, i32)) + 'static>,
+) -> (ApplicationWindow, DrawingArea) {
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("HintsX")
+        .decorated(false)
+        .resizable(false)
+        .build();
+    if cfg.overlay.remove_background_class {
+        window.remove_css_class("background");
+    }
+    window.add_css_class("overlay-window");
+
+    #[cfg(feature = "layer-shell")]
+    {
+        let geo = monitor.geometry();
+        window.init_layer_shell();
+        window.set_namespace(Some(&cfg.overlay.layer_shell_namespace));
+        window.set_layer(Layer::Overlay);
+        window.set_keyboard_mode(KeyboardMode::None);
+        window.set_anchor(Edge::Top, true);
+        window.set_anchor(Edge::Left, true);
+        window.set_exclusive_zone(cfg.overlay.layer_shell_exclusive_zone);
+        window.set_monitor(Some(monitor));
+        window.set_margin(Edge::Top, part.y() - geo.y() + cfg.overlay_y_offset);
+        window.set_margin(Edge::Left, part.x() - geo.x() + cfg.overlay_x_offset);
+    }
+    #[cfg(not(feature = "layer-shell"))]
+    let _ = monitor;
+    window.set_default_size(part.width().max(1), part.height().max(1));
+
+    let area = DrawingArea::new();
+    area.add_css_class("overlay-area");
+    area.set_hexpand(true);
+    area.set_vexpand(true);
+    area.set_opacity(layer_opacity(&cfg.overlay));
+    let offset = (part.x(), part.y());
+    area.set_draw_func(move |area, cr, w, h| draw_hints(area, cr, w, h, offset));
+    window.set_child(Some(&area));
+
+    window.connect_realize(|w| {
+        if let Some(surface) = w.surface() {
+            surface.set_opaque_region(None);
+            surface.set_input_region(&gtk4::cairo::Region::create());
+        }
+    });
     window.present();
+    (window, area)
+}
+
+/// Geometry (x, y, width, height) of the monitor whose connector is `name`
+/// (e.g. "DP-2"). GTK must be initialized.
+pub fn monitor_geometry(name: &str) -> Option<(i32, i32, i32, i32)> {
+    let monitors: ListModel = gdk::Display::default()?.monitors();
+    (0..monitors.n_items())
+        .filter_map(|idx| monitors.item(idx)?.downcast::<gdk::Monitor>().ok())
+        .find(|monitor| monitor.connector().is_some_and(|c| c == name))
+        .map(|monitor| {
+            let geo = monitor.geometry();
+            (geo.x(), geo.y(), geo.width(), geo.height())
+        })
 }
 
 fn monitor_for_point(x: i32, y: i32) -> Option<(gdk::Monitor, gdk::Rectangle)> {
     let display = gdk::Display::default()?;
     let monitors: ListModel = display.monitors();
     for idx in 0..monitors.n_items() {
-        if let Some(item) = monitors.item(idx) {
-            if let Ok(monitor) = item.downcast::<gdk::Monitor>() {
-                let geo = monitor.geometry();
-                if x >= geo.x()
-                    && y >= geo.y()
-                    && x < geo.x() + geo.width()
-                    && y < geo.y() + geo.height()
-                {
-                    return Some((monitor, geo));
-                }
+        if let Some(item) = monitors.item(idx)
+            && let Ok(monitor) = item.downcast::<gdk::Monitor>()
+        {
+            let geo = monitor.geometry();
+            if x >= geo.x()
+                && y >= geo.y()
+                && x < geo.x() + geo.width()
+                && y < geo.y() + geo.height()
+            {
+                return Some((monitor, geo));
             }
         }
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_count_is_capped() {
+        let typed = |max| {
+            "12".chars().fold(0, |count, ch| {
+                push_repeat_digit(count, ch.to_digit(10).unwrap(), max)
+            })
+        };
+        assert_eq!(typed(99), 12);
+        assert_eq!(typed(9), 9);
+        assert_eq!(push_repeat_digit(u32::MAX, 5, u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn text_outline_is_stroked_from_the_fill_origin_at_twice_its_width() {
+        let mut style = crate::config::HintsStyle::default();
+        assert_eq!(text_outline(&style, 12.0, 30.5), None);
+        style.text_outline_width = 1.5;
+        assert_eq!(text_outline(&style, 12.0, 30.5), Some(((12.0, 30.5), 3.0)));
+    }
+
+    #[test]
+    #[cfg(feature = "layer-shell")]
+    fn overlay_cursor_stays_set_unless_turned_off() {
+        let mut overlay = crate::config::OverlayConfig::default();
+        assert_eq!(overlay_cursor(&overlay), Some("default"));
+        overlay.cursor_name = "crosshair".into();
+        assert_eq!(overlay_cursor(&overlay), Some("crosshair"));
+        overlay.cursor_name = " ".into();
+        assert_eq!(overlay_cursor(&overlay), Some("default"));
+        overlay.keep_cursor_visible = false;
+        assert_eq!(overlay_cursor(&overlay), None);
+    }
+
+    #[test]
+    fn layer_opacity_scales_every_hint_alpha() {
+        let mut overlay = crate::config::OverlayConfig::default();
+        let hint_alpha = crate::config::HintsStyle::default().hint_background_color.3;
+        assert_eq!(hint_alpha * layer_opacity(&overlay), hint_alpha);
+        overlay.opacity = 0.5;
+        assert_eq!(hint_alpha * layer_opacity(&overlay), hint_alpha * 0.5);
+        overlay.opacity = 1.5;
+        assert_eq!(layer_opacity(&overlay), 1.0);
+        overlay.opacity = -0.5;
+        assert_eq!(layer_opacity(&overlay), 0.0);
+        overlay.opacity = f64::NAN;
+        assert_eq!(layer_opacity(&overlay), 1.0);
+    }
+
+    #[test]
+    fn debug_toggle_key_flips_the_debug_overlay() {
+        let f12 = gdk::Key::F12.into_glib();
+        assert_eq!(debug_toggled(f12, f12, false), Some(true));
+        assert_eq!(debug_toggled(f12, f12, true), Some(false));
+        assert_eq!(debug_toggled(f12, gdk::Key::a.into_glib(), false), None);
+        // Unset, no key toggles it
+        assert_eq!(debug_toggled(0, 0, false), None);
+    }
+
+    #[test]
+    fn backspace_erases_hint_chars_before_repeat_digits() {
+        let (mut input, mut repeat) = (String::from("as"), 12);
+        erase_last_input(&mut input, &mut repeat);
+        assert_eq!((input.as_str(), repeat), ("a", 12));
+        erase_last_input(&mut input, &mut repeat);
+        assert_eq!((input.as_str(), repeat), ("", 12));
+        erase_last_input(&mut input, &mut repeat);
+        assert_eq!((input.as_str(), repeat), ("", 1));
+        erase_last_input(&mut input, &mut repeat);
+        erase_last_input(&mut input, &mut repeat);
+        assert_eq!((input.as_str(), repeat), ("", 0));
+    }
+
+    #[test]
+    #[cfg(feature = "atspi-backend")]
+    fn caret_target_only_for_text_with_a_path() {
+        let entry = Child {
+            absolute_x: 0,
+            absolute_y: 0,
+            width: 100,
+            height: 20,
+            role: Some("Entry".into()),
+            accessible_path: Some("/org/a11y/atspi/accessible/9".into()),
+            name: None,
+            source: None,
+        };
+        let path = Some("/org/a11y/atspi/accessible/9".to_string());
+        assert_eq!(caret_target(&entry, CaretPlacement::End), path);
+        assert_eq!(caret_target(&entry, CaretPlacement::Start), path);
+        assert_eq!(caret_target(&entry, CaretPlacement::None), None);
+        let button = Child {
+            role: Some("PushButton".into()),
+            ..entry.clone()
+        };
+        assert_eq!(caret_target(&button, CaretPlacement::End), None);
+        let detected = Child {
+            accessible_path: None,
+            ..entry
+        };
+        assert_eq!(caret_target(&detected, CaretPlacement::End), None);
+    }
+
+    #[test]
+    fn warp_modifier_is_opt_in() {
+        use gdk::ModifierType as M;
+        let mut cfg = crate::config::MouseConfig::default();
+        let ctrl_alt = M::CONTROL_MASK | M::ALT_MASK;
+        // Unset by default, so Ctrl+Alt keeps meaning drag
+        assert_eq!(modifier_action(&cfg, ctrl_alt, false), ModifierAction::Drag);
+        assert_eq!(
+            modifier_action(&cfg, M::CONTROL_MASK, false),
+            ModifierAction::Hover
+        );
+        assert_eq!(
+            modifier_action(&cfg, M::empty(), true),
+            ModifierAction::RightClick
+        );
+        assert_eq!(
+            modifier_action(&cfg, M::empty(), false),
+            ModifierAction::Click
+        );
+
+        cfg.warp_modifier = ctrl_alt.bits();
+        assert_eq!(modifier_action(&cfg, ctrl_alt, false), ModifierAction::Warp);
+        assert_eq!(modifier_action(&cfg, ctrl_alt, true), ModifierAction::Warp);
+        assert_eq!(
+            modifier_action(&cfg, M::ALT_MASK, false),
+            ModifierAction::Drag
+        );
+        cfg.warp_stay_open = false;
+        assert_eq!(
+            modifier_action(&cfg, ctrl_alt, false),
+            ModifierAction::Hover
+        );
+    }
+
+    #[test]
+    fn shift_is_right_click_can_be_turned_off() {
+        use gdk::ModifierType as M;
+        let mut cfg = crate::config::MouseConfig {
+            shift_is_right_click: false,
+            ..Default::default()
+        };
+        let ctrl_shift = M::CONTROL_MASK | M::SHIFT_MASK;
+        // Uppercase labels and Shift alone are plain clicks now
+        assert_eq!(
+            modifier_action(&cfg, M::empty(), true),
+            ModifierAction::Click
+        );
+        assert_eq!(
+            modifier_action(&cfg, M::SHIFT_MASK, true),
+            ModifierAction::Click
+        );
+        assert_eq!(
+            modifier_action(&cfg, M::empty(), false),
+            ModifierAction::Click
+        );
+        // right_click_modifier takes over
+        assert_eq!(
+            modifier_action(&cfg, ctrl_shift, true),
+            ModifierAction::RightClick
+        );
+        cfg.right_click_modifier = 0;
+        assert_eq!(
+            modifier_action(&cfg, ctrl_shift, true),
+            ModifierAction::Hover
+        );
+    }
+
+    #[test]
+    fn page_down_turns_the_wheel_down() {
+        let cfg = crate::config::MouseConfig::default();
+        assert_eq!(
+            page_scroll_y(&cfg, cfg.page_down_key),
+            -cfg.scroll_pixel_sensitivity
+        );
+        assert_eq!(
+            page_scroll_y(&cfg, cfg.page_up_key),
+            cfg.scroll_pixel_sensitivity
+        );
+    }
+
+    #[test]
+    fn key_modifier_sends_the_activate_key() {
+        use gdk::ModifierType as M;
+        let key = |request: Option<Request>| match request {
+            Some(Request::Key { key }) => Some(key),
+            _ => None,
+        };
+        let mut cfg = crate::config::MouseConfig {
+            key_modifier: M::SUPER_MASK.bits(),
+            activate_key: "KEY_SPACE".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            key(key_request(&cfg, M::SUPER_MASK, None)),
+            Some("KEY_SPACE".into())
+        );
+        assert_eq!(
+            key(key_request(&cfg, M::SUPER_MASK | M::SHIFT_MASK, None)),
+            Some("KEY_SPACE".into())
+        );
+        assert_eq!(key(key_request(&cfg, M::empty(), None)), None);
+        assert_eq!(key(key_request(&cfg, M::CONTROL_MASK, None)), None);
+        // A button suffix wins over the modifier
+        assert_eq!(key(key_request(&cfg, M::SUPER_MASK, Some(2))), None);
+        // No modifier configured: nothing sends keys
+        cfg.key_modifier = 0;
+        assert_eq!(key(key_request(&cfg, M::SUPER_MASK, None)), None);
+    }
+
+    #[test]
+    fn diagonal_keys_are_optional() {
+        let mut cfg = crate::config::MouseConfig::default();
+        assert_eq!(diagonal_direction(&cfg, 'y'), None);
+        cfg.move_up_left = "y".into();
+        cfg.move_up_right = "u".into();
+        cfg.move_down_left = "b".into();
+        cfg.move_down_right = "n".into();
+        assert_eq!(diagonal_direction(&cfg, 'y'), Some((-1, -1)));
+        assert_eq!(diagonal_direction(&cfg, 'u'), Some((1, -1)));
+        assert_eq!(diagonal_direction(&cfg, 'b'), Some((-1, 1)));
+        assert_eq!(diagonal_direction(&cfg, 'n'), Some((1, 1)));
+        assert_eq!(diagonal_direction(&cfg, 'h'), None);
+        cfg.move_up_left = "yy".into();
+        assert_eq!(diagonal_direction(&cfg, 'y'), None);
+    }
+
+    #[test]
+    fn font_size_follows_the_monitor_scale_only_when_enabled() {
+        let mut style = crate::config::HintsStyle {
+            hint_font_size: 15,
+            ..Default::default()
+        };
+        assert_eq!(scaled_font_size(&style, 2), 15.0);
+        style.font_scale_with_dpi = true;
+        assert_eq!(scaled_font_size(&style, 2), 30.0);
+        assert_eq!(scaled_font_size(&style, 1), 15.0);
+        // A monitor reporting no scale counts as 1
+        assert_eq!(scaled_font_size(&style, 0), 15.0);
+    }
+
+    #[test]
+    fn hint_box_fits_text_and_padding() {
+        let style: crate::config::HintsStyle = serde_json::from_value(serde_json::json!({
+            "hint_width_padding": 4,
+            "hint_padding_y": 2,
+            "hint_height": 20
+        }))
+        .unwrap();
+        // The old name still sets the horizontal padding
+        assert_eq!(style.hint_padding_x, 4);
+        assert_eq!(hint_box_size(&style, 30.0, 12.0), (38.0, 20.0));
+        assert_eq!(hint_box_size(&style, 30.0, 18.0), (38.0, 22.0));
+    }
+
+    #[test]
+    fn input_region_rounds_hint_boxes_outwards() {
+        let region = hint_input_region(&[(10.5, 20.2, 30.0, 9.6), (100.0, 0.0, 20.0, 10.0)]);
+        assert_eq!(region.num_rectangles(), 2);
+        let rect = |i| {
+            let r = region.rectangle(i);
+            (r.x(), r.y(), r.width(), r.height())
+        };
+        // 10.5..40.5 and 20.2..29.8 cover pixels 10..41 and 20..30
+        assert_eq!(rect(1), (10, 20, 31, 10));
+        assert_eq!(rect(0), (100, 0, 20, 10));
+        assert!(hint_input_region(&[]).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "layer-shell")]
+    fn monitor_overlaps_split_a_spanning_window() {
+        let monitors = [
+            gdk::Rectangle::new(0, 0, 1920, 1080),
+            gdk::Rectangle::new(1920, 0, 1280, 1024),
+        ];
+        let parts = |extents| -> Vec<_> {
+            monitor_overlaps(extents, &monitors)
+                .into_iter()
+                .map(|(idx, r)| (idx, (r.x(), r.y(), r.width(), r.height())))
+                .collect()
+        };
+        // Ending exactly at the boundary stays on one monitor
+        assert_eq!(
+            parts((100, 100, 1820, 500)),
+            vec![(0, (100, 100, 1820, 500))]
+        );
+        assert_eq!(
+            parts((1800, 100, 400, 300)),
+            vec![(0, (1800, 100, 120, 300)), (1, (1920, 100, 280, 300))]
+        );
+        assert_eq!(parts((4000, 0, 100, 100)), vec![]);
+    }
+
+    #[test]
+    fn truncate_name_cuts_whole_chars() {
+        assert_eq!(truncate_name("Open\n  file", 20), "Open file");
+        assert_eq!(truncate_name("abc", 3), "abc");
+        assert_eq!(truncate_name("Ünïcödé näme", 5), "Ünïc…");
+        assert_eq!(truncate_name("日本語のボタン", 4), "日本語…");
+        // No space is left dangling before the ellipsis
+        assert_eq!(truncate_name("ab cd", 4), "ab…");
+    }
+
+    #[test]
+    fn appear_alpha_eases_from_zero_to_one() {
+        assert_eq!(appear_alpha(0.0, 200), 0.0);
+        assert_eq!(appear_alpha(100.0, 200), 0.5);
+        assert_eq!(appear_alpha(200.0, 200), 1.0);
+        assert_eq!(appear_alpha(500.0, 200), 1.0);
+        assert_eq!(appear_alpha(-10.0, 200), 0.0);
+        // No animation shows hints at full alpha right away
+        assert_eq!(appear_alpha(0.0, 0), 1.0);
+        let samples: Vec<f64> = (0..=20)
+            .map(|i| appear_alpha(i as f64 * 10.0, 200))
+            .collect();
+        assert!(samples.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn idle_expires_at_the_timeout() {
+        use std::time::Duration;
+        let timeout = Duration::from_millis(3000);
+        assert!(!idle_expired(Duration::from_millis(2999), timeout));
+        assert!(idle_expired(Duration::from_millis(3000), timeout));
+        assert!(idle_expired(Duration::from_millis(4000), timeout));
+        // A zero timeout would close the overlay at once, which is why
+        // `auto_dismiss_ms` 0 doesn't start the timer at all
+        assert!(idle_expired(Duration::ZERO, Duration::ZERO));
+    }
+
+    #[test]
+    fn alphabet_cycle_relabels_the_cached_children() {
+        let children: Vec<Child> = (0..3)
+            .map(|i| Child {
+                absolute_x: i * 20,
+                absolute_y: 0,
+                width: 10,
+                height: 10,
+                role: None,
+                accessible_path: None,
+                name: None,
+                source: None,
+            })
+            .collect();
+        let alphabets = [
+            ("default".to_string(), "asdf".to_string()),
+            ("numbers".to_string(), "123".to_string()),
+        ];
+        let idx = Cell::new(0);
+        let no_prefixes = std::collections::HashMap::new();
+        let targets = |hints: &HintMap| {
+            let mut targets: Vec<_> = hints.values().map(|c| c.absolute_x).collect();
+            targets.sort();
+            targets
+        };
+
+        let numbers = next_alphabet_hints(&children, &alphabets, &idx, &[], &no_prefixes);
+        assert_eq!(idx.get(), 1);
+        assert!(
+            numbers
+                .keys()
+                .all(|label| label.chars().all(|c| "123".contains(c)))
+        );
+        assert_eq!(targets(&numbers), [0, 20, 40]);
+
+        // Back round to the main alphabet, over the same children
+        let letters = next_alphabet_hints(&children, &alphabets, &idx, &[], &no_prefixes);
+        assert_eq!(idx.get(), 0);
+        assert!(
+            letters
+                .keys()
+                .all(|label| label.chars().all(|c| "asdf".contains(c)))
+        );
+        assert_eq!(targets(&letters), [0, 20, 40]);
+    }
+
+    #[test]
+    fn hint_bounds_cover_the_origin_and_every_hint() {
+        let at = |(x, y, w, h)| crate::hints::Child {
+            absolute_x: x,
+            absolute_y: y,
+            width: w,
+            height: h,
+            role: None,
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        let hints: HintMap = [
+            ("a".to_string(), at((-200, 50, 100, 20))),
+            ("s".to_string(), at((900, 700, 50, 30))),
+        ]
+        .into();
+        assert_eq!(hint_bounds(&hints), (-200, 0, 1150, 730));
+        assert_eq!(hint_bounds(&HintMap::new()), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn keyval_char_prefers_aliases() {
+        let aliases = crate::config::HintsStyle::default().key_aliases;
+        // KP_0, and shifted or non-ASCII keys GTK already maps
+        assert_eq!(keyval_char(0xffb0, None, &aliases), Some('0'));
+        assert_eq!(keyval_char(0x41, Some('A'), &aliases), Some('A'));
+        assert_eq!(keyval_char(0xe9, Some('é'), &aliases), Some('é'));
+        // dead_acute types nothing unless aliased, and an alias wins over GTK
+        assert_eq!(keyval_char(0xfe51, None, &aliases), None);
+        let aliases = std::collections::HashMap::from([(0xfe51, 'ä'), (0x41, 'q')]);
+        assert_eq!(keyval_char(0xfe51, None, &aliases), Some('ä'));
+        assert_eq!(keyval_char(0x41, Some('A'), &aliases), Some('q'));
+    }
+
+    #[test]
+    #[cfg(feature = "atspi-backend")]
+    fn scroll_to_needs_the_modifier_and_a_path() {
+        use gdk::ModifierType as M;
+        let detected = Child {
+            absolute_x: 0,
+            absolute_y: 0,
+            width: 10,
+            height: 10,
+            role: None,
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        let element = Child {
+            accessible_path: Some("/org/a11y/atspi/accessible/5".into()),
+            ..detected.clone()
+        };
+        let alt = M::ALT_MASK.bits();
+        let path = Some("/org/a11y/atspi/accessible/5".to_string());
+        assert_eq!(scroll_to_target(&element, M::ALT_MASK, alt), path);
+        assert_eq!(
+            scroll_to_target(&element, M::ALT_MASK | M::SHIFT_MASK, alt),
+            path
+        );
+        assert_eq!(scroll_to_target(&element, M::CONTROL_MASK, alt), None);
+        // Disabled by default
+        assert_eq!(scroll_to_target(&element, M::ALT_MASK, 0), None);
+        // OpenCV hints have no path and fall through to a click
+        assert_eq!(scroll_to_target(&detected, M::ALT_MASK, alt), None);
+    }
+
+    #[test]
+    #[cfg(feature = "atspi-backend")]
+    fn scoping_hints_a_container_then_its_elements() {
+        let element = |x, role: &str| Child {
+            absolute_x: x,
+            absolute_y: 0,
+            width: 10,
+            height: 10,
+            role: Some(role.into()),
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        let container = Child {
+            accessible_path: Some("/org/a11y/atspi/accessible/3".into()),
+            ..element(0, "Panel")
+        };
+        assert_eq!(
+            scope_root(true, &container),
+            Some("/org/a11y/atspi/accessible/3".to_string())
+        );
+        assert_eq!(scope_root(false, &container), None);
+        assert_eq!(scope_root(true, &element(0, "Panel")), None);
+
+        let mut cfg = Config::default();
+        cfg.hints.only_roles = vec!["PushButton".into()];
+        let inside = vec![
+            element(0, "PushButton"),
+            element(40, "Label"),
+            element(80, "PushButton"),
+        ];
+        let (children, hints) = hint_scope(&cfg, inside, "as").unwrap();
+        assert_eq!(children.len(), 2);
+        let mut labels: Vec<_> = hints.keys().cloned().collect();
+        labels.sort();
+        assert_eq!(labels, ["a", "s"]);
+        assert!(hint_scope(&cfg, vec![element(0, "Label")], "as").is_none());
+    }
+
+    #[test]
+    fn input_grace_covers_only_the_start() {
+        use std::time::Duration;
+        let grace = Duration::from_millis(150);
+        assert!(in_input_grace(Some(Duration::from_millis(20)), grace));
+        assert!(!in_input_grace(Some(Duration::from_millis(150)), grace));
+        assert!(!in_input_grace(None, grace));
+        assert!(!in_input_grace(Some(Duration::ZERO), Duration::ZERO));
+    }
+
+    #[test]
+    fn selection_clicks_in_the_order_chosen() {
+        let element = |absolute_x, absolute_y, width, height| Child {
+            absolute_x,
+            absolute_y,
+            width,
+            height,
+            role: None,
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        let button = Child {
+            role: Some("PushButton".into()),
+            ..element(0, 0, 10, 10)
+        };
+        let hints: HintMap = [
+            ("a".to_string(), button),
+            ("s".to_string(), element(40, 0, 10, 10)),
+            ("d".to_string(), element(100, 50, 20, 20)),
+        ]
+        .into();
+        let offsets = std::collections::HashMap::from([("PushButton".to_string(), (0.0, 0.0))]);
+        let mut selected = Vec::new();
+        for label in ["d", "s", "gone", "a", "s"] {
+            toggle_selection(&mut selected, label);
+        }
+        // "s" was toggled off again; "gone" isn't on screen
+        assert_eq!(selected, ["d", "gone", "a"]);
+        assert_eq!(
+            selection_clicks(&selected, &hints, &offsets),
+            vec![(110, 60), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn plain_hint_on_a_scroll_pane_anchors_scrolling_at_its_center() {
+        use gdk::ModifierType as M;
+        let cfg = crate::config::MouseConfig::default();
+        let pane = Child {
+            absolute_x: 100,
+            absolute_y: 200,
+            width: 400,
+            height: 300,
+            role: Some("ScrollPane".into()),
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        let center = pane.click_point(&cfg.click_offsets);
+        assert_eq!(center, (300, 350));
+        assert_eq!(
+            scroll_anchor_for(&cfg, &pane, M::empty(), false, center),
+            Some(center)
+        );
+        // Modified hints keep their usual actions
+        assert_eq!(
+            scroll_anchor_for(&cfg, &pane, M::empty(), true, center),
+            None
+        );
+        assert_eq!(
+            scroll_anchor_for(&cfg, &pane, M::CONTROL_MASK, false, center),
+            None
+        );
+        let button = Child {
+            role: Some("PushButton".into()),
+            ..pane
+        };
+        assert_eq!(
+            scroll_anchor_for(&cfg, &button, M::empty(), false, center),
+            None
+        );
+    }
+
+    #[test]
+    fn selection_is_sent_as_one_sequence_of_clicks() {
+        let points = [(110, 60), (2000, 10), (0, 0)];
+        let output = |x: i32, _| Some(if x < 1920 { "DP-1" } else { "HDMI-A-1" }.to_string());
+        let Request::Sequence { steps } = selection_sequence(&points, output) else {
+            panic!("expected a Sequence");
+        };
+        let clicks: Vec<_> = steps
+            .iter()
+            .map(|step| match step {
+                Request::Click {
+                    x,
+                    y,
+                    button: 0,
+                    button_states,
+                    repeat: 1,
+                    absolute: true,
+                    move_first: true,
+                    output,
+                    settle,
+                } if button_states == &[1, 0] => (*x, *y, output.as_deref(), *settle),
+                other => panic!("unexpected step {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            clicks,
+            [
+                (110, 60, Some("DP-1"), true),
+                (2000, 10, Some("HDMI-A-1"), false),
+                (0, 0, Some("DP-1"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn fold_case_lowers_single_chars_only() {
+        assert_eq!(fold_case('A'), 'a');
+        assert_eq!(fold_case('Ä'), 'ä');
+        assert_eq!(fold_case('a'), 'a');
+        assert_eq!(fold_case(';'), ';');
+        // 'İ' lowers to two chars, so it is kept as typed
+        assert_eq!(fold_case('İ'), 'İ');
+    }
+
+    #[test]
+    fn tab_close_point_only_for_tabs() {
+        let tab = Child {
+            absolute_x: 100,
+            absolute_y: 10,
+            width: 200,
+            height: 30,
+            role: Some("PageTab".into()),
+            accessible_path: None,
+            name: None,
+            source: None,
+        };
+        assert_eq!(tab_close_point(&tab, (0.9, 0.5)), Some((279, 25)));
+        assert_eq!(tab_close_point(&tab, (0.0, 0.0)), Some((100, 10)));
+        let button = Child {
+            role: Some("PushButton".into()),
+            ..tab
+        };
+        assert_eq!(tab_close_point(&button, (0.9, 0.5)), None);
+    }
+
+    #[test]
+    fn suffix_button_after_a_complete_label() {
+        let suffixes = std::collections::HashMap::from([('r', 1), ('m', 2)]);
+        assert_eq!(suffix_button(&suffixes, true, 'r'), Some(1));
+        assert_eq!(suffix_button(&suffixes, true, '\r'), Some(0));
+        assert_eq!(suffix_button(&suffixes, true, 'x'), None);
+        assert_eq!(suffix_button(&suffixes, false, 'r'), None);
+        assert_eq!(
+            suffix_button(&std::collections::HashMap::new(), true, '\r'),
+            None
+        );
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "x11")]
+//! Xlib calls for the X11 overlay where GTK4 has no API, e.g. placing a window:
+//! GTK4 windows can't position themselves.
+use anyhow::{Result, anyhow};
+use gdk4_x11::x11::xlib;
+use gdk4_x11::{X11Display, X11Surface};
+use gtk4::prelude::*;
+
+/// Where to move an X window, sent as a ConfigureWindow request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveRequest {
+    pub xid: xlib::Window,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl MoveRequest {
+    /// Move window `xid` so its top-left sits at `origin` shifted by `offset`
+    /// (the configured `overlay_x_offset`/`overlay_y_offset`).
+    pub fn new(xid: xlib::Window, origin: (i32, i32), offset: (i32, i32)) -> Self {
+        Self {
+            xid,
+            x: origin.0 + offset.0,
+            y: origin.1 + offset.1,
+        }
+    }
+}
+
+/// Send `request` on the connection `surface` belongs to and flush it.
+pub fn move_surface(surface: &X11Surface, request: MoveRequest) -> Result<()> {
+    let display = surface
+        .display()
+        .downcast::<X11Display>()
+        .map_err(|_| anyhow!("surface is not on an X11 display"))?;
+    // SAFETY: the Display pointer stays valid while `display` is alive, and the
+    // window belongs to that connection
+    unsafe {
+        let xdisplay = display.xdisplay();
+        if xdisplay.is_null() {
+            return Err(anyhow!("no Xlib display for {display:?}"));
+        }
+        xlib::XMoveWindow(xdisplay, request.xid, request.x, request.y);
+        xlib::XFlush(xdisplay);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_request_adds_the_overlay_offset() {
+        assert_eq!(
+            MoveRequest::new(0x2a00003, (1920, 40), (5, -10)),
+            MoveRequest {
+                xid: 0x2a00003,
+                x: 1925,
+                y: 30,
+            }
+        );
+        assert_eq!(MoveRequest::new(7, (-1280, 0), (0, 0)).x, -1280);
+    }
+}
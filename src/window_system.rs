@@ -84,6 +84,120 @@ impl WindowSystem {
         }
     }
 
+    /// Geometry of the focused window, asking the compositor first on Wayland.
+    pub fn active_window_geometry(&self) -> Option<(i32, i32, i32, i32)> {
+        if self.window_system_type == WindowSystemType::Wayland {
+            self.get_active_window_geometry_wayland()
+                .or_else(|| self.get_active_window_geometry_x11())
+        } else {
+            self.get_active_window_geometry_x11()
+        }
+    }
+
+    /// Screen position of the mouse pointer, from `hyprctl cursorpos` on Hyprland
+    /// or `xdotool` on X11 and XWayland. Sway doesn't tell.
+    pub fn cursor_position(&self) -> Option<(i32, i32)> {
+        if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+            let output = Command::new("hyprctl").arg("cursorpos").output().ok()?;
+            let text = String::from_utf8_lossy(&output.stdout);
+            let (x, y) = text.trim().split_once(',')?;
+            return Some((x.trim().parse().ok()?, y.trim().parse().ok()?));
+        }
+
+        let output = Command::new("xdotool")
+            .args(["getmouselocation", "--shell"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let content = String::from_utf8_lossy(&output.stdout);
+        let value = |key: &str| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.parse::<i32>().ok())
+        };
+        Some((value("X=")?, value("Y=")?))
+    }
+
+    /// Identifier of the focused window (Hyprland address, Sway node id or X11
+    /// window id), used to notice focus moving while hints are collected.
+    pub fn active_window_id(&self) -> Option<String> {
+        if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+            let output = Command::new("hyprctl")
+                .args(["activewindow", "-j"])
+                .output()
+                .ok()?;
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+            return json.get("address")?.as_str().map(str::to_string);
+        } else if std::env::var("SWAYSOCK").is_ok() {
+            let output = Command::new("swaymsg")
+                .args(["-t", "get_tree"])
+                .output()
+                .ok()?;
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+            return find_focused_id(&json).map(|id| id.to_string());
+        }
+
+        let output = Command::new("xdotool")
+            .arg("getactivewindow")
+            .output()
+            .ok()?;
+        let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !id.is_empty()).then_some(id)
+    }
+
+    /// Name and logical geometry of each active output, from Hyprland or Sway.
+    /// Empty when neither is running.
+    pub fn outputs(&self) -> Vec<(String, (i32, i32, i32, i32))> {
+        let query = if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+            Command::new("hyprctl").args(["monitors", "-j"]).output()
+        } else if std::env::var("SWAYSOCK").is_ok() {
+            Command::new("swaymsg").args(["-t", "get_outputs"]).output()
+        } else {
+            return Vec::new();
+        };
+        let Some(json) = query
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| serde_json::from_slice::<serde_json::Value>(&output.stdout).ok())
+        else {
+            return Vec::new();
+        };
+
+        let output_geometry =
+            |output: &serde_json::Value| -> Option<(String, (i32, i32, i32, i32))> {
+                let name = output.get("name")?.as_str()?.to_string();
+                if let Some(rect) = output.get("rect") {
+                    // Sway: logical rect; inactive outputs have no mode
+                    if output.get("active").and_then(|v| v.as_bool()) == Some(false) {
+                        return None;
+                    }
+                    let field = |key: &str| rect.get(key)?.as_i64().map(|v| v as i32);
+                    return Some((
+                        name,
+                        (field("x")?, field("y")?, field("width")?, field("height")?),
+                    ));
+                }
+                // Hyprland: position is logical, size is in device pixels
+                let scale = output.get("scale").and_then(|v| v.as_f64()).unwrap_or(1.0);
+                let field = |key: &str| output.get(key)?.as_i64().map(|v| v as i32);
+                let logical = |v: i32| (v as f64 / scale).round() as i32;
+                Some((
+                    name,
+                    (
+                        field("x")?,
+                        field("y")?,
+                        logical(field("width")?),
+                        logical(field("height")?),
+                    ),
+                ))
+            };
+        json.as_array()
+            .map(|outputs| outputs.iter().filter_map(output_geometry).collect())
+            .unwrap_or_default()
+    }
+
     pub fn get_active_window_geometry_wayland(&self) -> Option<(i32, i32, i32, i32)> {
         if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
             return self.get_hyprland_active_window();
@@ -107,9 +221,9 @@ impl WindowSystem {
         let at = json.get("at")?.as_array()?;
         let size = json.get("size")?.as_array()?;
 
-        let x = at.get(0)?.as_i64()? as i32;
+        let x = at.first()?.as_i64()? as i32;
         let y = at.get(1)?.as_i64()? as i32;
-        let w = size.get(0)?.as_i64()? as i32;
+        let w = size.first()?.as_i64()? as i32;
         let h = size.get(1)?.as_i64()? as i32;
 
         Some((x, y, w, h))
@@ -129,14 +243,14 @@ impl WindowSystem {
 
         // Recursive search for focused node
         fn find_focused(node: &serde_json::Value) -> Option<(i32, i32, i32, i32)> {
-            if node.get("focused").and_then(|v| v.as_bool()) == Some(true) {
-                if let Some(rect) = node.get("rect") {
-                    let x = rect.get("x")?.as_i64()? as i32;
-                    let y = rect.get("y")?.as_i64()? as i32;
-                    let w = rect.get("width")?.as_i64()? as i32;
-                    let h = rect.get("height")?.as_i64()? as i32;
-                    return Some((x, y, w, h));
-                }
+            if node.get("focused").and_then(|v| v.as_bool()) == Some(true)
+                && let Some(rect) = node.get("rect")
+            {
+                let x = rect.get("x")?.as_i64()? as i32;
+                let y = rect.get("y")?.as_i64()? as i32;
+                let w = rect.get("width")?.as_i64()? as i32;
+                let h = rect.get("height")?.as_i64()? as i32;
+                return Some((x, y, w, h));
             }
 
             if let Some(nodes) = node.get("nodes").and_then(|v| v.as_array()) {
@@ -163,6 +277,24 @@ impl WindowSystem {
     }
 }
 
+/// Whether focus moved from the window `before` to a different one `after`.
+/// Unknown ids on either side don't count as a change.
+pub fn focus_changed(before: Option<&str>, after: Option<&str>) -> bool {
+    matches!((before, after), (Some(before), Some(after)) if before != after)
+}
+
+/// Id of the focused node in a `swaymsg -t get_tree` dump.
+fn find_focused_id(node: &serde_json::Value) -> Option<i64> {
+    if node.get("focused").and_then(|v| v.as_bool()) == Some(true) {
+        return node.get("id")?.as_i64();
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key)?.as_array())
+        .flatten()
+        .find_map(find_focused_id)
+}
+
 fn detect_wayland_wm() -> Option<String> {
     let pattern = Regex::new(r"(?i)^(sway|hyprland|plasmashell|kwin_wayland|wayfire)$").ok()?;
     let output = Command::new("ps")
@@ -177,3 +309,16 @@ fn detect_wayland_wm() -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_changed_needs_two_different_known_windows() {
+        assert!(focus_changed(Some("0x1"), Some("0x2")));
+        assert!(!focus_changed(Some("0x1"), Some("0x1")));
+        assert!(!focus_changed(None, Some("0x2")));
+        assert!(!focus_changed(Some("0x1"), None));
+    }
+}